@@ -0,0 +1,133 @@
+//! Block-letter font for big terminal headings
+
+/// 5 pixel high glyphs, `#` is a set pixel
+const FONT: &[(char, [&str; 5])] = &[
+    ('A', [".##.", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "###."]),
+    ('C', [".###", "#...", "#...", "#...", ".###"]),
+    ('D', ["###.", "#..#", "#..#", "#..#", "###."]),
+    ('E', ["####", "#...", "###.", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#..."]),
+    ('G', [".###", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..##", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "####"]),
+    ('M', ["#...#", "##.##", "#.#.#", "#...#", "#...#"]),
+    ('N', ["#...#", "##..#", "#.#.#", "#..##", "#...#"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "###.", "#...", "#..."]),
+    ('Q', [".##.", "#..#", "#..#", "#.##", ".###"]),
+    ('R', ["###.", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", ".##.", "...#", "###."]),
+    ('T', ["#####", "..#..", "..#..", "..#..", "..#.."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('V', ["#...#", "#...#", "#...#", ".#.#.", "..#.."]),
+    ('W', ["#...#", "#...#", "#.#.#", "##.##", "#...#"]),
+    ('X', ["#...#", ".#.#.", "..#..", ".#.#.", "#...#"]),
+    ('Y', ["#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", ".##.", "#...", "####"]),
+    ('0', [".##.", "#.##", "##.#", "#..#", ".##."]),
+    ('1', [".#.", "##.", ".#.", ".#.", "###"]),
+    ('2', ["###.", "...#", ".##.", "#...", "####"]),
+    ('3', ["###.", "...#", ".##.", "...#", "###."]),
+    ('4', ["#..#", "#..#", "####", "...#", "...#"]),
+    ('5', ["####", "#...", "###.", "...#", "###."]),
+    ('6', [".##.", "#...", "###.", "#..#", ".##."]),
+    ('7', ["####", "...#", "..#.", ".#..", ".#.."]),
+    ('8', [".##.", "#..#", ".##.", "#..#", ".##."]),
+    ('9', [".##.", "#..#", ".###", "...#", ".##."]),
+    (' ', ["..", "..", "..", "..", ".."]),
+    ('-', ["...", "...", "###", "...", "..."]),
+    ('+', ["...", ".#.", "###", ".#.", "..."]),
+    ('.', [".", ".", ".", ".", "#"]),
+    (',', ["..", "..", "..", ".#", "#."]),
+    ('!', ["#", "#", "#", ".", "#"]),
+    ('?', ["###.", "...#", ".##.", "....", ".#.."]),
+    (':', [".", "#", ".", "#", "."]),
+    ('\'', ["#", "#", ".", ".", "."]),
+    ('(', [".#", "#.", "#.", "#.", ".#"]),
+    (')', ["#.", ".#", ".#", ".#", "#."]),
+    ('/', ["....#", "...#.", "..#..", ".#...", "#...."]),
+];
+
+fn glyph(c: char) -> Option<&'static [&'static str; 5]> {
+    let c = c.to_ascii_uppercase();
+    FONT.iter().find(|(g, _)| *g == c).map(|(_, rows)| rows)
+}
+
+/// Render text in the block font: three rows of unicode half blocks, or five
+/// rows of `#` without unicode. Returns None if a character has no glyph.
+pub fn render(text: &str, unicode: bool) -> Option<Vec<String>> {
+    let glyphs = text.chars().map(glyph).collect::<Option<Vec<_>>>()?;
+    if glyphs.is_empty() {
+        return None;
+    }
+
+    // Join the glyphs into five pixel rows with one blank column between letters
+    let pixels: Vec<Vec<bool>> = (0..5)
+        .map(|row| {
+            let mut line = Vec::new();
+            for (i, g) in glyphs.iter().enumerate() {
+                if i > 0 {
+                    line.push(false);
+                }
+                line.extend(g[row].chars().map(|c| c == '#'));
+            }
+            line
+        })
+        .collect();
+
+    let rows: Vec<String> = if unicode {
+        let blank = vec![false; pixels[0].len()];
+        pixels
+            .chunks(2)
+            .map(|pair| {
+                let lower = pair.get(1).unwrap_or(&blank);
+                pair[0]
+                    .iter()
+                    .zip(lower)
+                    .map(|(&top, &bottom)| match (top, bottom) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    })
+                    .collect::<String>()
+            })
+            .collect()
+    } else {
+        pixels
+            .iter()
+            .map(|row| row.iter().map(|&p| if p { '#' } else { ' ' }).collect::<String>())
+            .collect()
+    };
+    Some(rows.into_iter().map(|r| r.trim_end().to_string()).collect())
+}
+
+/// Width in cells of `text` rendered with [`render`]
+pub fn width(text: &str) -> Option<usize> {
+    let glyphs = text.chars().map(glyph).collect::<Option<Vec<_>>>()?;
+    Some(glyphs.iter().map(|g| g[0].len()).sum::<usize>() + glyphs.len().saturating_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_half_blocks() {
+        let rows = render("Hi", true).unwrap();
+        assert_eq!(rows, vec!["█  █ ▀█▀", "█▀▀█  █", "▀  ▀ ▀▀▀"]);
+        assert_eq!(width("Hi"), Some(8));
+    }
+
+    #[test]
+    fn test_ascii_and_unsupported() {
+        let rows = render("L", false).unwrap();
+        assert_eq!(rows, vec!["#", "#", "#", "#", "####"]);
+        assert!(render("Ünïcode", true).is_none());
+        assert!(width("日本").is_none());
+    }
+}
@@ -0,0 +1,125 @@
+//! Settings file (`mdview.ini`) shared by the standalone viewer and the Lister plugin
+
+use std::path::{Path, PathBuf};
+
+use crate::link_policy::LinkPolicy;
+use crate::settings::{Change, RemoteContent, Settings};
+
+pub const CONFIG_FILE_NAME: &str = "mdview.ini";
+
+/// Settings read from mdview.ini. Documents and command line options override these.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub settings: Settings,
+    /// What clicks on links may do, from the `[links]` section
+    pub links: LinkPolicy,
+    /// Reload documents when they change on disk, `reload` in `[view]`
+    pub live_reload: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { settings: Settings::default(), links: LinkPolicy::default(), live_reload: true }
+    }
+}
+
+impl Config {
+    /// Parse INI text. Unknown sections and keys are ignored.
+    pub fn parse(text: &str) -> Self {
+        let mut config = Config::default();
+        let mut section = String::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_ascii_lowercase();
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            match section.as_str() {
+                "extensions" => {
+                    if let Some(enabled) = parse_bool(value) {
+                        config.settings.extensions.set(key, enabled);
+                    }
+                }
+                "view" if key.eq_ignore_ascii_case("css") => config.settings.apply(&[Change::Css(PathBuf::from(value))]),
+                "view" if key.eq_ignore_ascii_case("remote") => {
+                    config.settings.apply(RemoteContent::parse(value).map(Change::Remote).as_slice())
+                }
+                "view" if key.eq_ignore_ascii_case("reload") => {
+                    if let Some(enabled) = parse_bool(value) {
+                        config.live_reload = enabled;
+                    }
+                }
+                "view" => config.settings.apply(Change::parse(key, value).as_slice()),
+                "links" => {
+                    config.links.set(key, value);
+                }
+                _ => {}
+            }
+        }
+        config
+    }
+
+    /// Load a settings file. A missing or unreadable file gives the defaults.
+    /// A relative stylesheet path is relative to the settings file.
+    pub fn load(path: &Path) -> Self {
+        let mut config: Self = std::fs::read_to_string(path)
+            .map(|text| Self::parse(text.trim_start_matches('\u{feff}')))
+            .unwrap_or_default();
+        if let (Some(css), Some(dir)) = (config.settings.css.as_mut(), path.parent()) {
+            *css = dir.join(&*css);
+        }
+        config
+    }
+}
+
+/// `%APPDATA%\MDView\mdview.ini`
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("MDView").join(CONFIG_FILE_NAME))
+}
+
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_extensions() {
+        let config = Config::parse(
+            "; MDView settings\n[Extensions]\nmath = yes\nsmart_punctuation=1\nwikilinks = off\nunknown = true\n\n[other]\ngfm = true\n\n[view]\ntheme = dark\nwidth = 100\nCSS = my.css\nremote = block\nreload = off\n\n[links]\nexecutable = confirm\nhttp = maybe\n",
+        );
+        let extensions = &config.settings.extensions;
+        assert!(extensions.math);
+        assert!(extensions.smart_punctuation);
+        assert!(!extensions.wikilinks);
+        assert!(!extensions.gfm);
+        assert_eq!(config.settings.theme, crate::settings::Theme::Dark);
+        assert_eq!(config.settings.width, Some(100));
+        assert_eq!(config.settings.css, Some(PathBuf::from("my.css")));
+        assert_eq!(config.settings.remote, RemoteContent::Block);
+        assert!(!config.live_reload);
+        assert_eq!(config.links, LinkPolicy { executable: crate::link_policy::Action::Confirm, ..LinkPolicy::default() });
+    }
+
+    #[test]
+    fn test_missing_file_gives_defaults() {
+        let config = Config::load(Path::new("does-not-exist/mdview.ini"));
+        assert_eq!(config.settings, Settings::default());
+        assert!(config.live_reload);
+    }
+}
@@ -0,0 +1,669 @@
+//! YAML (`---`) and TOML (`+++`) front matter at the top of a document
+
+use crate::markdown::escape_html;
+
+/// A front matter value. Tables keep the order of the document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// Kept as written so it round-trips to JSON unchanged
+    Number(String),
+    String(String),
+    List(Vec<Value>),
+    Table(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) | Value::Number(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Table(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Short single line form for tables and plain text
+    pub fn display(&self) -> String {
+        match self {
+            Value::Null => String::new(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(s) | Value::String(s) => s.clone(),
+            Value::List(items) => items.iter().map(Value::display).collect::<Vec<_>>().join(", "),
+            Value::Table(fields) => {
+                let inner: Vec<String> = fields.iter().map(|(k, v)| format!("{}: {}", k, v.display())).collect();
+                format!("{{{}}}", inner.join(", "))
+            }
+        }
+    }
+
+    fn write_json(&self, out: &mut String, indent: usize) {
+        let pad = "  ".repeat(indent + 1);
+        match self {
+            Value::Null => out.push_str("null"),
+            Value::Bool(b) => out.push_str(&b.to_string()),
+            Value::Number(n) if is_json_number(n) => out.push_str(n),
+            Value::Number(s) | Value::String(s) => push_json_string(out, s),
+            Value::List(items) if items.is_empty() => out.push_str("[]"),
+            Value::List(items) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(&pad);
+                    item.write_json(out, indent + 1);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push(']');
+            }
+            Value::Table(fields) if fields.is_empty() => out.push_str("{}"),
+            Value::Table(fields) => {
+                out.push_str("{\n");
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(&pad);
+                    push_json_string(out, key);
+                    out.push_str(": ");
+                    value.write_json(out, indent + 1);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push('}');
+            }
+        }
+    }
+
+    fn write_html(&self, out: &mut String) {
+        match self {
+            Value::Table(fields) => {
+                out.push_str("<table>");
+                for (key, value) in fields {
+                    out.push_str(&format!("<tr><th>{}</th><td>", escape_html(key)));
+                    value.write_html(out);
+                    out.push_str("</td></tr>");
+                }
+                out.push_str("</table>");
+            }
+            Value::List(items) if items.iter().any(|v| matches!(v, Value::Table(_))) => {
+                out.push_str("<ul>");
+                for item in items {
+                    out.push_str("<li>");
+                    item.write_html(out);
+                    out.push_str("</li>");
+                }
+                out.push_str("</ul>");
+            }
+            _ => out.push_str(&escape_html(&self.display())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Yaml,
+    Toml,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontMatter {
+    /// Always a [`Value::Table`]
+    pub data: Value,
+}
+
+impl FrontMatter {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.data.get(key)
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.get("title").and_then(Value::as_str).filter(|t| !t.trim().is_empty())
+    }
+
+    /// Pretty printed JSON object
+    #[allow(dead_code)]
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.data.write_json(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    /// Collapsed metadata table shown above the document
+    pub fn to_html(&self) -> String {
+        let mut out = String::from("<details class=\"front-matter\"><summary>Metadata</summary>");
+        self.data.write_html(&mut out);
+        out.push_str("</details>\n");
+        out
+    }
+
+    /// Top level fields as (key, value) text, nested tables as `parent.child`
+    #[allow(dead_code)]
+    pub fn flatten(&self) -> Vec<(String, String)> {
+        fn walk(prefix: &str, value: &Value, out: &mut Vec<(String, String)>) {
+            match value {
+                Value::Table(fields) => {
+                    for (key, value) in fields {
+                        let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                        walk(&key, value, out);
+                    }
+                }
+                _ => out.push((prefix.to_string(), value.display())),
+            }
+        }
+        let mut out = Vec::new();
+        walk("", &self.data, &mut out);
+        out
+    }
+}
+
+/// Separate front matter from the document body. Front matter must start on the
+/// first line, be closed and hold a mapping of keys to values; anything else,
+/// like a document that opens with a horizontal rule, is left to the markdown
+/// parser.
+pub fn split(markdown: &str) -> (Option<FrontMatter>, &str) {
+    let text = markdown.strip_prefix('\u{feff}').unwrap_or(markdown);
+    let first_end = text.find('\n').map_or(text.len(), |i| i + 1);
+    let format = match text[..first_end].trim_end() {
+        "---" => Format::Yaml,
+        "+++" => Format::Toml,
+        _ => return (None, markdown),
+    };
+
+    let mut offset = first_end;
+    for line in text[first_end..].split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        let closes = match format {
+            Format::Yaml => trimmed == "---" || trimmed == "...",
+            Format::Toml => trimmed == "+++",
+        };
+        if closes {
+            let source = &text[first_end..offset];
+            let data = match format {
+                Format::Yaml => parse_yaml(source),
+                Format::Toml => parse_toml(source),
+            };
+            return match data {
+                Some(data) => (Some(FrontMatter { data }), &text[offset + line.len()..]),
+                None => (None, markdown),
+            };
+        }
+        offset += line.len();
+    }
+    (None, markdown)
+}
+
+// ---------------------------------------------------------------------------
+// YAML: block mappings and sequences, flow collections and plain or quoted
+// scalars. Anchors, tags and multi-document streams are not supported.
+// ---------------------------------------------------------------------------
+
+/// `None` unless the source is a mapping: every line at the top level is a key
+fn parse_yaml(source: &str) -> Option<Value> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut raw = source.lines().peekable();
+    while let Some(line) = raw.next() {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - content.len();
+        // Block scalars (`|` and `>`) are read here, since they keep their lines
+        if let Some((key, style)) = block_scalar_header(content) {
+            let mut text_lines = Vec::new();
+            while let Some(next) = raw.peek() {
+                let next_indent = next.len() - next.trim_start().len();
+                if !next.trim().is_empty() && next_indent <= indent {
+                    break;
+                }
+                text_lines.push(next.trim().to_string());
+                raw.next();
+            }
+            let joined = if style == '|' { text_lines.join("\n") } else { text_lines.join(" ") };
+            lines.push((indent, format!("{}: {}", key, quote(joined.trim_end()))));
+            continue;
+        }
+        lines.push((indent, content.to_string()));
+    }
+
+    // A sequence may sit at the indentation of its key, but not come first
+    let (indent, first) = lines.first()?;
+    let indent = *indent;
+    let is_mapping = !first.starts_with('-')
+        && lines.iter().all(|(i, text)| {
+            *i > indent || (*i == indent && (text == "-" || text.starts_with("- ") || split_yaml_key(text).is_some()))
+        });
+    if !is_mapping {
+        return None;
+    }
+    let mut pos = 0;
+    match parse_yaml_block(&mut lines, &mut pos, indent) {
+        table @ Value::Table(_) => Some(table),
+        _ => None,
+    }
+}
+
+fn block_scalar_header(content: &str) -> Option<(&str, char)> {
+    let (key, value) = split_yaml_key(content)?;
+    let value = value.trim();
+    let style = value.chars().next().filter(|c| *c == '|' || *c == '>')?;
+    value[1..].chars().all(|c| c == '-' || c == '+' || c.is_ascii_digit()).then_some((key, style))
+}
+
+fn parse_yaml_block(lines: &mut [(usize, String)], pos: &mut usize, indent: usize) -> Value {
+    let is_item = |text: &str| text == "-" || text.starts_with("- ");
+
+    if *pos < lines.len() && is_item(&lines[*pos].1) {
+        let mut items = Vec::new();
+        while *pos < lines.len() && lines[*pos].0 == indent && is_item(&lines[*pos].1) {
+            let rest = lines[*pos].1[1..].trim_start().to_string();
+            if rest.is_empty() {
+                *pos += 1;
+                items.push(nested_yaml(lines, pos, indent));
+            } else if split_yaml_key(&rest).is_some() {
+                // `- key: value` starts a table; continue it as if the dash were a space
+                let item_indent = indent + lines[*pos].1.len() - rest.len();
+                lines[*pos] = (item_indent, rest);
+                items.push(parse_yaml_block(lines, pos, item_indent));
+            } else {
+                *pos += 1;
+                items.push(parse_yaml_scalar(&rest));
+            }
+        }
+        return Value::List(items);
+    }
+
+    let mut fields = Vec::new();
+    while *pos < lines.len() && lines[*pos].0 == indent && !is_item(&lines[*pos].1) {
+        let line = lines[*pos].1.clone();
+        *pos += 1;
+        let Some((key, value)) = split_yaml_key(&line) else {
+            continue;
+        };
+        let value = strip_comment(value).trim();
+        let value = if value.is_empty() { nested_yaml(lines, pos, indent) } else { parse_yaml_scalar(value) };
+        fields.push((unquote(key), value));
+    }
+    // Skip lines this block can't hold (bad indentation) so parsing always advances
+    while *pos < lines.len() && lines[*pos].0 > indent {
+        *pos += 1;
+    }
+    Value::Table(fields)
+}
+
+/// The value of a key or item with nothing after the colon or dash
+fn nested_yaml(lines: &mut [(usize, String)], pos: &mut usize, indent: usize) -> Value {
+    match lines.get(*pos) {
+        Some((next, _)) if *next > indent => {
+            let next = *next;
+            parse_yaml_block(lines, pos, next)
+        }
+        // A sequence may sit at the same indentation as its key
+        Some((next, text)) if *next == indent && (text == "-" || text.starts_with("- ")) => parse_yaml_block(lines, pos, indent),
+        _ => Value::Null,
+    }
+}
+
+/// Split `key: value` at the first colon followed by a space (or at the end) outside quotes
+fn split_yaml_key(line: &str) -> Option<(&str, &str)> {
+    if line.starts_with('[') || line.starts_with('{') {
+        return None;
+    }
+    let mut quote_char = None;
+    for (i, c) in line.char_indices() {
+        match (quote_char, c) {
+            (None, '"' | '\'') if i == 0 => quote_char = Some(c),
+            (Some(q), c) if c == q => quote_char = None,
+            (None, ':') => {
+                let rest = &line[i + 1..];
+                if rest.is_empty() || rest.starts_with(' ') || rest.starts_with('\t') {
+                    return Some((&line[..i], rest));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_yaml_scalar(text: &str) -> Value {
+    let text = strip_comment(text).trim();
+    if text.starts_with('[') || text.starts_with('{') {
+        return parse_flow(text, parse_yaml_scalar, ':');
+    }
+    if text.starts_with('"') || text.starts_with('\'') {
+        return Value::String(unquote(text));
+    }
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => Value::Null,
+        "true" | "True" | "TRUE" => Value::Bool(true),
+        "false" | "False" | "FALSE" => Value::Bool(false),
+        _ if text.parse::<f64>().is_ok() => Value::Number(text.to_string()),
+        _ => Value::String(text.to_string()),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// TOML: key/value pairs, dotted keys, [tables], [[arrays of tables]], arrays
+// and inline tables, which may span lines.
+// ---------------------------------------------------------------------------
+
+/// `None` unless every line is a table header or a `key = value` pair
+fn parse_toml(source: &str) -> Option<Value> {
+    let mut root: Vec<(String, Value)> = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut lines = source.lines();
+
+    while let Some(line) = lines.next() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("[[").and_then(|l| l.strip_suffix("]]")) {
+            path = split_toml_key(name);
+            if let Some((last, parents)) = path.split_last() {
+                let table = toml_table(&mut root, parents);
+                let list = entry(table, last, || Value::List(Vec::new()));
+                if let Value::List(items) = list {
+                    items.push(Value::Table(Vec::new()));
+                }
+            }
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            path = split_toml_key(name);
+            toml_table(&mut root, &path);
+            continue;
+        }
+        let (key, value) = line.split_once('=')?;
+
+        // Arrays, inline tables and multi-line strings continue until they are closed
+        let mut value = value.trim().to_string();
+        while !toml_value_complete(&value) {
+            let Some(next) = lines.next() else { break };
+            value.push('\n');
+            value.push_str(strip_comment(next).trim());
+        }
+
+        let mut key = split_toml_key(key);
+        let last = key.pop().filter(|k| !k.is_empty())?;
+        let mut full = path.clone();
+        full.extend(key);
+        let table = toml_table(&mut root, &full);
+        *entry(table, &last, || Value::Null) = parse_toml_value(&value);
+    }
+    (!root.is_empty()).then_some(Value::Table(root))
+}
+
+/// The table at `path`, created as needed. The last table of an array of tables is used.
+fn toml_table<'a>(root: &'a mut Vec<(String, Value)>, path: &[String]) -> &'a mut Vec<(String, Value)> {
+    let mut table = root;
+    for name in path {
+        let value = entry(table, name, || Value::Table(Vec::new()));
+        match value {
+            Value::Table(_) => {}
+            Value::List(items) if matches!(items.last(), Some(Value::Table(_))) => {}
+            Value::List(items) => items.push(Value::Table(Vec::new())),
+            _ => *value = Value::Table(Vec::new()),
+        }
+        table = match value {
+            Value::Table(fields) => fields,
+            Value::List(items) => match items.last_mut() {
+                Some(Value::Table(fields)) => fields,
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+    }
+    table
+}
+
+fn entry<'a>(table: &'a mut Vec<(String, Value)>, key: &str, default: impl FnOnce() -> Value) -> &'a mut Value {
+    let index = match table.iter().position(|(k, _)| k == key) {
+        Some(index) => index,
+        None => {
+            table.push((key.to_string(), default()));
+            table.len() - 1
+        }
+    };
+    &mut table[index].1
+}
+
+fn split_toml_key(key: &str) -> Vec<String> {
+    split_top_level(key, '.').iter().map(|part| unquote(part.trim())).collect()
+}
+
+fn toml_value_complete(value: &str) -> bool {
+    if let Some(rest) = value.strip_prefix("\"\"\"") {
+        return rest.contains("\"\"\"");
+    }
+    if let Some(rest) = value.strip_prefix("'''") {
+        return rest.contains("'''");
+    }
+    let mut depth = 0i32;
+    let mut quote_char = None;
+    for c in value.chars() {
+        match (quote_char, c) {
+            (None, '"' | '\'') => quote_char = Some(c),
+            (Some(q), c) if c == q => quote_char = None,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+fn parse_toml_value(text: &str) -> Value {
+    let text = text.trim();
+    for delimiter in ["\"\"\"", "'''"] {
+        if let Some(inner) = text.strip_prefix(delimiter).and_then(|t| t.strip_suffix(delimiter)) {
+            return Value::String(inner.strip_prefix('\n').unwrap_or(inner).to_string());
+        }
+    }
+    if text.starts_with('[') || text.starts_with('{') {
+        return parse_flow(text, parse_toml_value, '=');
+    }
+    if text.starts_with('"') || text.starts_with('\'') {
+        return Value::String(unquote(text));
+    }
+    match text {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ if text.replace('_', "").parse::<f64>().is_ok() => Value::Number(text.replace('_', "")),
+        // Dates and times stay strings
+        _ => Value::String(text.to_string()),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Shared helpers
+// ---------------------------------------------------------------------------
+
+/// `[a, b]` or `{key: value}` (`key = value` in TOML)
+fn parse_flow(text: &str, scalar: fn(&str) -> Value, separator: char) -> Value {
+    let inner = &text[1..];
+    let close = if text.starts_with('[') { ']' } else { '}' };
+    let inner = inner.trim_end().strip_suffix(close).unwrap_or(inner);
+    let parts = split_top_level(inner, ',');
+    let parts = parts.iter().map(|p| p.trim()).filter(|p| !p.is_empty());
+
+    if close == ']' {
+        return Value::List(parts.map(scalar).collect());
+    }
+    Value::Table(
+        parts
+            .filter_map(|part| {
+                let key = split_top_level(part, separator)[0];
+                let value = part.get(key.len() + 1..)?;
+                Some((unquote(key), scalar(value.trim())))
+            })
+            .collect(),
+    )
+}
+
+/// Split at `separator` outside quotes and brackets
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quote_char = None;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match (quote_char, c) {
+            (None, '"' | '\'') => quote_char = Some(c),
+            (Some(q), c) if c == q && !text[..i].ends_with('\\') => quote_char = None,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            (None, c) if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Remove a `#` comment that is outside quotes and preceded by whitespace
+fn strip_comment(text: &str) -> &str {
+    let mut quote_char = None;
+    for (i, c) in text.char_indices() {
+        match (quote_char, c) {
+            (None, '"' | '\'') => quote_char = Some(c),
+            (Some(q), c) if c == q => quote_char = None,
+            (None, '#') if i == 0 || text[..i].ends_with([' ', '\t']) => return &text[..i],
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Remove surrounding quotes. Double quoted strings have their escapes resolved.
+fn unquote(text: &str) -> String {
+    let text = text.trim();
+    if let Some(inner) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+        return inner.replace("''", "'");
+    }
+    let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) else {
+        return text.to_string();
+    };
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('u') => {
+                let code: String = chars.by_ref().take(4).collect();
+                out.extend(u32::from_str_radix(&code, 16).ok().and_then(char::from_u32));
+            }
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn quote(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+fn is_json_number(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+    digits.starts_with(|c: char| c.is_ascii_digit()) && !leading_zero && text.parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+fn push_json_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yaml_front_matter() {
+        let md = "---\ntitle: \"My: Doc\"\ntags: [rust, markdown]\ndraft: false\nversion: 1.5\nauthor:\n  name: Remko # maintainer\n  email: r@example.com\nitems:\n- one\n- name: two\n  size: 2\nnotes: |\n  line one\n  line two\n---\n# Body\n";
+        let (front, body) = split(md);
+        let front = front.unwrap();
+        assert_eq!(body, "# Body\n");
+        assert_eq!(front.title(), Some("My: Doc"));
+        assert_eq!(front.get("draft"), Some(&Value::Bool(false)));
+        assert_eq!(front.get("tags").unwrap().display(), "rust, markdown");
+        assert_eq!(front.get("author").unwrap().get("name").unwrap().as_str(), Some("Remko"));
+        assert!(matches!(front.get("items"), Some(Value::List(items)) if items.len() == 2));
+        assert_eq!(front.get("notes").unwrap().as_str(), Some("line one\nline two"));
+
+        let json = front.to_json();
+        assert!(json.contains("\"version\": 1.5"));
+        assert!(json.contains("\"tags\": [\n    \"rust\",\n    \"markdown\"\n  ]"));
+        assert!(json.contains("\"notes\": \"line one\\nline two\""));
+        assert!(front.flatten().contains(&("author.email".to_string(), "r@example.com".to_string())));
+        assert!(front.to_html().contains("<th>title</th><td>My: Doc</td>"));
+    }
+
+    #[test]
+    fn test_toml_front_matter() {
+        let md = "+++\ntitle = 'Release notes'\ndate = 2024-05-01\ncount = 1_000\nlist = [\n  \"a\",\n  \"b\",\n]\n[extra]\nshow = true\nnested.key = \"x\"\n[[links]]\nurl = \"one\"\n[[links]]\nurl = \"two\"\n+++\nText\n";
+        let (front, body) = split(md);
+        let front = front.unwrap();
+        assert_eq!(body, "Text\n");
+        assert_eq!(front.title(), Some("Release notes"));
+        assert_eq!(front.get("date").unwrap().as_str(), Some("2024-05-01"));
+        assert_eq!(front.get("count"), Some(&Value::Number("1000".to_string())));
+        assert_eq!(front.get("list").unwrap().display(), "a, b");
+        let extra = front.get("extra").unwrap();
+        assert_eq!(extra.get("show"), Some(&Value::Bool(true)));
+        assert_eq!(extra.get("nested").unwrap().get("key").unwrap().as_str(), Some("x"));
+        assert!(matches!(front.get("links"), Some(Value::List(links)) if links.len() == 2));
+    }
+
+    #[test]
+    fn test_not_front_matter() {
+        // A rule at the top without a closing delimiter is markdown
+        let md = "---\nJust text\n";
+        assert_eq!(split(md), (None, md));
+        assert_eq!(split("Text\n---\n").0, None);
+        let (front, body) = split("\u{feff}---\r\ntitle: x\r\n---\r\nBody");
+        assert_eq!(front.unwrap().title(), Some("x"));
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn test_horizontal_rule_is_not_front_matter() {
+        for md in [
+            "---\n# Heading\n\nSome intro text\n---\n\nMore\n",
+            "---\n- one\n- two\n---\n",
+            "---\n# Only a heading\n---\n",
+            "+++\nSome text\n+++\n",
+        ] {
+            assert_eq!(split(md), (None, md));
+        }
+        let html = crate::markdown::markdown_to_html("---\n# Heading\n\nSome intro text\n---\n\nMore\n", &Default::default());
+        assert!(!html.contains("front-matter"));
+        assert!(html.contains("Heading") && html.contains("Some intro text") && html.contains("<p>More</p>"));
+    }
+}
@@ -0,0 +1,75 @@
+//! Windows side of the viewers: what the standalone viewer's window and the
+//! Total Commander plugin's window do the same way
+
+use crate::link_policy::Link;
+use crate::protocol::HostMessage;
+use crate::resources;
+use crate::watch::Watcher;
+use std::path::{Path, PathBuf};
+use webview2_com::pwstr_from_str;
+use webview2_com::Microsoft::Web::WebView2::Win32::*;
+use widestring::U16CString;
+use windows::core::{Interface, PCWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::Shell::ShellExecuteW;
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+/// Posted to a viewer window when the files of its document changed
+pub const WM_FILE_CHANGED: u32 = WM_APP + 1;
+
+/// Serve `folder` to the page as `resources::DOCUMENT_HOST`
+pub fn map_folder(webview: &ICoreWebView2, folder: &Path) {
+    if let Ok(webview3) = webview.cast::<ICoreWebView2_3>() {
+        let host = pwstr_from_str(resources::DOCUMENT_HOST);
+        let folder = pwstr_from_str(&folder.to_string_lossy());
+        unsafe {
+            let _ = webview3.SetVirtualHostNameToFolderMapping(
+                PCWSTR(host.as_ptr()),
+                PCWSTR(folder.as_ptr()),
+                COREWEBVIEW2_HOST_RESOURCE_ACCESS_KIND_DENY_CORS,
+            );
+        }
+    }
+}
+
+/// Send a message to the viewer script of a page
+pub fn post_to(webview: &ICoreWebView2, message: &HostMessage) {
+    let json_wide = pwstr_from_str(&message.to_json());
+    let _ = unsafe { webview.PostWebMessageAsJson(PCWSTR(json_wide.as_ptr())) };
+}
+
+/// Watch `files` for the viewer window `hwnd`, which gets `WM_FILE_CHANGED`
+pub fn watch_files(hwnd: HWND, files: Vec<PathBuf>) -> Watcher {
+    // Window handles cannot cross threads, their values can
+    let hwnd = hwnd.0 as isize;
+    Watcher::start(files, move || unsafe {
+        let _ = PostMessageW(Some(HWND(hwnd as *mut _)), WM_FILE_CHANGED, WPARAM(0), LPARAM(0));
+    })
+}
+
+/// Ask before following a link the policy wants confirmed
+pub fn confirm_link(hwnd: Option<HWND>, link: &Link) -> bool {
+    let message: Vec<u16> = format!("This link opens:\n\n{}\n\nDo you want to continue?\0", link.target())
+        .encode_utf16()
+        .collect();
+    let title: Vec<u16> = "Open link\0".encode_utf16().collect();
+    unsafe {
+        MessageBoxW(hwnd, PCWSTR(message.as_ptr()), PCWSTR(title.as_ptr()), MB_YESNO | MB_ICONWARNING) == IDYES
+    }
+}
+
+/// Open a URL or file with the program Windows associates with it
+pub fn open_url_in_browser(url: &str) {
+    let url_wide = U16CString::from_str(url).unwrap_or_default();
+    let open_wide = U16CString::from_str("open").unwrap_or_default();
+    unsafe {
+        ShellExecuteW(
+            None,
+            PCWSTR(open_wide.as_ptr()),
+            PCWSTR(url_wide.as_ptr()),
+            None,
+            None,
+            SW_SHOWNORMAL,
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! A small JSON reader and writer for the messages between the viewers and
+//! their pages
+
+use std::fmt;
+
+/// Deepest nesting `parse` accepts
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in document order
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parse a complete JSON text
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser { text, pos: 0 };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.pos < text.len() {
+            return Err(format!("Unexpected text after JSON value at {}", parser.pos));
+        }
+        Ok(value)
+    }
+
+    /// A member of an object. The last one wins if a key is repeated.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Compact JSON text
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Json::Number(_) => f.write_str("null"),
+            Json::String(s) => f.write_str(&quote(s)),
+            Json::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(members) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}:{}", quote(key), value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// `text` as a JSON string literal. It is also a valid JavaScript literal, and
+/// safe inside a `<script>` element: line and paragraph separators and `<` are
+/// escaped too.
+pub fn quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '<' | '\u{2028}' | '\u{2029}' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), String> {
+        if self.text[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(())
+        } else {
+            Err(format!("Expected {} at {}", literal, self.pos))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, String> {
+        if depth > MAX_DEPTH {
+            return Err("JSON nested too deeply".to_string());
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.object(depth),
+            Some(b'[') => self.array(depth),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.expect("true").map(|_| Json::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Json::Bool(false)),
+            Some(b'n') => self.expect("null").map(|_| Json::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(format!("Unexpected character at {}", self.pos)),
+            None => Err("Unexpected end of JSON".to_string()),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Json, String> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(format!("Expected a key at {}", self.pos));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(":")?;
+            members.push((key, self.value(depth + 1)?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(members));
+                }
+                _ => return Err(format!("Expected , or }} at {}", self.pos)),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Json, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(format!("Expected , or ] at {}", self.pos)),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        let rest = &self.text[start..];
+        let len = rest.find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E')).unwrap_or(rest.len());
+        let number = &rest[..len];
+        let digits = number.strip_prefix('-').unwrap_or(number);
+        let leading_zero = digits.len() > 1 && digits.starts_with('0') && digits.as_bytes()[1].is_ascii_digit();
+        match number.parse::<f64>() {
+            Ok(n) if digits.starts_with(|c: char| c.is_ascii_digit()) && !leading_zero && !number.ends_with('.') => {
+                self.pos += len;
+                Ok(Json::Number(n))
+            }
+            _ => Err(format!("Invalid number at {}", start)),
+        }
+    }
+
+    /// A string literal starting at the current `"`
+    fn string(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let rest = &self.text[self.pos..];
+            let end = rest.find(['"', '\\']).ok_or_else(|| format!("Unterminated string at {}", start))?;
+            let literal = &rest[..end];
+            if literal.chars().any(|c| (c as u32) < 0x20) {
+                return Err(format!("Control character in string at {}", start));
+            }
+            out.push_str(literal);
+            self.pos += end + 1;
+            if rest.as_bytes()[end] == b'"' {
+                return Ok(out);
+            }
+            let escape = self.peek().ok_or_else(|| format!("Unterminated string at {}", start))?;
+            self.pos += 1;
+            match escape {
+                b'"' => out.push('"'),
+                b'\\' => out.push('\\'),
+                b'/' => out.push('/'),
+                b'b' => out.push('\u{8}'),
+                b'f' => out.push('\u{c}'),
+                b'n' => out.push('\n'),
+                b'r' => out.push('\r'),
+                b't' => out.push('\t'),
+                b'u' => out.push(self.unicode_escape()?),
+                _ => return Err(format!("Invalid escape at {}", self.pos - 2)),
+            }
+        }
+    }
+
+    /// The character of a `\uXXXX` escape, after the `u`. Surrogate pairs are
+    /// joined; a lone surrogate becomes U+FFFD.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return Ok(char::from_u32(high).unwrap_or('\u{fffd}'));
+        }
+        if !self.text[self.pos..].starts_with("\\u") {
+            return Ok('\u{fffd}');
+        }
+        let saved = self.pos;
+        self.pos += 2;
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            self.pos = saved;
+            return Ok('\u{fffd}');
+        }
+        Ok(char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).unwrap_or('\u{fffd}'))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let hex = self.text.get(self.pos..self.pos + 4).filter(|h| h.chars().all(|c| c.is_ascii_hexdigit()));
+        let code = hex.and_then(|h| u32::from_str_radix(h, 16).ok()).ok_or_else(|| format!("Invalid \\u escape at {}", self.pos))?;
+        self.pos += 4;
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let json = Json::parse(r#" { "url" : "a\"b\\c\u00e9\ud83d\ude00\/", "n": -1.5e2, "list": [true, null, {}], "url": "last" } "#).unwrap();
+        assert_eq!(json.get("url").and_then(Json::as_str), Some("last"));
+        assert_eq!(json.get("n").and_then(Json::as_f64), Some(-150.0));
+        let Json::Object(members) = &json else { panic!() };
+        assert_eq!(members[0].1.as_str(), Some("a\"b\\c\u{e9}\u{1f600}/"));
+        assert_eq!(members[2].1, Json::Array(vec![Json::Bool(true), Json::Null, Json::Object(Vec::new())]));
+        assert_eq!(Json::parse(r#""\ud800x""#).unwrap().as_str(), Some("\u{fffd}x"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        for text in [
+            "", "{", "{\"a\" 1}", "{\"a\":1,}", "[1 2]", "\"abc", "\"a\nb\"", "\"\\x\"", "\"\\u12\"", "01", "1.", "-",
+            "tru", "{} x", "{a:1}",
+        ] {
+            assert!(Json::parse(text).is_err(), "{:?} should not parse", text);
+        }
+        assert!(Json::parse(&"[".repeat(1000)).is_err());
+    }
+
+    #[test]
+    fn test_write() {
+        let text = "say \"hi\"\\\n\t</script>\u{1}\u{2028}é";
+        assert_eq!(quote(text), r#""say \"hi\"\\\n\t\u003c/script>\u0001\u2028é""#);
+        let json = Json::Object(vec![
+            ("text".to_string(), Json::String(text.to_string())),
+            ("n".to_string(), Json::Number(3.0)),
+            ("items".to_string(), Json::Array(vec![Json::Bool(false), Json::Null])),
+        ]);
+        assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+        assert!(json.to_string().starts_with("{\"text\":") && json.to_string().ends_with(",\"n\":3,\"items\":[false,null]}"));
+    }
+}
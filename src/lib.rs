@@ -1,16 +1,45 @@
 #![allow(non_snake_case)]
 
+mod config;
+mod frontmatter;
+mod host;
+mod json;
+mod link_policy;
 mod markdown;
+mod navigation;
+mod page;
+mod protocol;
+mod resources;
+mod sanitize;
+mod settings;
+mod slug;
+mod style;
+mod toc;
 mod viewer;
+mod watch;
 
 use std::ffi::{c_char, c_int, CStr};
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::sync::OnceLock;
 use widestring::U16CStr;
 use windows::Win32::Foundation::HWND;
 
 // WLX Plugin Constants
 const LCP_DARKMODE: c_int = 128;
 
+/// Directory of Total Commander's plugin ini, set by ListSetDefaultParams
+static PLUGIN_INI_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// ListDefaultParamStruct from the Lister plugin interface
+#[repr(C)]
+pub struct ListDefaultParamStruct {
+    size: c_int,
+    plugin_interface_version_low: u32,
+    plugin_interface_version_hi: u32,
+    default_ini_name: [c_char; 260],
+}
+
 /// Detection string for Total Commander - handles .md and .markdown files
 #[unsafe(no_mangle)]
 pub extern "system" fn ListGetDetectString(detect_string: *mut c_char, maxlen: c_int) {
@@ -63,30 +92,71 @@ pub extern "system" fn ListCloseWindow(list_win: HWND) {
     viewer::close_window(list_win);
 }
 
+/// Receives the location of Total Commander's plugin settings
+#[unsafe(no_mangle)]
+pub extern "system" fn ListSetDefaultParams(dps: *const ListDefaultParamStruct) {
+    if dps.is_null() {
+        return;
+    }
+    let ini_name = unsafe { CStr::from_ptr((*dps).default_ini_name.as_ptr()) };
+    if let Some(dir) = PathBuf::from(ini_name.to_string_lossy().as_ref()).parent() {
+        let _ = PLUGIN_INI_DIR.set(dir.to_path_buf());
+    }
+}
+
+/// mdview.ini next to the plugin ini if there is one, else the standalone viewer's settings
+fn plugin_config() -> config::Config {
+    let path = PLUGIN_INI_DIR
+        .get()
+        .map(|dir| dir.join(config::CONFIG_FILE_NAME))
+        .filter(|path| path.exists())
+        .or_else(config::default_path);
+    path.map(|path| config::Config::load(&path)).unwrap_or_default()
+}
+
 fn load_markdown_file(parent: HWND, file_path: &str, dark_mode: bool) -> HWND {
-    // Read the markdown file
-    let markdown_content = match std::fs::read_to_string(file_path) {
-        Ok(content) => content,
-        Err(_) => return HWND::default(),
+    let config = plugin_config();
+    let Some(page) = render_document(Path::new(file_path), &config, dark_mode, false) else {
+        return HWND::default();
     };
-
-    // Convert to HTML
-    let html_body = markdown::markdown_to_html(&markdown_content);
-    let full_html = markdown::wrap_html(&html_body, dark_mode);
+    let links = config.links;
+    let live_reload = config.live_reload;
+    // Documents reached through links, and documents changed on disk, are shown in the same window
+    let render = Box::new(move |path: &Path, remote: bool| render_document(path, &config, dark_mode, remote));
 
     // Create viewer window with WebView2
-    match viewer::create_viewer(parent, &full_html) {
+    match viewer::create_viewer(parent, page, links, render, live_reload) {
         Ok(hwnd) => hwnd,
         Err(_) => HWND::default(),
     }
 }
 
+/// The viewer page of a markdown file. `load_remote` shows the remote content
+/// the reader asked for.
+fn render_document(file_path: &Path, config: &config::Config, dark_mode: bool, load_remote: bool) -> Option<viewer::Page> {
+    let markdown_content = std::fs::read_to_string(file_path).ok()?;
+    let render = settings::RenderSettings { defaults: config.settings.clone(), overrides: Vec::new() };
+    let mut settings = render.resolve(&markdown_content);
+    // Without a theme of its own the page follows Total Commander, not Windows
+    if settings.theme == settings::Theme::Auto {
+        settings.theme = if dark_mode { settings::Theme::Dark } else { settings::Theme::Light };
+    }
+    let folder = resources::document_folder(file_path);
+    if settings.remote == settings::RemoteContent::Ask && load_remote {
+        settings.remote = settings::RemoteContent::Allow;
+    }
+    let title = frontmatter::split(&markdown_content).0.and_then(|f| f.title().map(str::to_string));
+    let (html, rendering) = page::build(&markdown_content, &settings, file_path, title.as_deref(), page::Controls::default());
+    Some(viewer::Page { path: file_path.to_path_buf(), html, folder, rendering })
+}
+
 // Additional optional exports for enhanced functionality
 
 // Search parameter flags
-const LP_FORWARDONLY: c_int = 2;
+const LP_FINDFIRST: c_int = 1;
+const LP_MATCHCASE: c_int = 2;
 const LP_WHOLEWORDS: c_int = 4;
-const LP_MATCHCASE: c_int = 8;
+const LP_BACKWARDS: c_int = 8;
 
 /// Search for text in the document
 #[unsafe(no_mangle)]
@@ -121,25 +191,16 @@ pub extern "system" fn ListSearchTextW(
 }
 
 fn do_search(list_win: HWND, search_string: &str, search_parameter: c_int) -> c_int {
-    let case_sensitive = (search_parameter & LP_MATCHCASE) != 0;
-    let whole_word = (search_parameter & LP_WHOLEWORDS) != 0;
-    let forward_only = (search_parameter & LP_FORWARDONLY) != 0;
-
-    // Escape quotes in search string for JavaScript
-    let escaped = search_string.replace('\\', "\\\\").replace('"', "\\\"");
-
-    // Use window.find() for search
-    // window.find(string, caseSensitive, backwards, wrapAround, wholeWord, searchInFrames, showDialog)
-    let script = format!(
-        "window.find(\"{}\", {}, {}, {}, {}, false, false)",
-        escaped,
-        case_sensitive,
-        false, // backwards (we search forward)
-        !forward_only, // wrapAround
-        whole_word
-    );
-
-    viewer::execute_script(list_win, &script);
+    // The page highlights the matches and shows their count
+    let find = protocol::HostMessage::Find {
+        text: search_string.to_string(),
+        match_case: (search_parameter & LP_MATCHCASE) != 0,
+        whole_words: (search_parameter & LP_WHOLEWORDS) != 0,
+        regex: false,
+        backwards: (search_parameter & LP_BACKWARDS) != 0,
+        from_start: (search_parameter & LP_FINDFIRST) != 0,
+    };
+    viewer::post_message(list_win, &find);
     0 // LISTPLUGIN_OK
 }
 
@@ -155,11 +216,11 @@ pub extern "system" fn ListSendCommand(
 
     match command {
         LC_COPY => {
-            viewer::execute_script(list_win, "document.execCommand('copy')");
+            viewer::post_message(list_win, &protocol::HostMessage::Copy);
             1 // LISTPLUGIN_OK
         }
         LC_SELECTALL => {
-            viewer::execute_script(list_win, "document.execCommand('selectAll')");
+            viewer::post_message(list_win, &protocol::HostMessage::SelectAll);
             1
         }
         _ => 0,
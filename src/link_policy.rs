@@ -0,0 +1,267 @@
+//! What a click on a link in a document may do. Documents come from anywhere,
+//! so a link must not be able to start a program without the reader agreeing.
+
+use crate::navigation::is_markdown_file;
+use crate::resources;
+use std::path::{Path, PathBuf};
+
+/// Targets, by what they are and where they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    Http,
+    Https,
+    Mailto,
+    /// `file:` URLs and paths on other machines
+    File,
+    /// A markdown file next to the document
+    Markdown,
+    /// Any other file next to the document
+    Relative,
+    /// Other schemes, like `ms-settings:`, and root-relative URLs
+    Unknown,
+}
+
+/// What happens when a link is clicked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Show markdown files in the viewer, open anything else
+    Navigate,
+    /// Hand the target to the program Windows associates with it
+    Open,
+    /// Ask first, then navigate or open
+    Confirm,
+    Block,
+}
+
+impl Action {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "navigate" => Some(Self::Navigate),
+            "open" => Some(Self::Open),
+            "confirm" | "ask" => Some(Self::Confirm),
+            "block" => Some(Self::Block),
+            _ => None,
+        }
+    }
+}
+
+/// File types Windows runs, installs or mounts instead of opening
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "appinstaller", "appref-ms", "application", "appx", "bat", "chm", "cmd", "com", "cpl", "desktopthemepackfile",
+    "diagcab", "dll", "exe", "gadget", "hta", "img", "inf", "iso", "jar", "jnlp", "js", "jse", "library-ms", "lnk",
+    "msc", "msi", "msix", "msp", "msu", "ocx", "pif", "ps1", "psm1", "py", "pyw", "rdp", "reg", "scf", "scr", "sct",
+    "searchconnector-ms", "settingcontent-ms", "sys", "theme", "themepack", "url", "vb", "vbe", "vbs", "vhd", "vhdx",
+    "vsto", "website", "ws", "wsc", "wsf", "wsh", "xbap", "xll",
+];
+
+/// A clicked link, classified
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub kind: LinkKind,
+    /// The URL as written
+    pub url: String,
+    /// The file a local target refers to
+    pub path: Option<PathBuf>,
+}
+
+impl Link {
+    /// Whether opening the target runs a program
+    pub fn is_executable(&self) -> bool {
+        self.path.as_deref().and_then(windows_extension).is_some_and(|e| EXECUTABLE_EXTENSIONS.contains(&e.as_str()))
+    }
+
+    /// The markdown file the link points to, to show in the viewer
+    pub fn document(&self) -> Option<&Path> {
+        self.path.as_deref().filter(|p| is_markdown_file(p))
+    }
+
+    /// What to pass to the shell to open the target
+    pub fn target(&self) -> String {
+        match &self.path {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => self.url.clone(),
+        }
+    }
+}
+
+/// The extension of the file Windows opens for `path`. It trims trailing dots
+/// and spaces, and `run.bat::$DATA` or `run.bat:stream` is a stream of `run.bat`.
+fn windows_extension(path: &Path) -> Option<String> {
+    let path = path.to_string_lossy();
+    let name = path.rsplit(['\\', '/']).next()?;
+    let name = name.split(':').next()?.trim_end_matches(['.', ' ']);
+    let (_, extension) = name.rsplit_once('.')?;
+    Some(extension.to_ascii_lowercase())
+}
+
+/// Classify the `href` of a link in a document in `base_dir`
+pub fn classify(url: &str, base_dir: &Path) -> Link {
+    let url = url.trim();
+    let link = |kind, path| Link { kind, url: url.to_string(), path };
+
+    if let Some(relative) = resources::local_path(url) {
+        let path = base_dir.join(relative);
+        let kind = if is_markdown_file(&path) {
+            LinkKind::Markdown
+        } else {
+            LinkKind::Relative
+        };
+        return link(kind, Some(path));
+    }
+    // \\server\share and //server/share
+    if url.starts_with(['/', '\\']) && url[1..].starts_with(['/', '\\']) {
+        return link(LinkKind::File, Some(PathBuf::from(url.replace('/', "\\"))));
+    }
+
+    let scheme = url.split_once(':').map(|(s, _)| s.to_ascii_lowercase()).unwrap_or_default();
+    match scheme.as_str() {
+        "http" => link(LinkKind::Http, None),
+        "https" => link(LinkKind::Https, None),
+        "mailto" => link(LinkKind::Mailto, None),
+        "file" => link(LinkKind::File, file_url_path(url)),
+        _ => link(LinkKind::Unknown, None),
+    }
+}
+
+/// `file:///C:/a%20b.txt` is `C:\a b.txt`, `file://server/share` is `\\server\share`
+fn file_url_path(url: &str) -> Option<PathBuf> {
+    let rest = url.get("file:".len()..)?;
+    let rest = &rest[..rest.find(['?', '#']).unwrap_or(rest.len())];
+    let path = match rest.strip_prefix("///") {
+        Some(local) => local.to_string(),
+        None => format!("//{}", rest.strip_prefix("//")?),
+    };
+    Some(PathBuf::from(resources::percent_decode(&path).replace('/', "\\")))
+}
+
+/// An action for each kind of link, and one for executable files that wins
+/// over the others. Set in the `[links]` section of mdview.ini.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkPolicy {
+    pub http: Action,
+    pub https: Action,
+    pub mailto: Action,
+    pub file: Action,
+    pub markdown: Action,
+    pub relative: Action,
+    pub unknown: Action,
+    pub executable: Action,
+}
+
+impl Default for LinkPolicy {
+    fn default() -> Self {
+        Self {
+            http: Action::Open,
+            https: Action::Open,
+            mailto: Action::Open,
+            file: Action::Confirm,
+            markdown: Action::Navigate,
+            // No list of file types that run something is complete
+            relative: Action::Confirm,
+            unknown: Action::Block,
+            executable: Action::Block,
+        }
+    }
+}
+
+impl LinkPolicy {
+    /// Change the action for one kind of link. `false` for unknown keys and values.
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        let Some(action) = Action::parse(value) else {
+            return false;
+        };
+        let field = match key.trim().to_ascii_lowercase().as_str() {
+            "http" => &mut self.http,
+            "https" => &mut self.https,
+            "mailto" => &mut self.mailto,
+            "file" => &mut self.file,
+            "markdown" => &mut self.markdown,
+            "relative" => &mut self.relative,
+            "unknown" => &mut self.unknown,
+            "executable" => &mut self.executable,
+            _ => return false,
+        };
+        *field = action;
+        true
+    }
+
+    pub fn action(&self, link: &Link) -> Action {
+        if link.is_executable() {
+            return self.executable;
+        }
+        match link.kind {
+            LinkKind::Http => self.http,
+            LinkKind::Https => self.https,
+            LinkKind::Mailto => self.mailto,
+            LinkKind::File => self.file,
+            LinkKind::Markdown => self.markdown,
+            LinkKind::Relative => self.relative,
+            LinkKind::Unknown => self.unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let base = Path::new("docs");
+        let kind = |url| classify(url, base).kind;
+        assert_eq!(kind("https://example.com/setup.exe"), LinkKind::Https);
+        assert_eq!(kind("HTTP://example.com"), LinkKind::Http);
+        assert_eq!(kind("mailto:a@b.c"), LinkKind::Mailto);
+        assert_eq!(kind("ms-settings:privacy"), LinkKind::Unknown);
+        assert_eq!(kind("/docs/guide.md"), LinkKind::Unknown);
+        assert_eq!(kind("\\\\server\\share\\x.exe"), LinkKind::File);
+
+        let guide = classify("guide%20v2.md#install", base);
+        assert_eq!(guide.kind, LinkKind::Markdown);
+        assert_eq!(guide.document(), Some(base.join("guide v2.md").as_path()));
+        let tool = classify("tools/run.BAT", base);
+        assert_eq!((tool.kind, tool.is_executable()), (LinkKind::Relative, true));
+        assert!(!classify("manual.pdf", base).is_executable());
+        let file = classify("file:///C:/Windows/notepad.exe", base);
+        assert_eq!((file.kind, file.is_executable()), (LinkKind::File, true));
+        assert_eq!(classify("file://server/share/a.txt", base).target(), "\\\\server\\share\\a.txt");
+    }
+
+    #[test]
+    fn test_executable_variants() {
+        let base = Path::new("docs");
+        // Windows drops trailing dots and spaces, and ::$DATA is the file itself
+        for url in ["tools/setup.exe.", "tools/setup.exe%20", "tools/setup.exe. .", "tools/run.bat::$DATA", "tools/run.BAT:x"] {
+            assert!(classify(url, base).is_executable(), "{} should be executable", url);
+        }
+        for url in [
+            "app.msix", "app.appx", "app.appinstaller", "addin.xll", "fix.diagcab", "disk.iso", "disk.img", "disk.vhd",
+            "disk.vhdx", "lib.library-ms", "search.searchconnector-ms", "pinned.website", "tool.py", "tool.pyw",
+            "server.rdp", "dark.theme", "dark.themepack", "dark.desktopthemepackfile", "app.jnlp", "app.xbap",
+            "script.sct", "control.ocx", "update.msu", "addin.vsto",
+        ] {
+            assert!(classify(url, base).is_executable(), "{} should be executable", url);
+        }
+        assert!(!classify("notes.txt.", base).is_executable());
+        assert!(!classify("exe", base).is_executable());
+        assert_eq!(LinkPolicy::default().action(&classify("tools/setup.exe.", base)), Action::Block);
+    }
+
+    #[test]
+    fn test_policy() {
+        let mut policy = LinkPolicy::default();
+        let base = Path::new(".");
+        assert_eq!(policy.action(&classify("https://example.com", base)), Action::Open);
+        assert_eq!(policy.action(&classify("other.md", base)), Action::Navigate);
+        assert_eq!(policy.action(&classify("setup.exe", base)), Action::Block);
+        assert_eq!(policy.action(&classify("shortcut.lnk", base)), Action::Block);
+        assert_eq!(policy.action(&classify("file:///C:/notes.txt", base)), Action::Confirm);
+        assert_eq!(policy.action(&classify("manual.pdf", base)), Action::Confirm);
+        assert_eq!(policy.action(&classify("search-ms:query=x", base)), Action::Block);
+
+        assert!(policy.set("Executable", "confirm") && policy.set("https", "block"));
+        assert!(!policy.set("ftp", "open") && !policy.set("http", "maybe"));
+        assert_eq!(policy.action(&classify("setup.exe", base)), Action::Confirm);
+        assert_eq!(policy.action(&classify("https://example.com", base)), Action::Block);
+    }
+}
@@ -1,14 +1,34 @@
 #![windows_subsystem = "windows"]
 
+mod bigtext;
+mod config;
 mod dark_menu;
+mod frontmatter;
+mod host;
+mod json;
+mod link_policy;
 mod markdown;
+mod mermaid;
+mod navigation;
+mod page;
+mod present;
+mod protocol;
+mod resources;
+mod sanitize;
+mod settings;
+mod slug;
+mod source;
+mod stream;
+mod style;
 mod terminal;
+mod toc;
+mod watch;
 
 use std::cell::RefCell;
 use std::env;
 use std::fs;
-use std::io::{self, Read, Write};
-use std::path::Path;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 fn print_usage_console() {
@@ -21,17 +41,54 @@ fn print_usage_console() {
          \x20 --gui        Open in GUI window\n\
          \x20 --term       Output with terminal colors/formatting\n\
          \x20 --html       Output full HTML document to stdout\n\
+         \x20 --self-contained  Like --html, with local images and stylesheets embedded\n\
          \x20 --body       Output HTML body only (no wrapper)\n\
          \x20 --text       Output plain text (no formatting)\n\
+         \x20 --meta       Output the front matter as JSON\n\
+         \x20 --print-css  Output the stylesheet used for HTML pages\n\
+         \x20 --present    Show the document as terminal slides\n\
+         \x20 --split MODE Slide breaks for --present: rule (default), h1, h2\n\
+         \x20 --source     Show the markdown source with syntax coloring\n\
+         \x20             (with --gui: show source next to the rendered view)\n\
+         \x20 -n, --line-numbers  Show line numbers with --source\n\
+         \x20 --big-headings[=h1|h2]  Draw H1 (or H1 and H2) headings large in --term\n\
+         \x20 --accessible Terminal output for screen readers (structure in words)\n\
+         \x20 --stream     Render stdin block by block as it arrives\n\
+         \x20 --ext LIST   Enable markdown extensions (comma separated):\n\
+         \x20             smart, heading-attributes, definition-lists, superscript,\n\
+         \x20             subscript, wikilinks, math, gfm, hard-breaks\n\
+         \x20 --no-ext LIST  Disable markdown extensions\n\
+         \x20 --number-headings  Number headings (1, 1.1, 1.2, ...)\n\
+         \x20 --toc        Add a table of contents (a [[_TOC_]] or <!-- toc --> line\n\
+         \x20             in the document always gets one); opens the GUI sidebar\n\
+         \x20 --toc-depth N  Deepest heading level in the table of contents (default 3)\n\
+         \x20 --trust      Keep raw HTML in the document as is (scripts included);\n\
+         \x20             only for files from a trusted source\n\
+         \x20 --remote MODE Remote images and media: ask (default: blocked, the\n\
+         \x20             viewer offers to load them), block or allow\n\
+         \x20 --update-toc Rewrite the list between <!-- toc --> and <!-- tocstop --> in FILE\n\
+         \x20 --check      With --update-toc: only exit with 1 if the list is out of date\n\
+         \x20 --width N    Wrap text at N columns (HTML: page width in characters)\n\
+         \x20 --theme MODE light, dark or auto (follow the system, the default)\n\
+         \x20 --style NAME HTML style: github (default), high-contrast, sepia,\n\
+         \x20             solarized, print, none\n\
+         \x20 --css FILE   Add a stylesheet after the style (with --style none: instead)\n\
          \x20 --register   Register as .md file viewer (Open With)\n\
          \x20 --unregister Remove .md file viewer registration\n\
          \x20 -h, --help   Show this help message\n\n\
-         If no FILE is specified, reads from stdin (CLI mode only).\n\n\
+         If no FILE is specified, reads from stdin (CLI mode only).\n\
+         Default settings are read from %APPDATA%\\MDView\\mdview.ini. A document can set\n\
+         its own under an `mdview:` front matter key; command line options win. The\n\
+         theme picked with the viewer's theme button replaces the mdview.ini theme there.\n\n\
          Examples:\n\
          \x20 mdview README.md              # Open in GUI window\n\
          \x20 mdview --term README.md       # Output with terminal colors\n\
          \x20 cat doc.md | mdview           # Piped input, terminal output\n\
-         \x20 mdview --html README.md       # Output HTML to stdout\n",
+         \x20 llm-tool | mdview --stream    # Render output as it is generated\n\
+         \x20 mdview --html README.md       # Output HTML to stdout\n\
+         \x20 mdview --self-contained README.md > README.html  # One file to share\n\
+         \x20 mdview --present talk.md      # Present slides split at ---\n\
+         \x20 mdview --update-toc --check README.md  # Fail if the TOC is stale\n",
         env!("CARGO_PKG_VERSION")
     );
     write_console(&usage);
@@ -42,18 +99,31 @@ struct Options {
     gui_mode: bool,
     terminal_mode: bool,
     html_full: bool,
+    self_contained: bool,
     html_body: bool,
     plain_text: bool,
+    meta: bool,
+    print_css: bool,
+    present: bool,
+    split: present::SplitMode,
+    source: bool,
+    line_numbers: bool,
+    big_headings: terminal::BigHeadings,
+    accessible: bool,
+    stream: bool,
+    render: settings::RenderSettings,
+    update_toc: bool,
+    check: bool,
     register: bool,
     unregister: bool,
     file_path: Option<String>,
 }
 
 fn parse_args(has_console: bool) -> Result<Options, String> {
-    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args = env::args().skip(1);
     let mut opts = Options::default();
 
-    for arg in args {
+    while let Some(arg) = args.next() {
         match arg.as_str() {
             "-h" | "--help" => {
                 print_usage_console();
@@ -62,8 +132,87 @@ fn parse_args(has_console: bool) -> Result<Options, String> {
             "--gui" => opts.gui_mode = true,
             "--term" | "--terminal" => opts.terminal_mode = true,
             "--html" => opts.html_full = true,
+            "--self-contained" => {
+                opts.html_full = true;
+                opts.self_contained = true;
+            }
             "--body" => opts.html_body = true,
             "--text" => opts.plain_text = true,
+            "--meta" => opts.meta = true,
+            "--print-css" => opts.print_css = true,
+            "--theme" => {
+                let value = args.next().ok_or("--theme requires a value")?;
+                let change = settings::Change::parse("theme", &value)
+                    .ok_or_else(|| format!("Invalid --theme value: {} (expected light, dark or auto)", value))?;
+                opts.render.overrides.push(change);
+            }
+            "--style" => {
+                let value = args.next().ok_or("--style requires a value")?;
+                let change = settings::Change::parse("style", &value).ok_or_else(|| {
+                    format!("Unknown style: {} (expected one of {})", value, style::Style::NAMES.join(", "))
+                })?;
+                opts.render.overrides.push(change);
+            }
+            "--css" => {
+                let value = args.next().ok_or("--css requires a file")?;
+                if !Path::new(&value).is_file() {
+                    return Err(format!("Stylesheet not found: {}", value));
+                }
+                opts.render.overrides.push(settings::Change::Css(value.into()));
+            }
+            "--present" => opts.present = true,
+            "--split" => {
+                let value = args.next().ok_or("--split requires a value")?;
+                opts.split = present::SplitMode::parse(&value)
+                    .ok_or_else(|| format!("Invalid --split value: {} (expected rule, h1 or h2)", value))?;
+            }
+            "--source" => opts.source = true,
+            "-n" | "--line-numbers" => opts.line_numbers = true,
+            "--accessible" => opts.accessible = true,
+            "--stream" => opts.stream = true,
+            "--ext" | "--no-ext" => {
+                let enabled = arg == "--ext";
+                let value = args.next().ok_or_else(|| format!("{} requires a value", arg))?;
+                for name in value.split(',').filter(|n| !n.trim().is_empty()) {
+                    if !markdown::Extensions::default().set(name, enabled) {
+                        return Err(format!(
+                            "Unknown extension: {} (expected one of {})",
+                            name.trim(),
+                            markdown::Extensions::NAMES.join(", ")
+                        ));
+                    }
+                    opts.render.overrides.push(settings::Change::Extension(name.to_string(), enabled));
+                }
+            }
+            "--number-headings" => opts.render.overrides.push(settings::Change::NumberHeadings(true)),
+            "--trust" => opts.render.overrides.push(settings::Change::Trust(true)),
+            "--remote" => {
+                let value = args.next().ok_or("--remote requires a value")?;
+                let remote = settings::RemoteContent::parse(&value)
+                    .ok_or_else(|| format!("Invalid --remote value: {} (expected allow, block or ask)", value))?;
+                opts.render.overrides.push(settings::Change::Remote(remote));
+            }
+            "--toc" => opts.render.overrides.push(settings::Change::Toc(true)),
+            "--toc-depth" => {
+                let value = args.next().ok_or("--toc-depth requires a value")?;
+                let change = settings::Change::parse("toc_depth", &value)
+                    .ok_or_else(|| format!("Invalid --toc-depth value: {} (expected 1 to 6)", value))?;
+                opts.render.overrides.push(change);
+            }
+            "--width" => {
+                let value = args.next().ok_or("--width requires a value")?;
+                let change = settings::Change::parse("width", &value)
+                    .ok_or_else(|| format!("Invalid --width value: {} (expected a number of at least 20)", value))?;
+                opts.render.overrides.push(change);
+            }
+            "--big-headings" => opts.big_headings = terminal::BigHeadings::H1,
+            s if s.starts_with("--big-headings=") => {
+                let value = &s["--big-headings=".len()..];
+                opts.big_headings = terminal::BigHeadings::parse(value)
+                    .ok_or_else(|| format!("Invalid --big-headings value: {} (expected h1 or h2)", value))?;
+            }
+            "--update-toc" => opts.update_toc = true,
+            "--check" => opts.check = true,
             "--register" => opts.register = true,
             "--unregister" => opts.unregister = true,
             s if s.starts_with('-') => {
@@ -78,23 +227,72 @@ fn parse_args(has_console: bool) -> Result<Options, String> {
         }
     }
 
+    if opts.line_numbers && !opts.source {
+        return Err("-n/--line-numbers can only be combined with --source".to_string());
+    }
+
     // Register/unregister are standalone operations
     if opts.register || opts.unregister {
         if opts.register && opts.unregister {
             return Err("--register and --unregister are mutually exclusive".to_string());
         }
-        let other_flags = opts.html_full as u8 + opts.html_body as u8 + opts.plain_text as u8
-            + opts.terminal_mode as u8 + opts.gui_mode as u8;
+        let other_flags = opts.html_full as u8 + opts.html_body as u8 + opts.plain_text as u8 + opts.meta as u8
+            + opts.terminal_mode as u8 + opts.gui_mode as u8 + opts.present as u8 + opts.source as u8
+            + opts.print_css as u8;
         if other_flags > 0 || opts.file_path.is_some() {
             return Err("--register and --unregister cannot be combined with other options".to_string());
         }
         return Ok(opts);
     }
 
+    // --update-toc edits the file instead of showing it
+    if opts.check && !opts.update_toc {
+        return Err("--check can only be combined with --update-toc".to_string());
+    }
+    if opts.update_toc {
+        let other_flags = opts.html_full as u8 + opts.html_body as u8 + opts.plain_text as u8 + opts.meta as u8
+            + opts.terminal_mode as u8 + opts.gui_mode as u8 + opts.present as u8 + opts.source as u8
+            + opts.stream as u8 + opts.print_css as u8;
+        if other_flags > 0 {
+            return Err("--update-toc cannot be combined with output options".to_string());
+        }
+        if opts.file_path.is_none() {
+            return Err("--update-toc requires a file".to_string());
+        }
+        return Ok(opts);
+    }
+
     // Validate mutually exclusive options
-    let cli_format_count = opts.html_full as u8 + opts.html_body as u8 + opts.plain_text as u8 + opts.terminal_mode as u8;
+    // --source is a view toggle in GUI mode and an output format otherwise
+    let cli_format_count = opts.html_full as u8 + opts.html_body as u8 + opts.plain_text as u8 + opts.meta as u8
+        + opts.print_css as u8 + opts.terminal_mode as u8 + opts.present as u8 + (opts.source && !opts.gui_mode) as u8;
     if cli_format_count > 1 {
-        return Err("Options --term, --html, --body, and --text are mutually exclusive".to_string());
+        return Err(
+            "Options --term, --html, --body, --text, --meta, --print-css, --present, and --source are mutually exclusive"
+                .to_string(),
+        );
+    }
+    if (opts.meta || opts.print_css) && opts.gui_mode {
+        return Err("--meta and --print-css cannot be combined with --gui".to_string());
+    }
+    if opts.present && (opts.gui_mode || opts.file_path.is_none()) {
+        return Err("--present requires a file and cannot be combined with --gui".to_string());
+    }
+    // Slides and stream blocks are shown one at a time, without the whole outline
+    if (opts.present || opts.stream) && opts.render.overrides.contains(&settings::Change::Toc(true)) {
+        return Err("--toc cannot be combined with --present or --stream".to_string());
+    }
+
+    // --accessible and --stream are flavors of terminal output
+    if opts.accessible || opts.stream {
+        if opts.gui_mode || (cli_format_count > 0 && !opts.terminal_mode) {
+            return Err("--accessible and --stream can only be combined with --term".to_string());
+        }
+        if opts.stream && opts.file_path.is_some() {
+            return Err("--stream reads from stdin and cannot be combined with a file".to_string());
+        }
+        opts.terminal_mode = true;
+        return Ok(opts);
     }
 
     // Default behavior based on whether we have a console (terminal) or not (double-clicked)
@@ -235,6 +433,165 @@ fn send_enter_key() {
     }
 }
 
+/// Visible console window size in character cells (columns, rows)
+fn console_size() -> Option<(usize, usize)> {
+    use windows::Win32::System::Console::{
+        GetConsoleScreenBufferInfo, GetStdHandle, CONSOLE_SCREEN_BUFFER_INFO, STD_OUTPUT_HANDLE,
+    };
+
+    unsafe {
+        let handle = GetStdHandle(STD_OUTPUT_HANDLE).ok()?;
+        let mut info = CONSOLE_SCREEN_BUFFER_INFO::default();
+        GetConsoleScreenBufferInfo(handle, &mut info).ok()?;
+        let width = (info.srWindow.Right - info.srWindow.Left + 1).max(1) as usize;
+        let height = (info.srWindow.Bottom - info.srWindow.Top + 1).max(1) as usize;
+        Some((width, height))
+    }
+}
+
+/// Block until a key is pressed on the console. Returns None when the window was
+/// resized, so the caller can redraw.
+fn read_console_key(input: HANDLE) -> io::Result<Option<present::Key>> {
+    use present::Key;
+    use windows::Win32::System::Console::{
+        ReadConsoleInputW, INPUT_RECORD, KEY_EVENT, WINDOW_BUFFER_SIZE_EVENT,
+    };
+    use windows::Win32::UI::Input::KeyboardAndMouse::*;
+
+    loop {
+        let mut records = [INPUT_RECORD::default()];
+        let mut read = 0u32;
+        unsafe { ReadConsoleInputW(input, &mut records, &mut read) }.map_err(io::Error::other)?;
+        if read == 0 {
+            continue;
+        }
+
+        let record = records[0];
+        if record.EventType as u32 == WINDOW_BUFFER_SIZE_EVENT {
+            return Ok(None);
+        }
+        if record.EventType as u32 != KEY_EVENT {
+            continue;
+        }
+
+        let key_event = unsafe { record.Event.KeyEvent };
+        if !key_event.bKeyDown.as_bool() {
+            continue;
+        }
+        let key = match VIRTUAL_KEY(key_event.wVirtualKeyCode) {
+            VK_LEFT => Key::Left,
+            VK_RIGHT => Key::Right,
+            VK_UP => Key::Up,
+            VK_DOWN => Key::Down,
+            VK_PRIOR => Key::PageUp,
+            VK_NEXT => Key::PageDown,
+            VK_HOME => Key::Home,
+            VK_END => Key::End,
+            VK_RETURN => Key::Enter,
+            VK_BACK => Key::Backspace,
+            VK_ESCAPE => Key::Escape,
+            _ => match char::from_u32(unsafe { key_event.uChar.UnicodeChar } as u32) {
+                Some(c) if c != '\0' => Key::Char(c),
+                _ => continue,
+            },
+        };
+        return Ok(Some(key));
+    }
+}
+
+/// Run an interactive slide presentation on the attached console
+fn run_presentation(markdown: &str, split: present::SplitMode, settings: &settings::Settings) -> io::Result<()> {
+    use windows::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, CONSOLE_MODE, ENABLE_EXTENDED_FLAGS,
+        ENABLE_WINDOW_INPUT, STD_INPUT_HANDLE,
+    };
+
+    let mut deck = present::Presentation::new(present::split_slides(markdown, split, &settings.extensions));
+    if deck.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Document contains no slides"));
+    }
+    let caps = terminal::TerminalCaps::detect();
+
+    let input = unsafe { GetStdHandle(STD_INPUT_HANDLE) }.map_err(io::Error::other)?;
+    let mut saved_mode = CONSOLE_MODE::default();
+    if unsafe { GetConsoleMode(input, &mut saved_mode) }.is_err() {
+        return Err(io::Error::other("--present needs an interactive console"));
+    }
+    // Raw key input: no line buffering, no echo, Ctrl+C delivered as a key
+    let _ = unsafe { SetConsoleMode(input, ENABLE_WINDOW_INPUT | ENABLE_EXTENDED_FLAGS) };
+
+    let mut stdout = io::stdout();
+    // Switch to the alternate screen and hide the cursor
+    stdout.write_all(b"\x1b[?1049h\x1b[?25l")?;
+
+    let result = (|| -> io::Result<()> {
+        loop {
+            let (width, height) = console_size().unwrap_or((80, 25));
+            stdout.write_all(deck.render(&caps, settings, width, height).as_bytes())?;
+            stdout.flush()?;
+
+            if let Some(key) = read_console_key(input)? {
+                if !deck.handle_key(key) {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+
+    stdout.write_all(b"\x1b[?25h\x1b[?1049l")?;
+    stdout.flush()?;
+    let _ = unsafe { SetConsoleMode(input, saved_mode) };
+    result
+}
+
+/// Render stdin to the terminal one block at a time, as soon as each block is complete
+fn run_stream(
+    caps: &terminal::TerminalCaps,
+    options: &terminal::TerminalOptions,
+    render: &settings::RenderSettings,
+) -> io::Result<()> {
+    let mut input = io::stdin().lock();
+    let mut stdout = io::stdout();
+    let mut splitter = stream::BlockSplitter::new(stream::DEFAULT_LIMIT);
+    let mut first = true;
+    let mut options = terminal::TerminalOptions { numbering: markdown::Numbering::streamed(), ..options.clone() };
+
+    let mut write_block = |block: &str| -> io::Result<()> {
+        // Front matter can only be the first block; its settings apply to the rest
+        if first && frontmatter::split(block).0.is_some() {
+            options.settings = render.resolve(block);
+        }
+        // A table of contents of one block would list only its own headings
+        options.settings.toc = false;
+        let rendered = terminal::render_to_terminal_with(block, caps, &options);
+        options.numbering.skip(block, &options.settings);
+        // Blocks are separated by exactly one blank line
+        if !first {
+            stdout.write_all(b"\n")?;
+        }
+        first = false;
+        stdout.write_all(rendered.trim_start_matches('\n').as_bytes())?;
+        stdout.flush()
+    };
+
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        // A line longer than the limit is handed over in pieces
+        let read = (&mut input).take(stream::DEFAULT_LIMIT as u64).read_until(b'\n', &mut line)?;
+        if read == 0 {
+            break;
+        }
+        for block in splitter.push_line(&String::from_utf8_lossy(&line)) {
+            write_block(&block)?;
+        }
+    }
+    if let Some(block) = splitter.finish() {
+        write_block(&block)?;
+    }
+    Ok(())
+}
+
 fn read_input(file_path: Option<&str>) -> io::Result<String> {
     match file_path {
         Some(path) => {
@@ -255,11 +612,21 @@ fn read_input(file_path: Option<&str>) -> io::Result<String> {
     }
 }
 
+fn terminal_options(opts: &Options, settings: settings::Settings) -> terminal::TerminalOptions {
+    terminal::TerminalOptions {
+        big_headings: opts.big_headings,
+        accessible: opts.accessible,
+        settings,
+        width: console_size().map_or(80, |(width, _)| width),
+        numbering: markdown::Numbering::default(),
+    }
+}
+
 fn main() {
     // Try to attach to parent console - this tells us if we're launched from a terminal
     let has_console = attach_console();
 
-    let opts = match parse_args(has_console) {
+    let mut opts = match parse_args(has_console) {
         Ok(o) => o,
         Err(e) => {
             eprintln!("Error: {}", e);
@@ -268,6 +635,12 @@ fn main() {
         }
     };
 
+    // Settings file first, then the document, command line options on top
+    let config = config::default_path().map(|path| config::Config::load(&path)).unwrap_or_default();
+    opts.render.defaults = config.settings;
+    LINK_POLICY.with(|l| *l.borrow_mut() = config.links);
+    LIVE_RELOAD.with(|l| *l.borrow_mut() = config.live_reload);
+
     // Handle --register / --unregister
     if opts.register {
         match register_file_association() {
@@ -304,15 +677,58 @@ fn main() {
         }
     }
 
+    if opts.update_toc {
+        let path = opts.file_path.as_deref().unwrap_or_default();
+        match update_toc_file(path, &opts.render, opts.check) {
+            Ok(false) => {}
+            Ok(true) if opts.check => {
+                eprintln!("{}: table of contents is out of date (run mdview --update-toc {})", path, path);
+                std::process::exit(1);
+            }
+            Ok(true) => eprintln!("Updated table of contents in {}", path),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        std::process::exit(0);
+    }
+
+    // The stylesheet only depends on the settings, so stdin is not read
+    if opts.print_css {
+        let markdown_content = match opts.file_path.as_deref() {
+            Some(path) => match read_input(Some(path)) {
+                Ok(content) => content,
+                Err(e) => {
+                    eprintln!("Error reading input: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            None => String::new(),
+        };
+        let css = style::stylesheet(&opts.render.resolve(&markdown_content));
+        if let Err(e) = io::stdout().write_all(css.as_bytes()) {
+            eprintln!("Error writing output: {}", e);
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
     // If no file and running in CLI/terminal mode, show help and exit
-    if opts.file_path.is_none() && !opts.gui_mode {
+    // (unless input is piped in)
+    if opts.file_path.is_none() && !opts.gui_mode && atty::is(atty::Stream::Stdin) {
         print_usage_console();
         send_enter_key(); // Release command prompt
         std::process::exit(0);
     }
 
     if opts.gui_mode {
-        // GUI mode - open window with WebView2
+        // GUI mode - open window with WebView2. The theme picked in the viewer
+        // replaces the settings file's; exports do not depend on it.
+        if let Some(theme) = load_theme_choice() {
+            opts.render.defaults.theme = theme;
+        }
+        RENDER_SETTINGS.with(|r| *r.borrow_mut() = opts.render.clone());
         let (title, full_html) = if let Some(ref path) = opts.file_path {
             let markdown_content = match read_input(Some(path)) {
                 Ok(content) => content,
@@ -321,25 +737,21 @@ fn main() {
                     std::process::exit(1);
                 }
             };
-            let html_body = markdown::markdown_to_html(&markdown_content);
-            let dark_mode = is_windows_dark_mode();
-            let full_html = markdown::wrap_html(&html_body, dark_mode);
-            let title = Path::new(path)
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
+            SHOW_SOURCE.with(|s| *s.borrow_mut() = opts.source);
+            let title = document_title(&markdown_content, path);
+            let (full_html, rendering) = build_page(&markdown_content, &title, path);
+            RENDERING.with(|r| *r.borrow_mut() = rendering);
             (title, full_html)
         } else {
             // No file - show welcome screen
-            let dark_mode = is_windows_dark_mode();
-            let welcome_html = markdown::wrap_html(
+            let welcome_html = markdown::wrap_viewer_html(
                 "<div style=\"text-align: center; margin-top: 100px; color: #888;\">\
                  <h1>MDView</h1>\
                  <p>Open a Markdown file using <strong>File &gt; Open</strong> (Ctrl+O)</p>\
                  <p>or drag and drop a .md file onto this window.</p>\
                  </div>",
-                dark_mode,
+                Some("MDView"),
+                &opts.render.resolve(""),
             );
             ("MDView".to_string(), welcome_html)
         };
@@ -348,6 +760,14 @@ fn main() {
             eprintln!("Error: {}", e);
             std::process::exit(1);
         }
+    } else if opts.stream {
+        enable_virtual_terminal_processing();
+        let caps = terminal::TerminalCaps::detect();
+        let options = terminal_options(&opts, opts.render.resolve(""));
+        if let Err(e) = run_stream(&caps, &options, &opts.render) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     } else {
         // CLI mode needs content
         let markdown_content = match read_input(opts.file_path.as_deref()) {
@@ -357,20 +777,51 @@ fn main() {
                 std::process::exit(1);
             }
         };
+
+        let mut settings = opts.render.resolve(&markdown_content);
+        // Exported pages have no viewer to ask in, so they show the placeholders
+        if settings.remote == settings::RemoteContent::Ask {
+            settings.remote = settings::RemoteContent::Block;
+        }
+
+        if opts.present {
+            enable_virtual_terminal_processing();
+            if let Err(e) = run_presentation(frontmatter::split(&markdown_content).1, opts.split, &settings) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+
         // CLI mode - output to stdout
         let output = if opts.terminal_mode {
             // Enable ANSI processing on Windows console
             enable_virtual_terminal_processing();
             let caps = terminal::TerminalCaps::detect();
-            terminal::render_to_terminal(&markdown_content, &caps)
+            terminal::render_to_terminal_with(&markdown_content, &caps, &terminal_options(&opts, settings))
+        } else if opts.source {
+            enable_virtual_terminal_processing();
+            let caps = terminal::TerminalCaps::detect();
+            source::render_source_to_terminal(&markdown_content, &settings.extensions, &caps, opts.line_numbers)
+        } else if opts.meta {
+            match frontmatter::split(&markdown_content).0 {
+                Some(front_matter) => front_matter.to_json(),
+                None => "{}\n".to_string(),
+            }
         } else if opts.plain_text {
-            markdown::markdown_to_plain_text(&markdown_content)
+            markdown::markdown_to_plain_text(&markdown_content, &settings)
         } else if opts.html_body {
-            markdown::markdown_to_html(&markdown_content)
+            markdown::markdown_to_html(&markdown_content, &settings)
         } else {
-            let html_body = markdown::markdown_to_html(&markdown_content);
-            let dark_mode = is_windows_dark_mode();
-            markdown::wrap_html(&html_body, dark_mode)
+            let html_body = markdown::markdown_to_html(&markdown_content, &settings);
+            let mode = if opts.self_contained { resources::LocalFiles::Embed } else { resources::LocalFiles::FileUrl };
+            let folder = resources::document_folder(Path::new(opts.file_path.as_deref().unwrap_or_default()));
+            let html_body = resources::link_local_files(&html_body, &folder, mode);
+            let title = match opts.file_path.as_deref() {
+                Some(path) => Some(document_title(&markdown_content, path)),
+                None => frontmatter::split(&markdown_content).0.and_then(|f| f.title().map(str::to_string)),
+            };
+            markdown::wrap_html(&html_body, title.as_deref(), &settings)
         };
 
         if let Err(e) = io::stdout().write_all(output.as_bytes()) {
@@ -425,6 +876,8 @@ const DARK_CLIENT: u32 = 0x00202020;
 const IDM_FILE_OPEN: u32 = 1001;
 const IDM_FILE_EXIT: u32 = 1002;
 const IDM_HELP_ABOUT: u32 = 2001;
+const IDM_VIEW_SOURCE: u32 = 3001;
+const IDM_VIEW_FIND: u32 = 3002;
 const IDM_FILE_REGISTER: u32 = 1010;
 const IDM_FILE_UNREGISTER: u32 = 1011;
 const IDM_FILE_RECENT_BASE: u32 = 1100; // 1100-1109 for recent files
@@ -511,6 +964,7 @@ fn create_menu() -> windows::core::Result<HMENU> {
     unsafe {
         let menu_bar = CreateMenu()?;
         let file_menu = CreatePopupMenu()?;
+        let view_menu = CreatePopupMenu()?;
         let help_menu = CreatePopupMenu()?;
 
         // File menu items (native Windows menus for proper dark mode styling)
@@ -528,6 +982,12 @@ fn create_menu() -> windows::core::Result<HMENU> {
         let exit_text: Vec<u16> = "E&xit\0".encode_utf16().collect();
         AppendMenuW(file_menu, MF_STRING, IDM_FILE_EXIT as usize, PCWSTR(exit_text.as_ptr()))?;
 
+        // View menu items
+        let source_text: Vec<u16> = "Show &Source\tCtrl+U\0".encode_utf16().collect();
+        AppendMenuW(view_menu, MF_STRING, IDM_VIEW_SOURCE as usize, PCWSTR(source_text.as_ptr()))?;
+        let find_text: Vec<u16> = "&Find...\tCtrl+F\0".encode_utf16().collect();
+        AppendMenuW(view_menu, MF_STRING, IDM_VIEW_FIND as usize, PCWSTR(find_text.as_ptr()))?;
+
         // Help menu items (native Windows menus)
         let about_text: Vec<u16> = "&About MDView\0".encode_utf16().collect();
         AppendMenuW(help_menu, MF_STRING, IDM_HELP_ABOUT as usize, PCWSTR(about_text.as_ptr()))?;
@@ -536,6 +996,9 @@ fn create_menu() -> windows::core::Result<HMENU> {
         let file_text: Vec<u16> = "&File\0".encode_utf16().collect();
         AppendMenuW(menu_bar, MF_POPUP, file_menu.0 as usize, PCWSTR(file_text.as_ptr()))?;
 
+        let view_text: Vec<u16> = "&View\0".encode_utf16().collect();
+        AppendMenuW(menu_bar, MF_POPUP, view_menu.0 as usize, PCWSTR(view_text.as_ptr()))?;
+
         let help_text: Vec<u16> = "&Help\0".encode_utf16().collect();
         AppendMenuW(menu_bar, MF_POPUP, help_menu.0 as usize, PCWSTR(help_text.as_ptr()))?;
 
@@ -552,6 +1015,16 @@ fn create_accelerators() -> windows::core::Result<HACCEL> {
                 key: 'O' as u16,
                 cmd: IDM_FILE_OPEN as u16,
             },
+            ACCEL {
+                fVirt: FVIRTKEY | FCONTROL,
+                key: 'U' as u16,
+                cmd: IDM_VIEW_SOURCE as u16,
+            },
+            ACCEL {
+                fVirt: FVIRTKEY | FCONTROL,
+                key: 'F' as u16,
+                cmd: IDM_VIEW_FIND as u16,
+            },
         ];
         let haccel = CreateAcceleratorTableW(&accels)?;
         Ok(haccel)
@@ -898,71 +1371,160 @@ fn restore_window_position(hwnd: HWND) -> bool {
     }
 }
 
-/// Open a URL in the default browser
-fn open_url_in_browser(url: &str) {
+/// Theme picked with the toggle in the viewer, from the registry
+fn load_theme_choice() -> Option<settings::Theme> {
     unsafe {
-        let url_wide = U16CString::from_str(url).unwrap_or_default();
-        let open_wide = U16CString::from_str("open").unwrap_or_default();
-        ShellExecuteW(
+        let mut hkey = std::mem::zeroed();
+        let subkey: Vec<u16> = format!("{}\0", REGISTRY_KEY).encode_utf16().collect();
+        if RegOpenKeyExW(HKEY_CURRENT_USER, PCWSTR(subkey.as_ptr()), Some(0), KEY_READ, &mut hkey).is_err() {
+            return None;
+        }
+
+        let name: Vec<u16> = "Theme\0".encode_utf16().collect();
+        let mut data = vec![0u16; 16];
+        let mut data_size = (data.len() * 2) as u32;
+        let mut data_type = REG_VALUE_TYPE::default();
+        let result = RegQueryValueExW(
+            hkey,
+            PCWSTR(name.as_ptr()),
             None,
-            PCWSTR(open_wide.as_ptr()),
-            PCWSTR(url_wide.as_ptr()),
+            Some(&mut data_type),
+            Some(data.as_mut_ptr() as *mut u8),
+            Some(&mut data_size),
+        );
+        let _ = RegCloseKey(hkey);
+        if result.is_err() {
+            return None;
+        }
+        let len = data.iter().position(|&c| c == 0).unwrap_or(data.len());
+        settings::Theme::parse(&String::from_utf16_lossy(&data[..len]))
+    }
+}
+
+/// Save the theme picked with the toggle (auto, light or dark)
+fn save_theme_choice(name: &str) {
+    unsafe {
+        let mut hkey = std::mem::zeroed();
+        let subkey: Vec<u16> = format!("{}\0", REGISTRY_KEY).encode_utf16().collect();
+        let mut disposition = REG_CREATE_KEY_DISPOSITION::default();
+        if RegCreateKeyExW(
+            HKEY_CURRENT_USER,
+            PCWSTR(subkey.as_ptr()),
+            Some(0),
             None,
+            windows::Win32::System::Registry::REG_OPTION_NON_VOLATILE,
+            KEY_WRITE,
             None,
-            SW_SHOWNORMAL,
-        );
+            &mut hkey,
+            Some(&mut disposition),
+        )
+        .is_ok()
+        {
+            let value_name: Vec<u16> = "Theme\0".encode_utf16().collect();
+            let data: Vec<u16> = format!("{}\0", name).encode_utf16().collect();
+            let _ = RegSetValueExW(
+                hkey,
+                PCWSTR(value_name.as_ptr()),
+                Some(0),
+                REG_SZ,
+                Some(std::slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * 2)),
+            );
+            let _ = RegCloseKey(hkey);
+        }
     }
 }
 
-/// Handle a regular click on a link in the viewer
-fn handle_follow_link(url: &str) {
-    // External URLs: open in browser
-    if url.starts_with("http://") || url.starts_with("https://") || url.starts_with("mailto:") {
-        open_url_in_browser(url);
+/// Apply the theme picked in the page to the documents opened next, and keep
+/// it for later sessions. Like at startup it replaces the mdview.ini theme, so
+/// a document's theme and --theme still win.
+fn remember_theme(theme: settings::Theme) {
+    RENDER_SETTINGS.with(|r| r.borrow_mut().defaults.theme = theme);
+    save_theme_choice(theme.attribute().unwrap_or("auto"));
+}
+
+/// Show the remote content of the current document, after the reader asked for it
+fn load_remote_content() {
+    let Some(path) = CURRENT_FILE.with(|f| f.borrow().clone()) else {
         return;
-    }
+    };
+    LOAD_REMOTE.with(|l| *l.borrow_mut() = Some(path.clone()));
+    MAIN_HWND.with(|h| {
+        if let Some(hwnd) = h.borrow().as_ref() {
+            load_file_into_webview(*hwnd, &path);
+        }
+    });
+}
 
-    // Strip fragment (#section) for file path resolution
-    let path_part = url.split('#').next().unwrap_or(url);
-
-    // Check if it's a markdown file
-    let ext = Path::new(path_part)
-        .extension()
-        .map(|e| e.to_string_lossy().to_lowercase())
-        .unwrap_or_default();
-
-    if ext == "md" || ext == "markdown" {
-        // Resolve relative path against current file's directory
-        let resolved = CURRENT_FILE.with(|f| {
-            let current = f.borrow();
-            if let Some(current_path) = current.as_ref() {
-                if let Some(dir) = Path::new(current_path).parent() {
-                    let target = dir.join(path_part);
-                    target.canonicalize().ok().map(|p| p.to_string_lossy().to_string())
-                } else {
-                    None
-                }
-            } else {
-                None
+/// Handle a click on a link in the viewer. Ctrl+click (`external`) opens
+/// markdown files in their own application too.
+fn handle_link(url: &str, external: bool) {
+    let base_dir = CURRENT_FILE.with(|f| f.borrow().as_deref().map(|p| resources::document_folder(Path::new(p))));
+    let base_dir = base_dir.unwrap_or_else(|| env::current_dir().unwrap_or_default());
+    let hwnd = MAIN_HWND.with(|h| *h.borrow());
+    let policy = LINK_POLICY.with(|p| *p.borrow());
+    match navigation::follow(url, &base_dir, &policy, external, |link| host::confirm_link(hwnd, link)) {
+        navigation::Navigation::Document { path, fragment } => {
+            if let Some(hwnd) = hwnd {
+                // setup.md#proxy opens at the heading once the page has loaded
+                PENDING_POSITION.with(|p| *p.borrow_mut() = fragment.map(navigation::Position::Section));
+                visit_file(hwnd, &path.to_string_lossy());
             }
-        });
-
-        if let Some(resolved_path) = resolved {
-            MAIN_HWND.with(|h| {
-                if let Some(hwnd) = h.borrow().as_ref() {
-                    load_file_into_webview(*hwnd, &resolved_path);
-                }
-            });
-            return;
         }
+        navigation::Navigation::Open(target) => host::open_url_in_browser(&target),
+        navigation::Navigation::Stay => {}
     }
+}
 
-    // Fallback: try to open with default handler
-    open_url_in_browser(url);
+/// Where the reader is, for the history
+fn current_location() -> Option<navigation::Location> {
+    let path = CURRENT_FILE.with(|f| f.borrow().clone())?;
+    Some(navigation::Location { path: PathBuf::from(path), scroll: SCROLL.with(|s| *s.borrow()) })
 }
 
-/// Load a file into the WebView
-fn load_file_into_webview(hwnd: HWND, file_path: &str) {
+/// Show another file, so that Back returns to the current one
+fn visit_file(hwnd: HWND, file_path: &str) {
+    let current = current_location();
+    if load_file_into_webview(hwnd, file_path)
+        && let Some(current) = current
+    {
+        HISTORY.with(|h| h.borrow_mut().visit(current));
+    }
+}
+
+/// Go one place back or forward in the history
+fn go_through_history(forward: bool) {
+    let (Some(current), Some(hwnd)) = (current_location(), MAIN_HWND.with(|h| *h.borrow())) else {
+        return;
+    };
+    let target = HISTORY.with(|h| {
+        let mut history = h.borrow_mut();
+        if forward { history.forward(current.clone()) } else { history.back(current.clone()) }
+    });
+    let Some(target) = target else {
+        return;
+    };
+    if target.path == current.path {
+        SCROLL.with(|s| *s.borrow_mut() = target.scroll);
+        post_message(&protocol::HostMessage::ScrollTo { y: target.scroll });
+    } else {
+        PENDING_POSITION.with(|p| *p.borrow_mut() = Some(navigation::Position::Scroll(target.scroll)));
+        load_file_into_webview(hwnd, &target.path.to_string_lossy());
+    }
+}
+
+/// Send a message to the page in the viewer
+fn post_message(message: &protocol::HostMessage) {
+    CONTROLLER.with(|c| {
+        if let Some(controller) = c.borrow().as_ref() {
+            if let Ok(webview) = unsafe { controller.CoreWebView2() } {
+                host::post_to(&webview, message);
+            }
+        }
+    });
+}
+
+/// Load a file into the WebView. Returns whether it could be read.
+fn load_file_into_webview(hwnd: HWND, file_path: &str) -> bool {
     // Read file
     let content = match fs::read_to_string(file_path) {
         Ok(c) => c,
@@ -979,20 +1541,43 @@ fn load_file_into_webview(hwnd: HWND, file_path: &str) {
                     MB_OK | MB_ICONERROR,
                 );
             }
-            return;
+            PENDING_POSITION.with(|p| *p.borrow_mut() = None);
+            return false;
+        }
+    };
+
+    // --trust covers the file named on the command line, not the ones opened from it
+    let same_file = CURRENT_FILE.with(|f| f.borrow().as_deref() == Some(file_path));
+    if !same_file {
+        RENDER_SETTINGS.with(|r| r.borrow_mut().overrides.retain(|c| !matches!(c, settings::Change::Trust(_))));
+    }
+
+    // Links and Back and Forward pick a position; a document shown again keeps its own
+    let scroll = SCROLL.with(|s| *s.borrow());
+    let position = PENDING_POSITION.with(|p| {
+        let mut pending = p.borrow_mut();
+        if same_file && pending.is_none() {
+            *pending = Some(navigation::Position::Scroll(scroll));
         }
+        pending.clone()
+    });
+    let scroll = match position {
+        Some(navigation::Position::Scroll(y)) => y,
+        _ => 0.0,
     };
+    SCROLL.with(|s| *s.borrow_mut() = scroll);
 
     // Convert to HTML
-    let html_body = markdown::markdown_to_html(&content);
-    let dark_mode = is_windows_dark_mode();
-    let full_html = markdown::wrap_html(&html_body, dark_mode);
+    let title = document_title(&content, file_path);
+    let (full_html, rendering) = build_page(&content, &title, file_path);
+    RENDERING.with(|r| *r.borrow_mut() = rendering);
 
     // Navigate WebView
     CONTROLLER.with(|c| {
         if let Some(controller) = c.borrow().as_ref() {
             unsafe {
                 if let Ok(webview) = controller.CoreWebView2() {
+                    host::map_folder(&webview, &resources::document_folder(Path::new(file_path)));
                     let html_wide = pwstr_from_str(&full_html);
                     let _ = webview.NavigateToString(PCWSTR(html_wide.as_ptr()));
                 }
@@ -1001,11 +1586,7 @@ fn load_file_into_webview(hwnd: HWND, file_path: &str) {
     });
 
     // Update window title
-    let filename = Path::new(file_path)
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| file_path.to_string());
-    let title: Vec<u16> = format!("{} - MDView\0", filename).encode_utf16().collect();
+    let title: Vec<u16> = format!("{} - MDView\0", title).encode_utf16().collect();
     unsafe {
         let _ = SetWindowTextW(hwnd, PCWSTR(title.as_ptr()));
     }
@@ -1015,6 +1596,81 @@ fn load_file_into_webview(hwnd: HWND, file_path: &str) {
         *f.borrow_mut() = Some(file_path.to_string());
     });
     add_to_recent_files(file_path);
+    watch_files(hwnd);
+    true
+}
+
+/// Watch the files of the current document, when live reload is on
+fn watch_files(hwnd: HWND) {
+    let files = RENDERING.with(|r| r.borrow().files.clone());
+    let watcher = (LIVE_RELOAD.with(|l| *l.borrow()) && !files.is_empty()).then(|| host::watch_files(hwnd, files));
+    WATCHER.with(|w| *w.borrow_mut() = watcher);
+}
+
+/// Show the current document again after it changed on disk. When the page
+/// around it stays the same, only the changed blocks are replaced.
+fn reload_current_file(hwnd: HWND) {
+    let Some(path) = CURRENT_FILE.with(|f| f.borrow().clone()) else {
+        return;
+    };
+    // Still being written: the next change reloads
+    let Ok(content) = fs::read_to_string(&path) else {
+        return;
+    };
+    let (_, rendering) = build_page(&content, &document_title(&content, &path), &path);
+    match RENDERING.with(|r| rendering.patch(&r.borrow())) {
+        Some(patch) => {
+            post_message(&patch);
+            RENDERING.with(|r| *r.borrow_mut() = rendering);
+            watch_files(hwnd);
+        }
+        None => {
+            load_file_into_webview(hwnd, &path);
+        }
+    }
+}
+
+/// Build the viewer page for a document, with the source pane next to it when enabled.
+/// Images next to `file_path` are loaded through the mapping of `host::map_folder`.
+fn build_page(markdown_content: &str, title: &str, file_path: &str) -> (String, page::Rendering) {
+    let mut settings = RENDER_SETTINGS.with(|r| r.borrow().resolve(markdown_content));
+    let load_remote = LOAD_REMOTE.with(|l| l.borrow().as_deref() == Some(file_path));
+    if settings.remote == settings::RemoteContent::Ask && load_remote {
+        settings.remote = settings::RemoteContent::Allow;
+    }
+    let source = SHOW_SOURCE
+        .with(|s| *s.borrow())
+        .then(|| source::render_source_to_html(markdown_content, &settings.extensions, true));
+    let controls = page::Controls { theme_toggle: true, source: source.as_deref() };
+    page::build(markdown_content, &settings, Path::new(file_path), Some(title), controls)
+}
+
+/// Regenerate the `<!-- toc -->` list of a file. Returns whether it was stale;
+/// with `check` the file is left alone.
+fn update_toc_file(path: &str, render: &settings::RenderSettings, check: bool) -> Result<bool, String> {
+    let markdown_content = read_input(Some(path)).map_err(|e| e.to_string())?;
+    let updated = toc::update_markers(&markdown_content, &render.resolve(&markdown_content))
+        .ok_or_else(|| format!("No <!-- toc --> ... <!-- tocstop --> markers in {}", path))?;
+    if updated == markdown_content {
+        return Ok(false);
+    }
+    if !check {
+        fs::write(path, updated).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+    }
+    Ok(true)
+}
+
+/// The front matter title, or the file name
+fn document_title(markdown_content: &str, file_path: &str) -> String {
+    frontmatter::split(markdown_content)
+        .0
+        .and_then(|f| f.title().map(str::to_string))
+        .unwrap_or_else(|| {
+            Path::new(file_path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| file_path.to_string())
+        })
 }
 
 /// Handle dropped files
@@ -1027,13 +1683,8 @@ fn handle_drop_files(hwnd: HWND, hdrop: HDROP) {
 
         if len > 0 {
             let path = String::from_utf16_lossy(&buffer[..len as usize]);
-            // Check if it's a markdown file
-            let ext = Path::new(&path)
-                .extension()
-                .map(|e| e.to_string_lossy().to_lowercase())
-                .unwrap_or_default();
-            if ext == "md" || ext == "markdown" {
-                load_file_into_webview(hwnd, &path);
+            if navigation::is_markdown_file(Path::new(&path)) {
+                visit_file(hwnd, &path);
             } else {
                 let msg: Vec<u16> = "Only Markdown files (.md, .markdown) are supported.\0"
                     .encode_utf16()
@@ -1543,6 +2194,7 @@ fn run_gui_inner(title: &str, html: &str, file_path: Option<&str>) -> windows::c
                 *f.borrow_mut() = Some(path.to_string());
             });
             add_to_recent_files(path);
+            watch_files(hwnd);
         }
 
         // Update recent files menu
@@ -1658,6 +2310,20 @@ thread_local! {
     static MENU_HANDLE: RefCell<Option<HMENU>> = const { RefCell::new(None) };
     static ACCEL_HANDLE: RefCell<Option<HACCEL>> = const { RefCell::new(None) };
     static MAIN_HWND: RefCell<Option<HWND>> = const { RefCell::new(None) };
+    static SHOW_SOURCE: RefCell<bool> = const { RefCell::new(false) };
+    static RENDER_SETTINGS: RefCell<settings::RenderSettings> = RefCell::new(settings::RenderSettings::default());
+    static LINK_POLICY: RefCell<link_policy::LinkPolicy> = RefCell::new(link_policy::LinkPolicy::default());
+    /// Document whose remote content the reader chose to load
+    static LOAD_REMOTE: RefCell<Option<String>> = const { RefCell::new(None) };
+    static HISTORY: RefCell<navigation::History> = RefCell::new(navigation::History::default());
+    /// Scroll position the page last reported
+    static SCROLL: RefCell<f64> = const { RefCell::new(0.0) };
+    /// Where to put the page once it is ready
+    static PENDING_POSITION: RefCell<Option<navigation::Position>> = const { RefCell::new(None) };
+    /// The document on the page, to patch it when it changes
+    static RENDERING: RefCell<page::Rendering> = RefCell::new(page::Rendering::default());
+    static LIVE_RELOAD: RefCell<bool> = const { RefCell::new(true) };
+    static WATCHER: RefCell<Option<watch::Watcher>> = const { RefCell::new(None) };
 }
 
 fn init_webview2_gui(hwnd: HWND, html: &str) -> windows::core::Result<()> {
@@ -1715,6 +2381,10 @@ fn init_webview2_gui(hwnd: HWND, html: &str) -> windows::core::Result<()> {
                                 let _ = settings.SetIsStatusBarEnabled(false);
                             }
 
+                            if let Some(path) = CURRENT_FILE.with(|f| f.borrow().clone()) {
+                                host::map_folder(&webview, &resources::document_folder(Path::new(&path)));
+                            }
+
                             // Use NavigateToString for direct HTML loading
                             let html_wide = pwstr_from_str(&html_for_nav);
                             if let Err(e) = webview.NavigateToString(PCWSTR(html_wide.as_ptr())) {
@@ -1730,21 +2400,28 @@ fn init_webview2_gui(hwnd: HWND, html: &str) -> windows::core::Result<()> {
                                             let msg_str = message_ptr.to_string().unwrap_or_default();
                                             windows::Win32::System::Com::CoTaskMemFree(Some(message_ptr.0 as *const _));
 
-                                            // Extract URL from JSON message
-                                            if let Some(start) = msg_str.find("\"url\":\"") {
-                                                let url_start = start + 7;
-                                                if let Some(end) = msg_str[url_start..].find('"') {
-                                                    let url = msg_str[url_start..url_start + end].to_string();
-                                                    // Unescape JSON backslashes
-                                                    let url = url.replace("\\\\", "\\").replace("\\/", "/");
-
-                                                    if msg_str.contains("openLink") {
-                                                        // Ctrl+click: always open in browser
-                                                        open_url_in_browser(&url);
-                                                    } else if msg_str.contains("followLink") {
-                                                        handle_follow_link(&url);
+                                            match protocol::PageMessage::parse(&msg_str) {
+                                                Ok(protocol::PageMessage::FollowLink { url }) => handle_link(&url, false),
+                                                // Ctrl+click: open outside the viewer
+                                                Ok(protocol::PageMessage::OpenLink { url }) => handle_link(&url, true),
+                                                Ok(protocol::PageMessage::Theme { theme }) => remember_theme(theme),
+                                                Ok(protocol::PageMessage::LoadRemote) => load_remote_content(),
+                                                Ok(protocol::PageMessage::Ready) => {
+                                                    if let Some(position) = PENDING_POSITION.with(|p| p.borrow_mut().take()) {
+                                                        post_message(&position.message());
+                                                    }
+                                                }
+                                                Ok(protocol::PageMessage::Scroll { y }) => SCROLL.with(|s| *s.borrow_mut() = y),
+                                                // A #section link: Back returns to where the reader was
+                                                Ok(protocol::PageMessage::Anchor) => {
+                                                    if let Some(current) = current_location() {
+                                                        HISTORY.with(|h| h.borrow_mut().visit(current));
                                                     }
                                                 }
+                                                Ok(protocol::PageMessage::Back) => go_through_history(false),
+                                                Ok(protocol::PageMessage::Forward) => go_through_history(true),
+                                                Ok(protocol::PageMessage::Close) => {}
+                                                Err(e) => eprintln!("Ignoring message from the page: {}", e),
                                             }
                                         }
                                     }
@@ -1852,6 +2529,12 @@ unsafe extern "system" fn window_proc(
                     IDM_FILE_UNREGISTER,
                     if registered { MF_BYCOMMAND } else { MF_BYCOMMAND | MF_GRAYED },
                 );
+                let show_source = SHOW_SOURCE.with(|s| *s.borrow());
+                CheckMenuItem(
+                    menu,
+                    IDM_VIEW_SOURCE,
+                    (if show_source { MF_BYCOMMAND | MF_CHECKED } else { MF_BYCOMMAND | MF_UNCHECKED }).0,
+                );
                 DefWindowProcW(hwnd, msg, wparam, lparam)
             }
         }
@@ -1860,7 +2543,7 @@ unsafe extern "system" fn window_proc(
             match cmd_id {
                 IDM_FILE_OPEN => {
                     if let Some(path) = show_open_file_dialog(hwnd) {
-                        load_file_into_webview(hwnd, &path);
+                        visit_file(hwnd, &path);
                     }
                     LRESULT(0)
                 }
@@ -1882,6 +2565,21 @@ unsafe extern "system" fn window_proc(
                     show_about_dialog(hwnd);
                     LRESULT(0)
                 }
+                IDM_VIEW_SOURCE => {
+                    SHOW_SOURCE.with(|s| {
+                        let mut show = s.borrow_mut();
+                        *show = !*show;
+                    });
+                    let current = CURRENT_FILE.with(|f| f.borrow().clone());
+                    if let Some(path) = current {
+                        load_file_into_webview(hwnd, &path);
+                    }
+                    LRESULT(0)
+                }
+                IDM_VIEW_FIND => {
+                    post_message(&protocol::HostMessage::ShowFind);
+                    LRESULT(0)
+                }
                 id if id >= IDM_FILE_RECENT_BASE && id < IDM_FILE_RECENT_BASE + 10 => {
                     let index = (id - IDM_FILE_RECENT_BASE) as usize;
                     RECENT_FILES.with(|files| {
@@ -1889,7 +2587,7 @@ unsafe extern "system" fn window_proc(
                         if let Some(path) = files.get(index) {
                             let path = path.clone();
                             drop(files);
-                            load_file_into_webview(hwnd, &path);
+                            visit_file(hwnd, &path);
                         }
                     });
                     LRESULT(0)
@@ -1897,6 +2595,10 @@ unsafe extern "system" fn window_proc(
                 _ => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
             }
         }
+        host::WM_FILE_CHANGED => {
+            reload_current_file(hwnd);
+            LRESULT(0)
+        }
         WM_DROPFILES => {
             let hdrop = HDROP(wparam.0 as _);
             handle_drop_files(hwnd, hdrop);
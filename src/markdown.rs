@@ -1,135 +1,772 @@
-use pulldown_cmark::{html, Options, Parser};
-
-pub fn markdown_to_html(markdown: &str) -> String {
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_TABLES);
-    options.insert(Options::ENABLE_FOOTNOTES);
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_TASKLISTS);
-
-    let parser = Parser::new_ext(markdown, options);
-    let mut html_output = String::new();
-    html::push_html(&mut html_output, parser);
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+
+use crate::frontmatter;
+use crate::resources;
+use crate::sanitize;
+use crate::settings::{RemoteContent, Settings};
+use crate::slug::Slugger;
+use crate::style;
+use crate::toc;
+
+/// Optional syntax on top of the always-on basics (tables, footnotes,
+/// strikethrough and task lists). Shared by every renderer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Extensions {
+    pub smart_punctuation: bool,
+    pub heading_attributes: bool,
+    pub definition_lists: bool,
+    pub superscript: bool,
+    pub subscript: bool,
+    pub wikilinks: bool,
+    pub math: bool,
+    /// Strict GitHub Flavored Markdown: GitHub's behavior and none of the other extensions
+    pub gfm: bool,
+    /// Render every soft line break as a hard break
+    pub hard_breaks: bool,
+}
+
+impl Extensions {
+    /// Names accepted by [`Extensions::set`], as used on the command line and in mdview.ini
+    #[allow(dead_code)]
+    pub const NAMES: [&'static str; 9] = [
+        "smart",
+        "heading-attributes",
+        "definition-lists",
+        "superscript",
+        "subscript",
+        "wikilinks",
+        "math",
+        "gfm",
+        "hard-breaks",
+    ];
+
+    /// Switch an extension by name. Returns false if the name is unknown.
+    pub fn set(&mut self, name: &str, enabled: bool) -> bool {
+        let flag = match name.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "smart" | "smart-punctuation" => &mut self.smart_punctuation,
+            "heading-attributes" | "attributes" => &mut self.heading_attributes,
+            "definition-lists" | "deflists" => &mut self.definition_lists,
+            "superscript" | "sup" => &mut self.superscript,
+            "subscript" | "sub" => &mut self.subscript,
+            "wikilinks" => &mut self.wikilinks,
+            "math" => &mut self.math,
+            "gfm" => &mut self.gfm,
+            "hard-breaks" | "breaks" => &mut self.hard_breaks,
+            _ => return false,
+        };
+        *flag = enabled;
+        true
+    }
+
+    /// Parser options for these extensions
+    pub fn options(&self) -> Options {
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_TABLES);
+        options.insert(Options::ENABLE_FOOTNOTES);
+        options.insert(Options::ENABLE_STRIKETHROUGH);
+        options.insert(Options::ENABLE_TASKLISTS);
+        if self.gfm {
+            options.insert(Options::ENABLE_GFM);
+            return options;
+        }
+
+        let flags = [
+            (self.smart_punctuation, Options::ENABLE_SMART_PUNCTUATION),
+            (self.heading_attributes, Options::ENABLE_HEADING_ATTRIBUTES),
+            (self.definition_lists, Options::ENABLE_DEFINITION_LIST),
+            (self.superscript, Options::ENABLE_SUPERSCRIPT),
+            (self.subscript, Options::ENABLE_SUBSCRIPT),
+            (self.wikilinks, Options::ENABLE_WIKILINKS),
+            (self.math, Options::ENABLE_MATH),
+        ];
+        for (enabled, flag) in flags {
+            options.set(flag, enabled);
+        }
+        options
+    }
+
+    /// Parse markdown with these extensions
+    pub fn parse<'a>(&self, markdown: &'a str) -> impl Iterator<Item = Event<'a>> {
+        let hard_breaks = self.hard_breaks && !self.gfm;
+        Parser::new_ext(markdown, self.options()).map(move |event| match event {
+            Event::SoftBreak if hard_breaks => Event::HardBreak,
+            event => event,
+        })
+    }
+}
+
+/// Parse a document body with the extensions and heading numbers of `settings`.
+/// Every heading gets an id.
+pub fn parse<'a>(body: &'a str, settings: &Settings) -> Vec<Event<'a>> {
+    parse_part(body, settings, &mut Numbering::default())
+}
+
+/// Parse one part of a document shown in parts, like a slide, numbering its
+/// headings after those of the parts before
+pub fn parse_part<'a>(body: &'a str, settings: &Settings, numbering: &mut Numbering) -> Vec<Event<'a>> {
+    let mut events: Vec<Event> = settings.extensions.parse(body).collect();
+    assign_heading_ids(&mut events);
+    if settings.number_headings { numbering.apply(events) } else { events }
+}
+
+/// Use GitHub's slug of the heading text as id, unless the author set one with `{#id}`
+fn assign_heading_ids(events: &mut [Event]) {
+    let mut slugger = Slugger::default();
+    for event in events.iter() {
+        if let Event::Start(Tag::Heading { id: Some(id), .. }) = event {
+            slugger.reserve(id);
+        }
+    }
+    let slugs: Vec<(usize, String)> = events
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| matches!(e, Event::Start(Tag::Heading { id: None, .. })))
+        .map(|(i, _)| (i, slugger.slug(&heading_text(&events[i + 1..]))))
+        .collect();
+    for (i, slug) in slugs {
+        if let Event::Start(Tag::Heading { id, .. }) = &mut events[i] {
+            *id = Some(slug.into());
+        }
+    }
+}
+
+/// Plain text of a heading, given the events after its start
+pub fn heading_text(events: &[Event]) -> String {
+    events
+        .iter()
+        .take_while(|e| !matches!(e, Event::End(TagEnd::Heading(_))))
+        .filter_map(|e| match e {
+            Event::Text(text) | Event::Code(text) | Event::InlineMath(text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Section numbers of a document, kept between the parts of a document shown
+/// in parts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Numbering {
+    /// Level of the headings with a one part number, once there are headings
+    top: Option<usize>,
+    counters: [usize; 6],
+    /// The parts arrive one by one, so the first heading decides the top level
+    streamed: bool,
+}
+
+impl Numbering {
+    /// Numbering for a document whose later parts are not known yet. A first
+    /// heading of level 1 is the title; any other is numbered.
+    #[allow(dead_code)]
+    pub fn streamed() -> Self {
+        Self { streamed: true, ..Self::default() }
+    }
+
+    /// Numbering for the parts of `markdown`, with the top level of the whole document
+    #[allow(dead_code)]
+    pub fn for_document(markdown: &str, settings: &Settings) -> Self {
+        let events: Vec<Event> = settings.extensions.parse(frontmatter::split(markdown).1).collect();
+        Self { top: top_level(&heading_levels(&events)), ..Self::default() }
+    }
+
+    /// Count the headings of a part without showing it
+    #[allow(dead_code)]
+    pub fn skip(&mut self, markdown: &str, settings: &Settings) {
+        self.apply(settings.extensions.parse(frontmatter::split(markdown).1).collect());
+    }
+
+    /// Insert section numbers at the start of each heading. Without a top level
+    /// yet, the headings of `events` decide it.
+    fn apply<'a>(&mut self, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+        let levels = heading_levels(&events);
+        if self.top.is_none() {
+            self.top = match levels.first() {
+                Some(&level) if self.streamed => Some(if level == 1 { 2 } else { level }),
+                _ => top_level(&levels),
+            };
+        }
+        let Some(top) = self.top else {
+            return events;
+        };
+
+        let mut numbered = Vec::with_capacity(events.len() + levels.len());
+        for event in events {
+            let level = match &event {
+                Event::Start(Tag::Heading { level, .. }) => Some(*level as usize).filter(|&l| l >= top),
+                _ => None,
+            };
+            numbered.push(event);
+            if let Some(level) = level {
+                self.counters[level - 1] += 1;
+                self.counters[level..].fill(0);
+                let number: Vec<String> = self.counters[top - 1..level].iter().map(|n| n.to_string()).collect();
+                numbered.push(Event::Text(format!("{} ", number.join(".")).into()));
+            }
+        }
+        numbered
+    }
+}
+
+fn heading_levels(events: &[Event]) -> Vec<usize> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            Event::Start(Tag::Heading { level, .. }) => Some(*level as usize),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The level numbered with one part. A lone top level heading is the document
+/// title and is not numbered.
+fn top_level(levels: &[usize]) -> Option<usize> {
+    let top = *levels.iter().min()?;
+    Some(if levels.iter().filter(|&&l| l == top).count() == 1 { top + 1 } else { top })
+}
+
+/// Render a document. Front matter is shown as a collapsed metadata table.
+/// Raw HTML is sanitized unless the settings trust the document, and remote
+/// content is blocked unless they allow it.
+pub fn markdown_to_html(markdown: &str, settings: &Settings) -> String {
+    let (front_matter, body) = frontmatter::split(markdown);
+    let mut html_output = front_matter.map(|f| f.to_html()).unwrap_or_default();
+    let mut events = parse(body, settings);
+    // Found before sanitizing, which drops the `<!-- toc -->` comment
+    let toc_range = toc::position(&events, settings.toc);
+    if !settings.trust {
+        sanitize_events(&mut events);
+    }
+    if let Some(range) = toc_range {
+        let entries = toc::entries(&events, settings.toc_depth.unwrap_or(toc::DEFAULT_DEPTH));
+        events.splice(range, [Event::Html(toc::to_html(&entries).into())]);
+    }
+    let events = events.into_iter().flat_map(|event| {
+        // Permalink shown when hovering the heading
+        let anchor = match &event {
+            Event::Start(Tag::Heading { id: Some(id), .. }) => Some(Event::InlineHtml(
+                format!("<a class=\"anchor\" href=\"#{}\" aria-hidden=\"true\">#</a>", escape_html(id)).into(),
+            )),
+            _ => None,
+        };
+        std::iter::once(event).chain(anchor)
+    });
+    html::push_html(&mut html_output, events);
+    if settings.remote != RemoteContent::Allow {
+        html_output = resources::block_remote_content(&html_output);
+    }
     html_output
 }
 
-pub fn wrap_html(content: &str, dark_mode: bool) -> String {
-    let bg_color = if dark_mode { "#1e1e1e" } else { "#ffffff" };
-    let text_color = if dark_mode { "#d4d4d4" } else { "#24292e" };
-    let code_bg = if dark_mode { "#2d2d2d" } else { "#f6f8fa" };
-    let link_color = if dark_mode { "#58a6ff" } else { "#0366d6" };
-    let border_color = if dark_mode { "#444" } else { "#e1e4e8" };
+/// Sanitize what the document wrote: raw HTML, link and image URLs and heading
+/// attributes. Markup the viewer adds, like permalinks and the table of
+/// contents, comes later. An HTML block is sanitized as a whole, in the event
+/// of its first line.
+fn sanitize_events(events: &mut [Event]) {
+    let mut i = 0;
+    while i < events.len() {
+        if events[i] == Event::Start(Tag::HtmlBlock) {
+            let end = events[i..].iter().position(|e| *e == Event::End(TagEnd::HtmlBlock)).map_or(events.len(), |n| i + n);
+            let mut html = String::new();
+            for event in &mut events[i + 1..end] {
+                if let Event::Html(line) = event {
+                    html.push_str(line);
+                    *line = "".into();
+                }
+            }
+            if i + 1 < end {
+                events[i + 1] = Event::Html(sanitize::sanitize_html(&html).into());
+            }
+            i = end;
+            continue;
+        }
+        match &mut events[i] {
+            Event::Html(html) | Event::InlineHtml(html) => *html = sanitize::sanitize_html(html).into(),
+            Event::Start(Tag::Link { dest_url, .. }) if !sanitize::attribute_allowed("a", "href", dest_url) => {
+                *dest_url = "".into()
+            }
+            Event::Start(Tag::Image { dest_url, .. }) if !sanitize::attribute_allowed("img", "src", dest_url) => {
+                *dest_url = "".into()
+            }
+            Event::Start(Tag::Heading { classes, attrs, .. }) => {
+                classes.retain(|class| !class.starts_with("mdview-"));
+                attrs.retain(|(name, value)| {
+                    !matches!(name.as_ref(), "class" | "id" | "name")
+                        && sanitize::attribute_allowed("h1", name, value.as_deref().unwrap_or_default())
+                });
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// A standalone page, as exported with `--html`. It has no script, and its
+/// policy allows none. Colors follow the reader's system unless the settings
+/// pick a theme.
+#[allow(dead_code)]
+pub fn wrap_html(content: &str, title: Option<&str>, settings: &Settings) -> String {
+    page(content, title, settings, None)
+}
+
+/// A page for the viewer window: links, keys and the sidebar talk to the host
+/// through the WebView bridge script
+pub fn wrap_viewer_html(content: &str, title: Option<&str>, settings: &Settings) -> String {
+    page(content, title, settings, Some(VIEWER_SCRIPT))
+}
+
+/// Script of viewer pages. It talks to the host with the messages of
+/// `protocol`. Clicks on links to other documents and pages go to the host;
+/// `#section` links scroll as usual, and tell the host for its history.
+/// The host keeps the scroll position, to restore it on Back, and sends
+/// changed documents to patch into the page.
+const VIEWER_SCRIPT: &str = r##"
+var viewerScript = document.currentScript;
+function mdviewPost(type, fields) {
+    var message = fields || {};
+    message.v = 1;
+    message.type = type;
+    window.chrome.webview.postMessage(message);
+}
+window.chrome.webview.addEventListener('message', function(e) {
+    var message = e.data;
+    if (!message || message.v !== 1) return;
+    if (message.type === 'find') {
+        findFromHost(message);
+    } else if (message.type === 'showFind') {
+        openFindBar();
+    } else if (message.type === 'copy') {
+        document.execCommand('copy');
+    } else if (message.type === 'selectAll') {
+        document.execCommand('selectAll');
+    } else if (message.type === 'scrollTo') {
+        window.scrollTo(0, message.y);
+    } else if (message.type === 'showSection') {
+        showSection(message.id);
+    } else if (message.type === 'patch') {
+        patchContent(message.html);
+    }
+});
+// Ids and names in the document's own HTML carry a prefix, as on GitHub
+function findSection(id) {
+    return document.getElementById(id) || document.getElementById('user-content-' + id) ||
+        document.getElementsByName('user-content-' + id)[0] || null;
+}
+function showSection(id) {
+    var target = findSection(id) || findSection(id.toLowerCase());
+    if (target) target.scrollIntoView(); else showNotice('Section not found: ' + id);
+}
+// A short message at the bottom of the window
+var noticeTimer = null;
+function showNotice(text) {
+    var notice = document.querySelector('.mdview-notice');
+    if (!notice) {
+        notice = document.createElement('div');
+        notice.className = 'mdview-notice';
+        notice.setAttribute('role', 'status');
+        document.body.appendChild(notice);
+    }
+    notice.textContent = text;
+    notice.hidden = false;
+    clearTimeout(noticeTimer);
+    noticeTimer = setTimeout(function() { notice.hidden = true; }, 3000);
+}
+// Find: every match is marked, the current one is marked twice. A match that
+// crosses inline markup, like `foo **bar**`, has a mark in each text node. The
+// find bar and the host's find messages share the search.
+var finder = {bar: null, key: null, matches: [], current: -1};
+var MAX_MATCHES = 10000;
+var BLOCKS = 'p, li, dt, dd, td, th, h1, h2, h3, h4, h5, h6, pre, blockquote, figcaption, caption, summary, div, body';
+function clearMatches() {
+    finder.matches.forEach(function(marks) {
+        marks.forEach(function(mark) {
+            var parent = mark.parentNode;
+            if (!parent) return;
+            parent.replaceChild(document.createTextNode(mark.textContent), mark);
+            parent.normalize();
+        });
+    });
+    finder.matches = [];
+    finder.current = -1;
+    finder.key = null;
+}
+// Returns an error for a bad regular expression
+function markMatches(query) {
+    clearMatches();
+    if (!query.text) return null;
+    // Spaces also match the line breaks of the source
+    var source = query.regex ? query.text : query.text.replace(/[.*+?^${}()|[\]\\]/g, '\\$&').replace(/\s+/g, '\\s+');
+    if (query.wholeWords) source = '\\b(?:' + source + ')\\b';
+    var pattern;
+    try { pattern = new RegExp(source, query.matchCase ? 'g' : 'gi'); } catch (err) { return 'Invalid pattern'; }
+    var skip = 'script, style, .mdview-findbar, .mdview-notice, .mdview-sidebar, .mdview-theme-toggle';
+    var walker = document.createTreeWalker(document.body, NodeFilter.SHOW_TEXT, {
+        acceptNode: function(node) {
+            return node.parentNode.closest(skip) ? NodeFilter.FILTER_REJECT : NodeFilter.FILTER_ACCEPT;
+        }
+    });
+    // The text of each block is searched as a whole
+    var groups = [], block = null;
+    while (walker.nextNode()) {
+        var node = walker.currentNode, parent = node.parentNode.closest(BLOCKS);
+        if (parent !== block || !groups.length) groups.push({nodes: [], text: ''});
+        block = parent;
+        groups[groups.length - 1].nodes.push(node);
+        groups[groups.length - 1].text += node.nodeValue;
+    }
+    groups.forEach(function(group) {
+        var ranges = [], match;
+        pattern.lastIndex = 0;
+        while (finder.matches.length + ranges.length < MAX_MATCHES && (match = pattern.exec(group.text)) !== null) {
+            if (match[0] === '') { pattern.lastIndex++; continue; }
+            ranges.push([match.index, match.index + match[0].length]);
+        }
+        if (!ranges.length) return;
+        var found = ranges.map(function() { return []; }), start = 0;
+        group.nodes.forEach(function(node) {
+            var text = node.nodeValue, end = start + text.length, last = 0;
+            var parts = document.createDocumentFragment();
+            ranges.forEach(function(range, i) {
+                var from = Math.max(range[0], start) - start, to = Math.min(range[1], end) - start;
+                if (from >= to) return;
+                parts.appendChild(document.createTextNode(text.slice(last, from)));
+                var mark = document.createElement('mark');
+                mark.className = 'mdview-match';
+                mark.textContent = text.slice(from, to);
+                parts.appendChild(mark);
+                found[i].push(mark);
+                last = to;
+            });
+            start = end;
+            if (!last) return;
+            parts.appendChild(document.createTextNode(text.slice(last)));
+            node.parentNode.replaceChild(parts, node);
+        });
+        finder.matches.push.apply(finder.matches, found);
+    });
+    return null;
+}
+// Search for `query`, or move to the next or previous match of the same search.
+// Returns what to tell the reader.
+function findText(query, backwards, fromStart) {
+    var key = JSON.stringify(query), error = null;
+    if (key !== finder.key || fromStart) {
+        error = markMatches(query);
+        finder.key = key;
+    }
+    var count = finder.matches.length;
+    if (error || !count) return error || (query.text ? 'No matches' : '');
+    if (finder.current >= 0) {
+        finder.matches[finder.current].forEach(function(mark) { mark.classList.remove('mdview-match-current'); });
+        finder.current = (finder.current + (backwards ? count - 1 : 1)) % count;
+    } else if (fromStart) {
+        finder.current = backwards ? count - 1 : 0;
+    } else {
+        // The first match from where the reader is
+        finder.current = 0;
+        while (finder.current < count - 1 && finder.matches[finder.current][0].getBoundingClientRect().top < 0) finder.current++;
+    }
+    var marks = finder.matches[finder.current];
+    marks.forEach(function(mark) { mark.classList.add('mdview-match-current'); });
+    marks[0].scrollIntoView({block: 'center'});
+    return (finder.current + 1) + ' of ' + count + (count === MAX_MATCHES ? '+' : '');
+}
+function findBarQuery() {
+    var query = {text: finder.bar.querySelector('.mdview-findbar-text').value};
+    finder.bar.querySelectorAll('input[data-option]').forEach(function(box) {
+        query[box.getAttribute('data-option')] = box.checked;
+    });
+    return query;
+}
+function findFromBar(backwards) {
+    finder.bar.querySelector('.mdview-findbar-count').textContent = findText(findBarQuery(), backwards, false);
+}
+function openFindBar() {
+    if (!finder.bar) {
+        var bar = document.createElement('div');
+        bar.className = 'mdview-findbar';
+        bar.innerHTML = '<input class="mdview-findbar-text" type="text" placeholder="Find" aria-label="Find">' +
+            '<label title="Match case"><input type="checkbox" data-option="matchCase">Aa</label>' +
+            '<label title="Whole words"><input type="checkbox" data-option="wholeWords">W</label>' +
+            '<label title="Regular expression"><input type="checkbox" data-option="regex">.*</label>' +
+            '<span class="mdview-findbar-count" role="status"></span>' +
+            '<button data-step="previous" title="Previous (Shift+F3)">&#8593;</button>' +
+            '<button data-step="next" title="Next (F3)">&#8595;</button>' +
+            '<button data-step="close" title="Close (Esc)">&#10005;</button>';
+        document.body.appendChild(bar);
+        finder.bar = bar;
+        var text = bar.querySelector('.mdview-findbar-text');
+        text.addEventListener('input', function() { findFromBar(false); });
+        text.addEventListener('keydown', function(e) {
+            if (e.key === 'Enter') {
+                e.preventDefault();
+                findFromBar(e.shiftKey);
+            }
+        });
+        bar.querySelectorAll('input[data-option]').forEach(function(box) {
+            box.addEventListener('change', function() { findFromBar(false); });
+        });
+        bar.addEventListener('click', function(e) {
+            var step = e.target.getAttribute('data-step');
+            if (step === 'close') closeFindBar(); else if (step) findFromBar(step === 'previous');
+        });
+    }
+    finder.bar.hidden = false;
+    var input = finder.bar.querySelector('.mdview-findbar-text');
+    input.focus();
+    input.select();
+}
+function closeFindBar() {
+    finder.bar.hidden = true;
+    clearMatches();
+}
+function findBarOpen() {
+    return finder.bar !== null && !finder.bar.hidden;
+}
+// A search started by the host, like Total Commander's find
+function findFromHost(message) {
+    var query = {text: message.text, matchCase: message.matchCase, wholeWords: message.wholeWords, regex: message.regex};
+    var result = findText(query, message.backwards, message.fromStart);
+    if (findBarOpen()) {
+        finder.bar.querySelector('.mdview-findbar-text').value = query.text;
+        finder.bar.querySelectorAll('input[data-option]').forEach(function(box) {
+            box.checked = query[box.getAttribute('data-option')];
+        });
+        finder.bar.querySelector('.mdview-findbar-count').textContent = result;
+    } else {
+        showNotice(result);
+    }
+}
+// Live reload: the blocks that are the same at the start and at the end of the
+// document stay, so the reader keeps their place; the ones between are replaced.
+var CHROME = 'script, .mdview-theme-toggle, .mdview-sidebar, .mdview-sidebar-toggle, .mdview-remote-bar, ' +
+    '.mdview-findbar, .mdview-notice';
+function patchContent(html) {
+    clearMatches();
+    var template = document.createElement('template');
+    template.innerHTML = html;
+    var fresh = Array.prototype.slice.call(template.content.children);
+    var old = Array.prototype.filter.call(document.body.children, function(node) { return !node.matches(CHROME); });
+    var start = 0, end = 0;
+    while (start < old.length && start < fresh.length && old[start].isEqualNode(fresh[start])) start++;
+    while (end < old.length - start && end < fresh.length - start &&
+        old[old.length - 1 - end].isEqualNode(fresh[fresh.length - 1 - end])) end++;
+    var next = end ? old[old.length - end] : viewerScript;
+    old.slice(start, old.length - end).forEach(function(node) { node.remove(); });
+    fresh.slice(start, fresh.length - end).forEach(function(node) { document.body.insertBefore(node, next); });
+    // Mark the matches again, without moving away from the reader's place
+    if (findBarOpen()) {
+        var query = findBarQuery();
+        var error = markMatches(query);
+        finder.key = JSON.stringify(query);
+        var count = finder.matches.length;
+        finder.bar.querySelector('.mdview-findbar-count').textContent =
+            error || (count ? count + ' matches' : query.text ? 'No matches' : '');
+    }
+    window.dispatchEvent(new Event('scroll'));
+}
+// Scroll positions go to the host at most every 200 ms, and right before leaving
+var scrollTimer = null;
+function reportScroll() {
+    clearTimeout(scrollTimer);
+    scrollTimer = null;
+    mdviewPost('scroll', {y: window.scrollY});
+}
+window.addEventListener('scroll', function() {
+    if (scrollTimer === null) scrollTimer = setTimeout(reportScroll, 200);
+}, { passive: true });
+window.addEventListener('load', function() { mdviewPost('ready'); });
+function goThroughHistory(type) {
+    reportScroll();
+    mdviewPost(type);
+}
+document.addEventListener('click', function(e) {
+    var link = e.target.closest('a');
+    if (link) {
+        var href = link.getAttribute('href');
+        if (!href) return;
+        reportScroll();
+        if (href.charAt(0) === '#') {
+            var id = href.slice(1);
+            try { id = decodeURIComponent(id); } catch (err) {}
+            var target = id && findSection(id);
+            if (id && !target) {
+                e.preventDefault();
+                showNotice('Section not found: ' + id);
+                return;
+            }
+            mdviewPost('anchor');
+            if (target && target.id !== id) {
+                e.preventDefault();
+                target.scrollIntoView();
+            }
+            return;
+        }
+        e.preventDefault();
+        mdviewPost(e.ctrlKey ? 'openLink' : 'followLink', {url: href});
+    }
+});
+document.addEventListener('keydown', function(e) {
+    var editing = e.target.isContentEditable || /^(INPUT|TEXTAREA|SELECT)$/.test(e.target.tagName);
+    if (e.key === 'Escape' && findBarOpen()) {
+        closeFindBar();
+    } else if (e.key === 'Escape') {
+        mdviewPost('close');
+    } else if (e.ctrlKey && (e.key === 'f' || e.key === 'F')) {
+        e.preventDefault();
+        openFindBar();
+    } else if (e.key === 'F3') {
+        e.preventDefault();
+        if (findBarOpen() && finder.bar.querySelector('.mdview-findbar-text').value) findFromBar(e.shiftKey); else openFindBar();
+    } else if ((e.altKey && e.key === 'ArrowLeft') || (e.key === 'Backspace' && !editing)) {
+        e.preventDefault();
+        goThroughHistory('back');
+    } else if (e.altKey && e.key === 'ArrowRight') {
+        e.preventDefault();
+        goThroughHistory('forward');
+    }
+});
+// Mouse buttons 4 and 5
+document.addEventListener('mouseup', function(e) {
+    if (e.button === 3 || e.button === 4) {
+        e.preventDefault();
+        goThroughHistory(e.button === 3 ? 'back' : 'forward');
+    }
+});
+(function() {
+    var button = document.querySelector('.mdview-load-remote');
+    if (!button) return;
+    button.addEventListener('click', function() {
+        mdviewPost('loadRemote');
+    });
+})();
+(function() {
+    // Theme toggle: auto (follow the system), light, dark. The host remembers the choice.
+    var toggle = document.querySelector('.mdview-theme-toggle');
+    if (!toggle) return;
+    var root = document.documentElement;
+    var next = {auto: 'light', light: 'dark', dark: 'auto'};
+    function show() { toggle.title = 'Theme: ' + (root.getAttribute('data-theme') || 'auto'); }
+    toggle.addEventListener('click', function() {
+        var theme = next[root.getAttribute('data-theme') || 'auto'];
+        if (theme === 'auto') root.removeAttribute('data-theme'); else root.setAttribute('data-theme', theme);
+        show();
+        mdviewPost('theme', {theme: theme});
+    });
+    show();
+})();
+(function() {
+    var sidebar = document.querySelector('.mdview-sidebar');
+    if (!sidebar) return;
+    if (sidebar.getAttribute('data-open') === 'true') document.body.classList.add('mdview-sidebar-open');
+    document.querySelector('.mdview-sidebar-toggle').addEventListener('click', function() {
+        document.body.classList.toggle('mdview-sidebar-open');
+    });
+    // Highlight the section being read. Headings are looked up each time, as
+    // live reload replaces them.
+    var links = Array.prototype.slice.call(sidebar.querySelectorAll('a[href^="#"]'));
+    var ids = links.map(function(link) { return decodeURIComponent(link.getAttribute('href').slice(1)); });
+    function update() {
+        var current = -1;
+        for (var i = 0; i < ids.length; i++) {
+            var target = document.getElementById(ids[i]);
+            if (target && target.getBoundingClientRect().top <= 80) current = i;
+        }
+        links.forEach(function(link, i) { link.classList.toggle('active', i === current); });
+    }
+    window.addEventListener('scroll', update, { passive: true });
+    update();
+})();
+"##;
+
+/// Content-Security-Policy of a page. Only the page's own script, marked with
+/// `nonce`, may run; trusted documents keep their scripts. Nothing is loaded
+/// from the network unless the settings allow remote content.
+fn content_security_policy(settings: &Settings, nonce: Option<&str>) -> String {
+    let sources = match settings.remote {
+        RemoteContent::Allow => "*".to_string(),
+        _ => format!("file: https://{}", resources::DOCUMENT_HOST),
+    };
+    if settings.trust {
+        return format!("default-src {} data: blob: 'unsafe-inline'; base-uri 'none'", sources);
+    }
+    let script = nonce.map_or("'none'".to_string(), |n| format!("'nonce-{}'", n));
+    format!(
+        "default-src 'none'; script-src {}; style-src 'unsafe-inline'; img-src {} data:; base-uri 'none'; form-action 'none'",
+        script, sources
+    )
+}
+
+/// A value for the script nonce that the document cannot guess
+fn new_nonce() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    let time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    hasher.write_u128(time.as_nanos());
+    format!("{:016x}", hasher.finish())
+}
+
+fn page(content: &str, title: Option<&str>, settings: &Settings, script: Option<&str>) -> String {
+    let title = title.map(|t| format!("<title>{}</title>\n", escape_html(t))).unwrap_or_default();
+    // Trusted documents may have inline scripts of their own, which a nonce would block
+    let nonce = script.filter(|_| !settings.trust).map(|_| new_nonce());
+    let csp = content_security_policy(settings, nonce.as_deref());
+    let script = match (script, &nonce) {
+        (Some(script), Some(nonce)) => format!("<script nonce=\"{}\">{}</script>\n", nonce, script),
+        (Some(script), None) => format!("<script>{}</script>\n", script),
+        (None, _) => String::new(),
+    };
+    let css = style::stylesheet(settings);
+    let theme = settings.theme.attribute().map(|t| format!(" data-theme=\"{}\"", t)).unwrap_or_default();
 
     format!(
         r#"<!DOCTYPE html>
-<html>
+<html{theme}>
 <head>
 <meta charset="UTF-8">
-<style>
-body {{
-    font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif;
-    font-size: 14px;
-    line-height: 1.6;
-    padding: 20px;
-    max-width: 900px;
-    margin: 0 auto;
-    background-color: {bg_color};
-    color: {text_color};
-}}
-a {{ color: {link_color}; text-decoration: none; }}
-a:hover {{ text-decoration: underline; }}
-code {{
-    background-color: {code_bg};
-    padding: 0.2em 0.4em;
-    border-radius: 3px;
-    font-family: "Cascadia Code", "Fira Code", Consolas, monospace;
-    font-size: 85%;
-}}
-pre {{
-    background-color: {code_bg};
-    padding: 16px;
-    overflow: auto;
-    border-radius: 6px;
-}}
-pre code {{
-    background-color: transparent;
-    padding: 0;
-}}
-blockquote {{
-    border-left: 4px solid {border_color};
-    margin: 0;
-    padding-left: 16px;
-    color: {text_color};
-    opacity: 0.8;
-}}
-table {{
-    border-collapse: collapse;
-    width: 100%;
-}}
-th, td {{
-    border: 1px solid {border_color};
-    padding: 8px 12px;
-    text-align: left;
-}}
-th {{
-    background-color: {code_bg};
-}}
-img {{
-    max-width: 100%;
-}}
-h1, h2 {{
-    border-bottom: 1px solid {border_color};
-    padding-bottom: 0.3em;
-}}
-hr {{
-    border: none;
-    border-top: 1px solid {border_color};
-}}
-input[type="checkbox"] {{
-    margin-right: 0.5em;
-}}
-a {{ cursor: pointer; }}
-</style>
+<meta http-equiv="Content-Security-Policy" content="{csp}">
+{title}<style>
+{css}</style>
 </head>
 <body>
 {content}
-<script>
-document.addEventListener('click', function(e) {{
-    var link = e.target.closest('a');
-    if (link) {{
-        var href = link.getAttribute('href');
-        if (!href || href.charAt(0) === '#') return;
-        e.preventDefault();
-        if (e.ctrlKey) {{
-            window.chrome.webview.postMessage({{type: 'openLink', url: href}});
-        }} else {{
-            window.chrome.webview.postMessage({{type: 'followLink', url: href}});
-        }}
-    }}
-}});
-document.addEventListener('keydown', function(e) {{
-    if (e.key === 'Escape') {{
-        window.chrome.webview.postMessage({{type: 'close'}});
-    }}
-}});
-</script>
-</body>
+{script}</body>
 </html>"#
     )
 }
 
 #[allow(dead_code)]
-pub fn markdown_to_plain_text(markdown: &str) -> String {
-    use pulldown_cmark::{Event, Tag, TagEnd};
-
-    let options = Options::empty();
-    let parser = Parser::new_ext(markdown, options);
+pub fn markdown_to_plain_text(markdown: &str, settings: &Settings) -> String {
+    use pulldown_cmark::{Tag, TagEnd};
 
     let mut output = String::new();
+    let events = parse(frontmatter::split(markdown).1, settings);
+    let toc_range = toc::position(&events, settings.toc);
+    let entries = toc::entries(&events, settings.toc_depth.unwrap_or(toc::DEFAULT_DEPTH));
 
-    for event in parser {
+    for (i, event) in events.into_iter().enumerate() {
+        if let Some(range) = &toc_range {
+            if i == range.start {
+                output.push_str("Contents\n");
+                for (depth, text) in toc::outline(&entries) {
+                    output.push_str(&format!("{}- {}\n", "  ".repeat(depth + 1), text));
+                }
+                output.push('\n');
+            }
+            if range.contains(&i) {
+                continue;
+            }
+        }
         match event {
-            Event::Text(text) => output.push_str(&text),
+            Event::Text(text) | Event::InlineMath(text) => output.push_str(&text),
+            Event::DisplayMath(math) => {
+                output.push('\n');
+                output.push_str(&math);
+                output.push('\n');
+            }
+            Event::TaskListMarker(checked) => output.push_str(if checked { "[x] " } else { "[ ] " }),
             Event::Code(code) => {
                 output.push('`');
                 output.push_str(&code);
@@ -148,6 +785,13 @@ pub fn markdown_to_plain_text(markdown: &str) -> String {
             Event::End(TagEnd::Item) => output.push('\n'),
             Event::Start(Tag::BlockQuote(_)) => output.push_str("> "),
             Event::End(TagEnd::BlockQuote(_)) => output.push('\n'),
+            Event::End(TagEnd::TableCell) => output.push('\t'),
+            Event::End(TagEnd::TableHead | TagEnd::TableRow) => output.push('\n'),
+            Event::End(TagEnd::Table) => output.push('\n'),
+            Event::End(TagEnd::DefinitionListTitle) => output.push('\n'),
+            Event::Start(Tag::DefinitionListDefinition) => output.push_str("    "),
+            Event::End(TagEnd::DefinitionListDefinition) => output.push('\n'),
+            Event::End(TagEnd::DefinitionList) => output.push('\n'),
             _ => {}
         }
     }
@@ -162,8 +806,8 @@ mod tests {
     #[test]
     fn test_basic_markdown() {
         let md = "# Hello\n\nThis is **bold** and *italic*.";
-        let html = markdown_to_html(md);
-        assert!(html.contains("<h1>Hello</h1>"));
+        let html = markdown_to_html(md, &Settings::default());
+        assert!(html.contains("<h1 id=\"hello\"><a class=\"anchor\" href=\"#hello\" aria-hidden=\"true\">#</a>Hello</h1>"));
         assert!(html.contains("<strong>bold</strong>"));
         assert!(html.contains("<em>italic</em>"));
     }
@@ -171,8 +815,144 @@ mod tests {
     #[test]
     fn test_code_block() {
         let md = "```rust\nfn main() {}\n```";
-        let html = markdown_to_html(md);
+        let html = markdown_to_html(md, &Settings::default());
         assert!(html.contains("<code"));
         assert!(html.contains("fn main()"));
     }
+
+    #[test]
+    fn test_extensions() {
+        let md = "# Title {#top}\n\nIndex ~2~ and power ^2^, \"quoted\"\nnext line\n\nTerm\n: Definition";
+        let plain = markdown_to_html(md, &Settings::default());
+        assert!(plain.contains("{#top}"));
+        assert!(!plain.contains("<dl>"));
+
+        let mut extensions = Extensions::default();
+        for name in Extensions::NAMES {
+            assert!(extensions.set(name, true));
+        }
+        assert!(!extensions.set("unknown", true));
+        extensions.gfm = false;
+        let mut settings = Settings { extensions, ..Settings::default() };
+        let html = markdown_to_html(md, &settings);
+        assert!(html.contains("<h1 id=\"top\">"));
+        assert!(html.contains("<sub>2</sub>"));
+        assert!(html.contains("<sup>2</sup>"));
+        assert!(html.contains("\u{201c}quoted\u{201d}"));
+        assert!(html.contains("<br />"));
+        assert!(html.contains("<dl>"));
+
+        // Strict GFM ignores the other extensions
+        settings.extensions.gfm = true;
+        let gfm = markdown_to_html(md, &settings);
+        assert!(gfm.contains("{#top}") && !gfm.contains("<br />"));
+        assert!(markdown_to_plain_text("a\nb", &settings).contains("a\nb"));
+    }
+
+    #[test]
+    fn test_heading_ids() {
+        let md = "## Setup\n\n## Setup\n\n## Install `mdview` (Windows)!\n\n## Custom {#setup-1}\n";
+        let extensions = Extensions { heading_attributes: true, ..Extensions::default() };
+        let settings = Settings { extensions, ..Settings::default() };
+        let html = markdown_to_html(md, &settings);
+        assert!(html.contains("<h2 id=\"setup\">"));
+        assert!(html.contains("<h2 id=\"setup-2\">"));
+        assert!(html.contains("<h2 id=\"install-mdview-windows\">"));
+        assert!(html.contains("<h2 id=\"setup-1\">"));
+    }
+
+    #[test]
+    fn test_front_matter_is_hidden() {
+        let md = "---\ntitle: Notes & more\n---\n# Heading\n";
+        let html = markdown_to_html(md, &Settings::default());
+        assert!(html.starts_with("<details class=\"front-matter\">"));
+        assert!(!html.contains("<hr />"));
+        assert_eq!(markdown_to_plain_text(md, &Settings::default()), "Heading");
+        assert!(wrap_html(&html, Some("Notes & more"), &Settings::default()).contains("<title>Notes &amp; more</title>"));
+    }
+
+    #[test]
+    fn test_number_headings() {
+        let md = "# Title\n\n## Intro\n\n## Usage\n\n### Options\n\n## End\n";
+        let settings = Settings { number_headings: true, ..Settings::default() };
+        assert_eq!(
+            markdown_to_plain_text(md, &settings),
+            "Title\n\n1 Intro\n\n2 Usage\n\n2.1 Options\n\n3 End"
+        );
+        let html = markdown_to_html("# A\n\n# B\n\n## C\n", &settings);
+        assert!(html.contains("#</a>1 A</h1>") && html.contains("#</a>2.1 C</h2>"));
+        // Ids come from the heading text, not the numbers
+        assert!(html.contains("<h2 id=\"c\">"));
+
+        // Parts of a streamed document continue the numbers
+        let mut numbering = Numbering::streamed();
+        let text = |events: Vec<Event>| heading_text(&events[1..]);
+        assert_eq!(text(parse_part("# Title\n", &settings, &mut numbering)), "Title");
+        assert_eq!(text(parse_part("## Intro\n", &settings, &mut numbering)), "1 Intro");
+        assert_eq!(text(parse_part("## Usage\n", &settings, &mut numbering)), "2 Usage");
+    }
+
+    #[test]
+    fn test_viewer_script_only_in_viewer() {
+        let settings = Settings::default();
+        let export = wrap_html("<p>x</p>", None, &settings);
+        assert!(!export.contains("<script") && !export.contains("postMessage"));
+        assert!(export.contains("<html>\n") && export.contains("prefers-color-scheme: dark"));
+        let dark = Settings { theme: crate::settings::Theme::Dark, ..Settings::default() };
+        assert!(wrap_html("", None, &dark).contains("<html data-theme=\"dark\">"));
+        assert!(export.contains("content=\"default-src 'none'; script-src 'none';"));
+
+        let viewer = wrap_viewer_html("<p>x</p>", None, &settings);
+        let nonce = viewer.split("<script nonce=\"").nth(1).and_then(|s| s.split('"').next()).unwrap();
+        assert_eq!(nonce.len(), 16);
+        assert!(viewer.contains(&format!("script-src 'nonce-{}';", nonce)));
+        assert!(viewer.contains("postMessage"));
+        assert_ne!(wrap_viewer_html("", None, &settings), wrap_viewer_html("", None, &settings));
+    }
+
+    #[test]
+    fn test_raw_html_is_sanitized() {
+        let md = "<div onclick=\"x()\">Hi</div>\n\n<script>alert(1)</script>\n\nText <img src=x onerror=alert(1)>\n";
+        let html = markdown_to_html(md, &Settings::default());
+        assert_eq!(html, "<div>Hi</div>\n\n<p>Text <img src=\"x\"></p>\n");
+        let trusted = markdown_to_html(md, &Settings { trust: true, ..Settings::default() });
+        assert!(trusted.contains("<script>alert(1)</script>") && trusted.contains("onerror"));
+
+        // Only what the document wrote is sanitized: the viewer's own markup keeps its classes
+        let md = "# Intro {.mdview-notice .note onclick=x}\n\n<a class=\"mdview-load-remote\" id=\"go\">Load</a>\n\n\
+                  [x](javascript:alert(1)) ![y](data:text/html,z)\n";
+        let settings = Settings { extensions: Extensions { heading_attributes: true, ..Extensions::default() }, ..Settings::default() };
+        let html = markdown_to_html(md, &settings);
+        assert!(html.starts_with("<h1 id=\"intro\" class=\"note\"><a class=\"anchor\" href=\"#intro\""));
+        assert!(html.contains("<a id=\"user-content-go\">Load</a>"));
+        assert!(html.contains("<a href=\"\">x</a> <img src=\"\" alt=\"y\" />"));
+    }
+
+    #[test]
+    fn test_remote_content() {
+        let md = "![logo](https://example.com/logo.png) ![local](logo.png)\n";
+        let blocked = markdown_to_html(md, &Settings::default());
+        assert!(blocked.contains("<span class=\"mdview-remote\" title=\"Remote image not loaded\">logo"));
+        assert!(blocked.contains("<img src=\"logo.png\" alt=\"local\" />"));
+        assert!(wrap_html("", None, &Settings::default()).contains("img-src file: https://document.mdview.example data:;"));
+
+        let allowed = Settings { remote: RemoteContent::Allow, ..Settings::default() };
+        assert!(markdown_to_html(md, &allowed).contains("<img src=\"https://example.com/logo.png\""));
+        assert!(wrap_html("", None, &allowed).contains("img-src * data:;"));
+    }
+
+    #[test]
+    fn test_table_of_contents() {
+        let md = "# Guide\n\n[[_TOC_]]\n\n## Install\n\n### Windows\n\n#### Details\n";
+        let html = markdown_to_html(md, &Settings::default());
+        assert!(!html.contains("[[_TOC_]]"));
+        assert!(html.contains("<nav class=\"toc\">\n<ul>\n<li><a href=\"#guide\">Guide</a>"));
+        assert!(html.contains("<a href=\"#windows\">Windows</a>") && !html.contains("<a href=\"#details\">"));
+
+        let settings = Settings { toc: true, toc_depth: Some(2), ..Settings::default() };
+        assert_eq!(
+            markdown_to_plain_text("# A\n\n## B\n\n### C\n", &settings),
+            "Contents\n  - A\n    - B\n\nA\n\nB\n\nC"
+        );
+    }
 }
@@ -0,0 +1,1141 @@
+//! Text-art rendering of simple mermaid diagrams (flowcharts and sequence diagrams)
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Render a mermaid diagram as box drawing (or ASCII) art.
+///
+/// Returns an error describing why the diagram could not be drawn, so the
+/// caller can fall back to showing the source.
+pub fn render(source: &str, unicode: bool) -> Result<String, String> {
+    let statements = split_statements(source);
+    let (header, body) = statements.split_first().ok_or("empty diagram")?;
+    let mut words = header.split_whitespace();
+    let kind = words.next().unwrap_or_default();
+
+    let mut canvas = Canvas::new(unicode);
+    let notes = match kind {
+        "graph" | "flowchart" => {
+            let direction = match words.next().map(|d| d.to_ascii_uppercase()).as_deref() {
+                None | Some("TD") | Some("TB") => Direction::Down,
+                Some("LR") => Direction::Right,
+                Some(other) => return Err(format!("flowchart direction {} is not supported", other)),
+            };
+            let graph = Flowchart::parse(body)?;
+            graph.draw(direction, &mut canvas)
+        }
+        "sequenceDiagram" => Sequence::parse(body)?.draw(&mut canvas),
+        other => return Err(format!("'{}' diagrams are not supported", other)),
+    };
+
+    let mut output = canvas.to_string();
+    for note in notes {
+        output.push_str(&note);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Split the source into statements at newlines and `;`, dropping `%%` comments
+fn split_statements(source: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    for line in source.lines() {
+        let line = line.trim();
+        if line.starts_with("%%") {
+            continue;
+        }
+        let mut current = String::new();
+        let mut in_quotes = false;
+        let mut depth = 0i32;
+        for ch in line.chars() {
+            match ch {
+                '"' => in_quotes = !in_quotes,
+                '[' | '(' | '{' if !in_quotes => depth += 1,
+                ']' | ')' | '}' if !in_quotes => depth -= 1,
+                ';' if !in_quotes && depth <= 0 => {
+                    statements.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(ch);
+        }
+        statements.push(current);
+    }
+    statements
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn text_width(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Split a label at `<br>` tags and strip surrounding quotes
+fn label_lines(label: &str) -> Vec<String> {
+    let label = label.trim().trim_matches('"');
+    let mut text = label.to_string();
+    for tag in ["<br/>", "<br />", "<BR>", "<br>"] {
+        text = text.replace(tag, "\n");
+    }
+    text.lines().map(|l| l.trim().to_string()).collect()
+}
+
+// ============================================================================
+// Canvas with line merging
+// ============================================================================
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineStyle {
+    Solid,
+    Dotted,
+    Thick,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cell {
+    Empty,
+    Line(u8, LineStyle),
+    Char(char),
+}
+
+struct Canvas {
+    rows: Vec<Vec<Cell>>,
+    unicode: bool,
+}
+
+impl Canvas {
+    fn new(unicode: bool) -> Self {
+        Self { rows: Vec::new(), unicode }
+    }
+
+    fn cell_mut(&mut self, x: usize, y: usize) -> &mut Cell {
+        if self.rows.len() <= y {
+            self.rows.resize(y + 1, Vec::new());
+        }
+        let row = &mut self.rows[y];
+        if row.len() <= x {
+            row.resize(x + 1, Cell::Empty);
+        }
+        &mut row[x]
+    }
+
+    fn get(&self, x: usize, y: usize) -> Cell {
+        self.rows.get(y).and_then(|r| r.get(x)).copied().unwrap_or(Cell::Empty)
+    }
+
+    fn put(&mut self, x: usize, y: usize, ch: char) {
+        *self.cell_mut(x, y) = Cell::Char(ch);
+    }
+
+    fn add_line(&mut self, x: usize, y: usize, mask: u8, style: LineStyle) {
+        let cell = self.cell_mut(x, y);
+        *cell = match *cell {
+            Cell::Line(existing, existing_style) => {
+                let style = if existing_style == style { style } else { LineStyle::Solid };
+                Cell::Line(existing | mask, style)
+            }
+            Cell::Char(c) => Cell::Char(c),
+            Cell::Empty => Cell::Line(mask, style),
+        };
+    }
+
+    /// Draw a horizontal or vertical segment between two points
+    fn segment(&mut self, from: (usize, usize), to: (usize, usize), style: LineStyle) {
+        let (x0, y0) = from;
+        let (x1, y1) = to;
+        if y0 == y1 {
+            let (start, end) = (x0.min(x1), x0.max(x1));
+            for x in start..=end {
+                let mut mask = 0;
+                if x > start {
+                    mask |= LEFT;
+                }
+                if x < end {
+                    mask |= RIGHT;
+                }
+                if mask != 0 {
+                    self.add_line(x, y0, mask, style);
+                }
+            }
+        } else {
+            let (start, end) = (y0.min(y1), y0.max(y1));
+            for y in start..=end {
+                let mut mask = 0;
+                if y > start {
+                    mask |= UP;
+                }
+                if y < end {
+                    mask |= DOWN;
+                }
+                if mask != 0 {
+                    self.add_line(x0, y, mask, style);
+                }
+            }
+        }
+    }
+
+    /// Draw an orthogonal path through `points`, ending in `head`
+    fn path(&mut self, points: &[(usize, usize)], style: LineStyle, head: Head) {
+        let points: Vec<(usize, usize)> = points.windows(2).fold(vec![points[0]], |mut acc, pair| {
+            if pair[1] != pair[0] {
+                acc.push(pair[1]);
+            }
+            acc
+        });
+        for pair in points.windows(2) {
+            self.segment(pair[0], pair[1], style);
+        }
+        if points.len() < 2 {
+            return;
+        }
+        let (prev, last) = (points[points.len() - 2], points[points.len() - 1]);
+        let ch = match head {
+            Head::None => return,
+            Head::Circle => 'o',
+            Head::Cross => 'x',
+            Head::Arrow => match (last.0.cmp(&prev.0), last.1.cmp(&prev.1), self.unicode) {
+                (_, std::cmp::Ordering::Greater, true) => '▼',
+                (_, std::cmp::Ordering::Less, true) => '▲',
+                (std::cmp::Ordering::Greater, _, true) => '▶',
+                (_, _, true) => '◀',
+                (_, std::cmp::Ordering::Greater, false) => 'v',
+                (_, std::cmp::Ordering::Less, false) => '^',
+                (std::cmp::Ordering::Greater, _, false) => '>',
+                (_, _, false) => '<',
+            },
+        };
+        self.put(last.0, last.1, ch);
+    }
+
+    /// Write text if every cell it covers is empty or a plain line
+    fn try_text(&mut self, x: usize, y: usize, text: &str) -> bool {
+        let free = (0..text_width(text)).all(|i| match self.get(x + i, y) {
+            Cell::Empty => true,
+            Cell::Line(mask, _) => mask & !(LEFT | RIGHT) == 0,
+            Cell::Char(_) => false,
+        });
+        if free {
+            self.text(x, y, text);
+        }
+        free
+    }
+
+    fn text(&mut self, x: usize, y: usize, text: &str) {
+        for (i, ch) in text.chars().enumerate() {
+            self.put(x + i, y, ch);
+        }
+    }
+
+    /// Draw a node box with its label centered inside
+    fn node_box(&mut self, x: usize, y: usize, w: usize, h: usize, shape: Shape, label: &[String]) {
+        let (right, bottom) = (x + w - 1, y + h - 1);
+        self.segment((x, y), (right, y), LineStyle::Solid);
+        self.segment((x, bottom), (right, bottom), LineStyle::Solid);
+        self.segment((x, y), (x, bottom), LineStyle::Solid);
+        self.segment((right, y), (right, bottom), LineStyle::Solid);
+
+        let corners = match (shape, self.unicode) {
+            (Shape::Rect, _) => None,
+            (Shape::Round, true) => Some(['╭', '╮', '╰', '╯']),
+            (Shape::Round, false) => Some(['.', '.', '\'', '\'']),
+            (Shape::Decision, true) => Some(['╱', '╲', '╲', '╱']),
+            (Shape::Decision, false) => Some(['/', '\\', '\\', '/']),
+        };
+        if let Some([tl, tr, bl, br]) = corners {
+            self.put(x, y, tl);
+            self.put(right, y, tr);
+            self.put(x, bottom, bl);
+            self.put(right, bottom, br);
+        }
+
+        for (i, line) in label.iter().enumerate() {
+            let pad = (w - 2 - text_width(line)) / 2;
+            self.text(x + 1 + pad, y + 1 + i, line);
+        }
+    }
+
+    fn line_char(&self, mask: u8, style: LineStyle) -> char {
+        if !self.unicode {
+            return match mask {
+                m if m & (UP | DOWN) != 0 && m & (LEFT | RIGHT) != 0 => '+',
+                m if m & (UP | DOWN) != 0 => if style == LineStyle::Dotted { ':' } else { '|' },
+                _ => match style {
+                    LineStyle::Solid => '-',
+                    LineStyle::Dotted => '.',
+                    LineStyle::Thick => '=',
+                },
+            };
+        }
+        match mask {
+            m if m == UP || m == DOWN || m == UP | DOWN => match style {
+                LineStyle::Solid => '│',
+                LineStyle::Dotted => '┆',
+                LineStyle::Thick => '┃',
+            },
+            m if m == LEFT || m == RIGHT || m == LEFT | RIGHT => match style {
+                LineStyle::Solid => '─',
+                LineStyle::Dotted => '┄',
+                LineStyle::Thick => '━',
+            },
+            m if m == DOWN | RIGHT => '┌',
+            m if m == DOWN | LEFT => '┐',
+            m if m == UP | RIGHT => '└',
+            m if m == UP | LEFT => '┘',
+            m if m == UP | DOWN | RIGHT => '├',
+            m if m == UP | DOWN | LEFT => '┤',
+            m if m == LEFT | RIGHT | DOWN => '┬',
+            m if m == LEFT | RIGHT | UP => '┴',
+            _ => '┼',
+        }
+    }
+}
+
+impl std::fmt::Display for Canvas {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in &self.rows {
+            let line: String = row
+                .iter()
+                .map(|cell| match *cell {
+                    Cell::Empty => ' ',
+                    Cell::Char(c) => c,
+                    Cell::Line(mask, style) => self.line_char(mask, style),
+                })
+                .collect();
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Flowcharts
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Down,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Rect,
+    Round,
+    Decision,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Head {
+    None,
+    Arrow,
+    Circle,
+    Cross,
+}
+
+struct Node {
+    label: Vec<String>,
+    shape: Shape,
+}
+
+struct Edge {
+    from: usize,
+    to: usize,
+    label: String,
+    style: LineStyle,
+    head: Head,
+}
+
+struct Flowchart {
+    nodes: Vec<Node>,
+    ids: HashMap<String, usize>,
+    edges: Vec<Edge>,
+}
+
+/// Character scanner over one statement
+struct Scanner {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Scanner {
+    fn new(text: &str) -> Self {
+        Self { chars: text.chars().collect(), pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn rest(&self) -> String {
+        self.chars[self.pos..].iter().collect()
+    }
+
+    /// Read until `close`, honoring a quoted label, and consume the closer
+    fn read_until(&mut self, close: &str) -> Option<String> {
+        let mut text = String::new();
+        let mut in_quotes = false;
+        while self.pos < self.chars.len() {
+            if !in_quotes && self.starts_with(close) {
+                self.pos += close.chars().count();
+                return Some(text);
+            }
+            let ch = self.chars[self.pos];
+            if ch == '"' {
+                in_quotes = !in_quotes;
+            }
+            text.push(ch);
+            self.pos += 1;
+        }
+        None
+    }
+}
+
+const NODE_SHAPES: [(&str, &str, Shape); 12] = [
+    ("([", "])", Shape::Round),
+    ("((", "))", Shape::Round),
+    ("[[", "]]", Shape::Rect),
+    ("[(", ")]", Shape::Rect),
+    ("{{", "}}", Shape::Decision),
+    ("[/", "/]", Shape::Rect),
+    ("[\\", "\\]", Shape::Rect),
+    ("[", "]", Shape::Rect),
+    ("(", ")", Shape::Round),
+    ("{", "}", Shape::Decision),
+    (">", "]", Shape::Rect),
+    ("", "", Shape::Rect),
+];
+
+const EDGE_CLOSERS: [&str; 8] = ["-->", "==>", ".->", "---", "===", "-.-", "--x", "--o"];
+
+impl Flowchart {
+    fn parse(statements: &[String]) -> Result<Self, String> {
+        let mut chart = Flowchart { nodes: Vec::new(), ids: HashMap::new(), edges: Vec::new() };
+        for statement in statements {
+            let keyword = statement.split_whitespace().next().unwrap_or_default();
+            if matches!(
+                keyword,
+                "subgraph" | "end" | "direction" | "classDef" | "class" | "style" | "linkStyle" | "click"
+            ) {
+                continue;
+            }
+            chart.parse_statement(statement)?;
+        }
+        if chart.nodes.is_empty() {
+            return Err("flowchart has no nodes".to_string());
+        }
+        if chart.nodes.len() > 60 {
+            return Err("flowchart is too large to draw".to_string());
+        }
+        Ok(chart)
+    }
+
+    fn parse_statement(&mut self, statement: &str) -> Result<(), String> {
+        let mut scanner = Scanner::new(statement);
+        let mut previous = self.parse_node_group(&mut scanner, statement)?;
+        loop {
+            scanner.skip_ws();
+            if scanner.peek().is_none() {
+                return Ok(());
+            }
+            let (label, style, head) =
+                parse_edge(&mut scanner).ok_or_else(|| format!("cannot parse '{}'", statement))?;
+            let next = self.parse_node_group(&mut scanner, statement)?;
+            if let Some(style) = style {
+                for &from in &previous {
+                    for &to in &next {
+                        self.edges.push(Edge { from, to, label: label.clone(), style, head });
+                    }
+                }
+            }
+            previous = next;
+        }
+    }
+
+    /// Parse `A`, `A[label]` or `A & B`
+    fn parse_node_group(&mut self, scanner: &mut Scanner, statement: &str) -> Result<Vec<usize>, String> {
+        let mut group = vec![self.parse_node(scanner, statement)?];
+        loop {
+            scanner.skip_ws();
+            if scanner.peek() != Some('&') {
+                return Ok(group);
+            }
+            scanner.pos += 1;
+            group.push(self.parse_node(scanner, statement)?);
+        }
+    }
+
+    fn parse_node(&mut self, scanner: &mut Scanner, statement: &str) -> Result<usize, String> {
+        scanner.skip_ws();
+        let start = scanner.pos;
+        while scanner.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            scanner.pos += 1;
+        }
+        let id: String = scanner.chars[start..scanner.pos].iter().collect();
+        if id.is_empty() {
+            return Err(format!("expected a node in '{}'", statement));
+        }
+
+        let mut definition = None;
+        for (open, close, shape) in NODE_SHAPES {
+            if !open.is_empty() && scanner.starts_with(open) {
+                scanner.pos += open.chars().count();
+                let label = scanner
+                    .read_until(close)
+                    .ok_or_else(|| format!("unterminated node '{}'", id))?;
+                definition = Some((label_lines(&label), shape));
+                break;
+            }
+        }
+        // Skip a `:::className` suffix
+        if scanner.starts_with(":::") {
+            scanner.pos += 3;
+            while scanner.peek().is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+                scanner.pos += 1;
+            }
+        }
+
+        let index = match self.ids.get(&id) {
+            Some(&index) => index,
+            None => {
+                self.nodes.push(Node { label: vec![id.clone()], shape: Shape::Rect });
+                self.ids.insert(id, self.nodes.len() - 1);
+                self.nodes.len() - 1
+            }
+        };
+        if let Some((label, shape)) = definition {
+            self.nodes[index] = Node { label, shape };
+        }
+        Ok(index)
+    }
+
+    /// Assign each node to a layer: longest path from the roots, ignoring back edges
+    fn ranks(&self) -> (Vec<usize>, Vec<bool>) {
+        let n = self.nodes.len();
+        let mut outgoing: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for (i, edge) in self.edges.iter().enumerate() {
+            outgoing[edge.from].push(i);
+        }
+
+        // Depth-first search to find edges that close a cycle
+        let mut back = vec![false; self.edges.len()];
+        let mut state = vec![0u8; n]; // 0 = new, 1 = on stack, 2 = done
+        for root in 0..n {
+            if state[root] != 0 {
+                continue;
+            }
+            let mut stack = vec![(root, 0usize)];
+            state[root] = 1;
+            while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+                if let Some(&edge) = outgoing[node].get(*next) {
+                    *next += 1;
+                    let target = self.edges[edge].to;
+                    match state[target] {
+                        0 => {
+                            state[target] = 1;
+                            stack.push((target, 0));
+                        }
+                        1 => back[edge] = true,
+                        _ => {}
+                    }
+                } else {
+                    state[node] = 2;
+                    stack.pop();
+                }
+            }
+        }
+
+        // Longest path layering over the remaining acyclic edges
+        let mut rank = vec![0usize; n];
+        let mut indegree = vec![0usize; n];
+        for (i, edge) in self.edges.iter().enumerate() {
+            if !back[i] {
+                indegree[edge.to] += 1;
+            }
+        }
+        let mut queue: Vec<usize> = (0..n).filter(|&v| indegree[v] == 0).collect();
+        while let Some(node) = queue.pop() {
+            for &edge in &outgoing[node] {
+                if back[edge] {
+                    continue;
+                }
+                let target = self.edges[edge].to;
+                rank[target] = rank[target].max(rank[node] + 1);
+                indegree[target] -= 1;
+                if indegree[target] == 0 {
+                    queue.push(target);
+                }
+            }
+        }
+        (rank, back)
+    }
+
+    fn draw(&self, direction: Direction, canvas: &mut Canvas) -> Vec<String> {
+        let (rank, _) = self.ranks();
+        let rank_count = rank.iter().max().map_or(0, |r| r + 1);
+
+        // Order nodes within each layer by the average position of their parents
+        let mut layers: Vec<Vec<usize>> = vec![Vec::new(); rank_count];
+        for (node, &r) in rank.iter().enumerate() {
+            layers[r].push(node);
+        }
+        for r in 1..rank_count {
+            let position: HashMap<usize, usize> =
+                layers[r - 1].iter().enumerate().map(|(i, &n)| (n, i)).collect();
+            let mut keyed: Vec<(f32, usize)> = layers[r]
+                .iter()
+                .enumerate()
+                .map(|(i, &node)| {
+                    let parents: Vec<usize> = self
+                        .edges
+                        .iter()
+                        .filter(|e| e.to == node)
+                        .filter_map(|e| position.get(&e.from).copied())
+                        .collect();
+                    let key = if parents.is_empty() {
+                        i as f32
+                    } else {
+                        parents.iter().sum::<usize>() as f32 / parents.len() as f32
+                    };
+                    (key, node)
+                })
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+            layers[r] = keyed.into_iter().map(|(_, node)| node).collect();
+        }
+
+        // Box sizes in screen space, then mapped to (main, cross) axes
+        let size = |node: usize| -> (usize, usize) {
+            let label = &self.nodes[node].label;
+            let w = label.iter().map(|l| text_width(l)).max().unwrap_or(0) + 4;
+            let h = label.len() + 2;
+            match direction {
+                Direction::Down => (h, w),
+                Direction::Right => (w, h),
+            }
+        };
+        let cross_gap = if direction == Direction::Down { 3 } else { 1 };
+
+        // Adjacent-layer edges are routed through the gap between layers;
+        // everything else goes around the side through its own lane
+        let adjacent: Vec<bool> = self.edges.iter().map(|e| rank[e.to] == rank[e.from] + 1).collect();
+        let mut notes = Vec::new();
+        let mut lane_edges = Vec::new();
+        for (i, edge) in self.edges.iter().enumerate() {
+            if edge.from == edge.to {
+                notes.push(self.edge_note(edge));
+            } else if !adjacent[i] {
+                lane_edges.push(i);
+            }
+        }
+        let mut out_slots = vec![0usize; rank_count];
+        let mut in_slots = vec![0usize; rank_count];
+        for &i in &lane_edges {
+            out_slots[rank[self.edges[i].from]] += 1;
+            in_slots[rank[self.edges[i].to]] += 1;
+        }
+        let label_slot = |r: usize| -> usize {
+            let widest = self
+                .edges
+                .iter()
+                .zip(&adjacent)
+                .filter(|(e, adj)| **adj && rank[e.from] == r && !e.label.is_empty())
+                .map(|(e, _)| text_width(&e.label))
+                .max();
+            match (widest, direction) {
+                (None, _) => 0,
+                (Some(_), Direction::Down) => 1,
+                (Some(w), Direction::Right) => w + 2,
+            }
+        };
+
+        // Cross-axis positions within each layer, centered on the widest layer
+        let layer_cross: Vec<usize> = layers
+            .iter()
+            .map(|layer| layer.iter().map(|&n| size(n).1).sum::<usize>() + cross_gap * layer.len().saturating_sub(1))
+            .collect();
+        let cross_extent = layer_cross.iter().copied().max().unwrap_or(0);
+        let mut cross_pos = vec![0usize; self.nodes.len()];
+        for (layer, &total) in layers.iter().zip(&layer_cross) {
+            let mut c = (cross_extent - total) / 2;
+            for &node in layer {
+                cross_pos[node] = c;
+                c += size(node).1 + cross_gap;
+            }
+        }
+
+        // Main-axis positions: layer, then exit row, outgoing lanes, bus,
+        // incoming lanes, label slot and arrow row
+        let layer_main: Vec<usize> =
+            layers.iter().map(|layer| layer.iter().map(|&n| size(n).0).max().unwrap_or(0)).collect();
+        let mut layer_start = vec![0usize; rank_count];
+        let mut bus = vec![0usize; rank_count];
+        let mut in_lane_start = vec![0usize; rank_count];
+        let mut m = 0;
+        if in_slots[0] > 0 {
+            in_lane_start[0] = 0;
+            m = in_slots[0] + 1;
+        }
+        for r in 0..rank_count {
+            layer_start[r] = m;
+            m += layer_main[r];
+            let exit = m;
+            if r + 1 < rank_count {
+                bus[r] = exit + 1 + out_slots[r];
+                in_lane_start[r + 1] = bus[r] + 1;
+                m = in_lane_start[r + 1] + in_slots[r + 1] + label_slot(r) + 1;
+            } else {
+                bus[r] = exit + 1 + out_slots[r];
+            }
+        }
+
+        let to_screen = |main: usize, cross: usize| -> (usize, usize) {
+            match direction {
+                Direction::Down => (cross, main),
+                Direction::Right => (main, cross),
+            }
+        };
+        let center = |node: usize| cross_pos[node] + size(node).1 / 2;
+        let exit_point = |node: usize| layer_start[rank[node]] + size(node).0 - 1;
+        let entry_point = |node: usize| layer_start[rank[node]];
+
+        for (node, info) in self.nodes.iter().enumerate() {
+            let (x, y) = to_screen(layer_start[rank[node]], cross_pos[node]);
+            let (main, cross) = size(node);
+            let (w, h) = match direction {
+                Direction::Down => (cross, main),
+                Direction::Right => (main, cross),
+            };
+            canvas.node_box(x, y, w, h, info.shape, &info.label);
+        }
+
+        for (i, edge) in self.edges.iter().enumerate() {
+            if !adjacent[i] || edge.from == edge.to {
+                continue;
+            }
+            let r = rank[edge.from];
+            let (c0, c1) = (center(edge.from), center(edge.to));
+            let arrow = entry_point(edge.to) - 1;
+            let points = [
+                to_screen(exit_point(edge.from), c0),
+                to_screen(bus[r], c0),
+                to_screen(bus[r], c1),
+                to_screen(arrow, c1),
+            ];
+            canvas.path(&points, edge.style, edge.head);
+            if !edge.label.is_empty() {
+                let slot = in_lane_start[r + 1] + in_slots[r + 1];
+                let (x, y) = match direction {
+                    Direction::Down => (c1 + 2, slot),
+                    Direction::Right => (slot + 1, c1),
+                };
+                if !canvas.try_text(x, y, &edge.label) {
+                    notes.push(self.edge_note(edge));
+                }
+            }
+        }
+
+        let mut out_used = vec![0usize; rank_count];
+        let mut in_used = vec![0usize; rank_count];
+        for (k, &i) in lane_edges.iter().enumerate() {
+            let edge = &self.edges[i];
+            let (from_rank, to_rank) = (rank[edge.from], rank[edge.to]);
+            let lane = cross_extent + 1 + 2 * k;
+            let out_row = exit_point(edge.from).max(layer_start[from_rank] + layer_main[from_rank]) + 1 + out_used[from_rank];
+            let in_row = in_lane_start[to_rank] + in_used[to_rank];
+            out_used[from_rank] += 1;
+            in_used[to_rank] += 1;
+            let (c0, c1) = (center(edge.from), center(edge.to));
+            let points = [
+                to_screen(exit_point(edge.from), c0),
+                to_screen(out_row, c0),
+                to_screen(out_row, lane),
+                to_screen(in_row, lane),
+                to_screen(in_row, c1),
+                to_screen(entry_point(edge.to) - 1, c1),
+            ];
+            canvas.path(&points, edge.style, edge.head);
+            if !edge.label.is_empty() {
+                notes.push(self.edge_note(edge));
+            }
+        }
+        notes
+    }
+
+    fn edge_note(&self, edge: &Edge) -> String {
+        let name = |n: usize| self.nodes[n].label.join(" ");
+        let arrow = if edge.head == Head::None { "---" } else { "-->" };
+        if edge.label.is_empty() {
+            format!("{} {} {}", name(edge.from), arrow, name(edge.to))
+        } else {
+            format!("{} {} {}: {}", name(edge.from), arrow, name(edge.to), edge.label)
+        }
+    }
+}
+
+/// Parse a link such as `-->`, `-.->`, `==>`, `-->|label|` or `-- label -->`.
+/// The style is None for invisible links (`~~~`).
+fn parse_edge(scanner: &mut Scanner) -> Option<(String, Option<LineStyle>, Head)> {
+    scanner.skip_ws();
+    if scanner.peek() == Some('<') {
+        scanner.pos += 1;
+    }
+    let start = scanner.pos;
+    while scanner.peek().is_some_and(|c| matches!(c, '-' | '=' | '.' | '~')) {
+        scanner.pos += 1;
+    }
+    let mut run: String = scanner.chars[start..scanner.pos].iter().collect();
+    if run.len() < 2 {
+        return None;
+    }
+    let mut head = read_head(scanner);
+    let mut label = String::new();
+
+    // `-- label -->` form: the text runs up to the closing part of the link
+    if head == Head::None && matches!(run.as_str(), "--" | "==" | "-.") && scanner.peek() != Some('|') {
+        let rest = scanner.rest();
+        let (offset, _) = EDGE_CLOSERS
+            .iter()
+            .filter_map(|closer| rest.find(closer).map(|i| (i, closer)))
+            .min_by_key(|(i, _)| *i)?;
+        label = rest[..offset].trim().to_string();
+        scanner.pos += rest[..offset].chars().count();
+        let close_start = scanner.pos;
+        while scanner.peek().is_some_and(|c| matches!(c, '-' | '=' | '.')) {
+            scanner.pos += 1;
+        }
+        run.extend(scanner.chars[close_start..scanner.pos].iter());
+        head = read_head(scanner);
+    }
+
+    scanner.skip_ws();
+    if scanner.peek() == Some('|') {
+        scanner.pos += 1;
+        label = scanner.read_until("|")?.trim().trim_matches('"').to_string();
+    }
+
+    let style = if run.starts_with('~') {
+        None
+    } else if run.contains('.') {
+        Some(LineStyle::Dotted)
+    } else if run.contains('=') {
+        Some(LineStyle::Thick)
+    } else {
+        Some(LineStyle::Solid)
+    };
+    Some((label, style, head))
+}
+
+fn read_head(scanner: &mut Scanner) -> Head {
+    let next_is_break = |s: &Scanner| s.chars.get(s.pos + 1).is_none_or(|c| c.is_whitespace());
+    match scanner.peek() {
+        Some('>') => {
+            scanner.pos += 1;
+            Head::Arrow
+        }
+        Some('o') if next_is_break(scanner) => {
+            scanner.pos += 1;
+            Head::Circle
+        }
+        Some('x') if next_is_break(scanner) => {
+            scanner.pos += 1;
+            Head::Cross
+        }
+        _ => Head::None,
+    }
+}
+
+// ============================================================================
+// Sequence diagrams
+// ============================================================================
+
+enum SeqItem {
+    Message { from: usize, to: usize, text: String, style: LineStyle, head: Head },
+    Note { left: usize, right: usize, side: NoteSide, text: String },
+    Block(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NoteSide {
+    Left,
+    Right,
+    Over,
+}
+
+struct Sequence {
+    participants: Vec<(String, String)>, // (id, display name)
+    items: Vec<SeqItem>,
+}
+
+const MESSAGE_ARROWS: [(&str, LineStyle, Head); 8] = [
+    ("-->>", LineStyle::Dotted, Head::Arrow),
+    ("->>", LineStyle::Solid, Head::Arrow),
+    ("--x", LineStyle::Dotted, Head::Cross),
+    ("-x", LineStyle::Solid, Head::Cross),
+    ("--)", LineStyle::Dotted, Head::Arrow),
+    ("-)", LineStyle::Solid, Head::Arrow),
+    ("-->", LineStyle::Dotted, Head::None),
+    ("->", LineStyle::Solid, Head::None),
+];
+
+impl Sequence {
+    fn parse(statements: &[String]) -> Result<Self, String> {
+        let mut seq = Sequence { participants: Vec::new(), items: Vec::new() };
+        for statement in statements {
+            let keyword = statement.split_whitespace().next().unwrap_or_default();
+            match keyword {
+                "participant" | "actor" => {
+                    let rest = statement[keyword.len()..].trim();
+                    let (id, name) = match rest.split_once(" as ") {
+                        Some((id, name)) => (id.trim(), name.trim()),
+                        None => (rest, rest),
+                    };
+                    let index = seq.participant(id);
+                    seq.participants[index].1 = name.to_string();
+                }
+                "autonumber" | "activate" | "deactivate" | "title" | "box" => {}
+                "loop" | "alt" | "else" | "opt" | "par" | "and" | "critical" | "break" | "rect" | "end" => {
+                    seq.items.push(SeqItem::Block(statement.clone()));
+                }
+                "Note" | "note" => seq.parse_note(statement)?,
+                _ => seq.parse_message(statement)?,
+            }
+        }
+        if seq.participants.is_empty() {
+            return Err("sequence diagram has no participants".to_string());
+        }
+        Ok(seq)
+    }
+
+    fn participant(&mut self, id: &str) -> usize {
+        let id = id.trim().trim_start_matches(['+', '-']);
+        if let Some(index) = self.participants.iter().position(|(p, _)| p == id) {
+            return index;
+        }
+        self.participants.push((id.to_string(), id.to_string()));
+        self.participants.len() - 1
+    }
+
+    fn parse_message(&mut self, statement: &str) -> Result<(), String> {
+        let (head_part, text) = statement
+            .split_once(':')
+            .ok_or_else(|| format!("cannot parse '{}'", statement))?;
+        let (offset, arrow, style, head) = MESSAGE_ARROWS
+            .iter()
+            .filter_map(|&(arrow, style, head)| head_part.find(arrow).map(|i| (i, arrow, style, head)))
+            .min_by_key(|&(i, arrow, ..)| (i, std::cmp::Reverse(arrow.len())))
+            .ok_or_else(|| format!("cannot parse '{}'", statement))?;
+        let from = self.participant(&head_part[..offset]);
+        let to = self.participant(&head_part[offset + arrow.len()..]);
+        self.items.push(SeqItem::Message { from, to, text: text.trim().to_string(), style, head });
+        Ok(())
+    }
+
+    fn parse_note(&mut self, statement: &str) -> Result<(), String> {
+        let (position, text) = statement
+            .split_once(':')
+            .ok_or_else(|| format!("cannot parse '{}'", statement))?;
+        let position = position.split_whitespace().skip(1).collect::<Vec<_>>().join(" ");
+        let (side, targets) = if let Some(t) = position.strip_prefix("left of ") {
+            (NoteSide::Left, t)
+        } else if let Some(t) = position.strip_prefix("right of ") {
+            (NoteSide::Right, t)
+        } else if let Some(t) = position.strip_prefix("over ") {
+            (NoteSide::Over, t)
+        } else {
+            return Err(format!("cannot parse '{}'", statement));
+        };
+        let mut ids = targets.split(',').map(|t| self.participant(t)).collect::<Vec<_>>();
+        ids.sort_unstable();
+        let (left, right) = (ids[0], *ids.last().unwrap_or(&ids[0]));
+        self.items.push(SeqItem::Note { left, right, side, text: text.trim().to_string() });
+        Ok(())
+    }
+
+    fn draw(&self, canvas: &mut Canvas) -> Vec<String> {
+        let count = self.participants.len();
+        let box_width: Vec<usize> = self.participants.iter().map(|(_, name)| text_width(name) + 4).collect();
+
+        // Space lifelines so every message label fits between its endpoints
+        let mut centers = vec![box_width[0] / 2; count];
+        for k in 1..count {
+            let mut center = centers[k - 1] + box_width[k - 1].div_ceil(2) + box_width[k] / 2 + 2;
+            for item in &self.items {
+                let (a, b, width) = match item {
+                    SeqItem::Message { from, to, text, .. } if from != to => {
+                        (*from.min(to), *from.max(to), text_width(text) + 4)
+                    }
+                    SeqItem::Note { left, right, side: NoteSide::Over, text } if left != right => {
+                        (*left, *right, text_width(text) + 4)
+                    }
+                    _ => continue,
+                };
+                if b == k {
+                    center = center.max(centers[a] + width);
+                }
+            }
+            // Self messages and side notes need room to the right of the lifeline
+            for item in &self.items {
+                let width = match item {
+                    SeqItem::Message { from, to, text, .. } if from == to && *from == k - 1 => text_width(text) + 6,
+                    SeqItem::Note { left, side: NoteSide::Right, text, .. } if *left == k - 1 => text_width(text) + 6,
+                    SeqItem::Note { left, side: NoteSide::Left, text, .. } if *left == k => {
+                        center = center.max(centers[k - 1] + text_width(text) + 6);
+                        continue;
+                    }
+                    _ => continue,
+                };
+                center = center.max(centers[k - 1] + width);
+            }
+            centers[k] = center;
+        }
+        // Shift right when a note sits left of the first participant
+        let shift = self
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                SeqItem::Note { left: 0, side: NoteSide::Left, text, .. } => Some(text_width(text) + 5),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+            .saturating_sub(centers[0]);
+        for c in &mut centers {
+            *c += shift;
+        }
+
+        for (k, (_, name)) in self.participants.iter().enumerate() {
+            let x = centers[k] - box_width[k] / 2;
+            canvas.node_box(x, 0, box_width[k], 3, Shape::Rect, std::slice::from_ref(name));
+        }
+
+        let mut note_boxes: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+        let mut y = 3;
+        for item in &self.items {
+            match item {
+                SeqItem::Message { from, to, text, style, head } if from == to => {
+                    let x = centers[*from];
+                    canvas.text(x + 5, y, text);
+                    canvas.path(&[(x, y), (x + 3, y), (x + 3, y + 1), (x + 1, y + 1)], *style, *head);
+                    y += 2;
+                }
+                SeqItem::Message { from, to, text, style, head } => {
+                    let (x0, x1) = (centers[*from], centers[*to]);
+                    let (lo, hi) = (x0.min(x1), x0.max(x1));
+                    let label_x = lo + (hi - lo).saturating_sub(text_width(text)) / 2;
+                    canvas.text(label_x.max(lo + 1), y, text);
+                    let end = if x1 > x0 { x1 - 1 } else { x1 + 1 };
+                    canvas.path(&[(x0, y + 1), (end, y + 1)], *style, *head);
+                    y += 2;
+                }
+                SeqItem::Note { left, right, side, text } => {
+                    let width = text_width(text) + 4;
+                    let x = match side {
+                        NoteSide::Right => centers[*left] + 2,
+                        NoteSide::Left => centers[*left].saturating_sub(width + 1),
+                        NoteSide::Over => {
+                            let mid = (centers[*left] + centers[*right]) / 2;
+                            mid.saturating_sub(width / 2)
+                        }
+                    };
+                    let width = match side {
+                        NoteSide::Over if left != right => width.max(centers[*right] - centers[*left] + 4),
+                        _ => width,
+                    };
+                    let x = if *side == NoteSide::Over && left != right { centers[*left] - 2 } else { x };
+                    canvas.node_box(x, y, width, 3, Shape::Rect, std::slice::from_ref(text));
+                    note_boxes.push((x..x + width, y..y + 3));
+                    y += 3;
+                }
+                SeqItem::Block(text) => {
+                    canvas.text(0, y, &format!("[{}]", text));
+                    y += 1;
+                }
+            }
+        }
+
+        // Lifelines go underneath everything drawn so far: they join the
+        // messages that start or end on them and pass behind notes
+        for &x in &centers {
+            canvas.add_line(x, 2, DOWN, LineStyle::Solid);
+            for row in 3..=y {
+                if note_boxes.iter().any(|(cols, rows)| cols.contains(&x) && rows.contains(&row)) {
+                    continue;
+                }
+                match canvas.get(x, row) {
+                    Cell::Empty => canvas.add_line(x, row, UP | DOWN, LineStyle::Solid),
+                    Cell::Line(mask, _) if mask & (LEFT | RIGHT) != LEFT | RIGHT => {
+                        canvas.add_line(x, row, UP | DOWN, LineStyle::Solid)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flowchart_top_down() {
+        let art = render("graph TD\n  A[Start] --> B{Ok?}\n  B -->|Yes| C[Done]\n  B -- No --> A", true).unwrap();
+        assert!(art.contains("│ Start │"));
+        assert!(art.contains("Ok?"));
+        assert!(art.contains("Yes"));
+        assert!(art.contains('▼'));
+        // The back edge is drawn through a lane and its label listed below
+        assert!(art.contains("Ok? --> Start: No"));
+    }
+
+    #[test]
+    fn test_flowchart_left_right_ascii() {
+        let art = render("flowchart LR; A --> B; B -.-> C", false).unwrap();
+        let first_row = art.lines().next().unwrap();
+        assert_eq!(first_row.matches('+').count(), 6);
+        assert!(art.contains("| A +-->| B +..>| C |"));
+        assert!(art.contains('>'));
+        assert!(art.is_ascii());
+    }
+
+    #[test]
+    fn test_sequence_diagram() {
+        let art = render(
+            "sequenceDiagram\n  participant A as Alice\n  A->>Bob: Hello\n  Bob-->>A: Hi\n  Note over A,Bob: done",
+            true,
+        )
+        .unwrap();
+        assert!(art.contains("│ Alice │"));
+        assert_eq!(art.matches("│ Alice │").count(), 1);
+        assert!(art.contains('├'));
+        assert!(art.contains("Hello"));
+        assert!(art.contains('▶'));
+        assert!(art.contains('◀'));
+        assert!(art.contains("done"));
+    }
+
+    #[test]
+    fn test_unsupported_diagram() {
+        assert!(render("pie title Pets\n  \"Dogs\" : 386", true).is_err());
+        assert!(render("graph BT\n  A --> B", true).is_err());
+    }
+}
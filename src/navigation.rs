@@ -0,0 +1,174 @@
+//! Following links between documents. The standalone viewer and the Total
+//! Commander plugin both go through `follow`, so a link leads to the same
+//! place in either, and both keep a `History` for Back and Forward.
+
+use crate::link_policy::{self, Action, Link, LinkPolicy};
+use crate::protocol::HostMessage;
+use crate::resources;
+use std::path::{Path, PathBuf};
+
+/// Where a click on a link leads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Navigation {
+    /// Show a markdown file in the viewer, at the heading `fragment`
+    Document { path: PathBuf, fragment: Option<String> },
+    /// Hand the target to the program Windows associates with it
+    Open(String),
+    /// Nothing happens: the link policy or the reader said no
+    Stay,
+}
+
+/// Files the viewers show, by extension
+pub fn is_markdown_file(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|e| e == "md" || e == "markdown")
+}
+
+/// Follow `url` from a document in `base_dir`. `external` links (Ctrl+click)
+/// open markdown files in their own program too. `confirm` asks the reader
+/// about links the policy wants confirmed.
+pub fn follow(
+    url: &str,
+    base_dir: &Path,
+    policy: &LinkPolicy,
+    external: bool,
+    confirm: impl FnOnce(&Link) -> bool,
+) -> Navigation {
+    let link = link_policy::classify(url, base_dir);
+    match policy.action(&link) {
+        Action::Block => return Navigation::Stay,
+        Action::Confirm if !confirm(&link) => return Navigation::Stay,
+        Action::Open => return Navigation::Open(link.target()),
+        _ => {}
+    }
+    match link.document() {
+        Some(path) if !external => Navigation::Document { path: path.to_path_buf(), fragment: fragment(&link.url) },
+        _ => Navigation::Open(link.target()),
+    }
+}
+
+/// Most places `History` remembers behind the current one
+const MAX_HISTORY: usize = 100;
+
+/// A place in a document to come back to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub path: PathBuf,
+    /// How far the page was scrolled, in CSS pixels
+    pub scroll: f64,
+}
+
+/// Where a page goes once it has loaded
+#[derive(Debug, Clone, PartialEq)]
+pub enum Position {
+    /// Back where the reader was, in CSS pixels
+    Scroll(f64),
+    /// At the element with this id, like a heading
+    Section(String),
+}
+
+impl Position {
+    /// The message that puts the page there
+    pub fn message(&self) -> HostMessage {
+        match self {
+            Position::Scroll(y) => HostMessage::ScrollTo { y: *y },
+            Position::Section(id) => HostMessage::ShowSection { id: id.clone() },
+        }
+    }
+}
+
+/// The places before and after the current one
+#[derive(Debug, Default)]
+pub struct History {
+    back: Vec<Location>,
+    forward: Vec<Location>,
+}
+
+impl History {
+    /// Leave `current` for a new place, which drops the places after it
+    pub fn visit(&mut self, current: Location) {
+        self.forward.clear();
+        self.back.push(current);
+        if self.back.len() > MAX_HISTORY {
+            self.back.remove(0);
+        }
+    }
+
+    /// The place before `current`, if any
+    pub fn back(&mut self, current: Location) -> Option<Location> {
+        let previous = self.back.pop()?;
+        self.forward.push(current);
+        Some(previous)
+    }
+
+    /// The place `back` left, if any
+    pub fn forward(&mut self, current: Location) -> Option<Location> {
+        let next = self.forward.pop()?;
+        self.back.push(current);
+        Some(next)
+    }
+}
+
+/// The heading a URL points to: `setup.md#proxy%20settings` gives `proxy settings`
+pub fn fragment(url: &str) -> Option<String> {
+    let (_, fragment) = url.split_once('#')?;
+    Some(resources::percent_decode(fragment)).filter(|f| !f.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_follow() {
+        let base = Path::new("docs");
+        let policy = LinkPolicy::default();
+        let follow = |url, external| follow(url, base, &policy, external, |_| true);
+        assert_eq!(
+            follow("setup.md#proxy-configuration", false),
+            Navigation::Document { path: base.join("setup.md"), fragment: Some("proxy-configuration".to_string()) }
+        );
+        assert_eq!(
+            follow("../README.MARKDOWN", false),
+            Navigation::Document { path: base.join("../README.MARKDOWN"), fragment: None }
+        );
+        assert_eq!(follow("setup.md#x", true), Navigation::Open(base.join("setup.md").to_string_lossy().into_owned()));
+        assert_eq!(follow("https://example.com/a.md", false), Navigation::Open("https://example.com/a.md".to_string()));
+        assert_eq!(follow("tools/setup.exe", false), Navigation::Stay);
+        assert_eq!(follow("ms-settings:privacy", false), Navigation::Stay);
+
+        let declined = super::follow("file:///C:/notes.md", base, &policy, false, |_| false);
+        assert_eq!(declined, Navigation::Stay);
+    }
+
+    #[test]
+    fn test_history() {
+        let at = |path: &str, scroll| Location { path: PathBuf::from(path), scroll };
+        let mut history = History::default();
+        assert_eq!(history.back(at("a.md", 0.0)), None);
+        history.visit(at("a.md", 120.0));
+        history.visit(at("b.md", 0.0));
+        history.visit(at("b.md", 300.0));
+        assert_eq!(history.back(at("c.md", 50.0)), Some(at("b.md", 300.0)));
+        assert_eq!(history.back(at("b.md", 300.0)), Some(at("b.md", 0.0)));
+        assert_eq!(history.forward(at("b.md", 0.0)), Some(at("b.md", 300.0)));
+        assert_eq!(history.forward(at("b.md", 300.0)), Some(at("c.md", 50.0)));
+        assert_eq!(history.forward(at("c.md", 50.0)), None);
+
+        history.back(at("c.md", 50.0));
+        history.visit(at("b.md", 300.0));
+        assert_eq!(history.forward(at("d.md", 0.0)), None);
+        assert_eq!(history.back(at("d.md", 0.0)), Some(at("b.md", 300.0)));
+    }
+
+    #[test]
+    fn test_fragment() {
+        assert_eq!(fragment("guide.md#install"), Some("install".to_string()));
+        assert_eq!(fragment("guide.md#caf%C3%A9"), Some("café".to_string()));
+        assert_eq!(fragment("guide.md#"), None);
+        assert_eq!(fragment("guide.md"), None);
+        assert_eq!(Position::Section("install".to_string()).message(), HostMessage::ShowSection { id: "install".to_string() });
+        assert!(is_markdown_file(Path::new("a/Notes.MD")) && !is_markdown_file(Path::new("a/notes.txt")));
+    }
+}
@@ -0,0 +1,125 @@
+//! Viewer pages for documents. The standalone viewer and the Total Commander
+//! plugin build their pages here, and tell from the `Rendering` whether a
+//! changed document can be patched into the page it is on.
+
+use crate::markdown;
+use crate::protocol::HostMessage;
+use crate::resources;
+use crate::settings::{RemoteContent, Settings};
+use crate::toc;
+use std::path::{Path, PathBuf};
+
+/// Button in the viewer cycling through auto, light and dark
+const THEME_TOGGLE: &str =
+    "<button class=\"mdview-theme-toggle\" title=\"Theme\" aria-label=\"Switch between light and dark\">&#9680;</button>\n";
+
+/// What a page has besides the document
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Controls<'a> {
+    pub theme_toggle: bool,
+    /// The source pane shown next to the document
+    pub source: Option<&'a str>,
+}
+
+/// What a viewer shows of a document, to tell how to show a new version
+#[derive(Debug, Clone, Default)]
+pub struct Rendering {
+    /// Everything on the page around the document: title, settings, sidebar.
+    /// Empty for pages that are always loaded again.
+    pub layout: String,
+    /// The document's part of the page body
+    pub content: String,
+    /// The document and the local files it shows, to watch
+    pub files: Vec<PathBuf>,
+}
+
+impl Rendering {
+    /// The message that turns a page showing `old` into this one. `None` when
+    /// the page has to be loaded again: the layout changed, or the content did
+    /// not and an image did.
+    pub fn patch(&self, old: &Rendering) -> Option<HostMessage> {
+        if self.layout.is_empty() || self.layout != old.layout || self.content == old.content {
+            return None;
+        }
+        Some(HostMessage::Patch { html: self.content.clone() })
+    }
+}
+
+/// The viewer page of `markdown`, read from `file_path`. Local images are
+/// loaded from `resources::DOCUMENT_HOST`, which serves the document's folder.
+pub fn build(
+    markdown: &str,
+    settings: &Settings,
+    file_path: &Path,
+    title: Option<&str>,
+    controls: Controls,
+) -> (String, Rendering) {
+    let folder = resources::document_folder(file_path);
+    let html_body = markdown::markdown_to_html(markdown, settings);
+    let mut files = vec![file_path.to_path_buf()];
+    files.extend(resources::local_files(&html_body, &folder));
+    let mut content = resources::link_local_files(&html_body, &folder, resources::LocalFiles::VirtualHost);
+    if let Some(source) = controls.source {
+        content = format!(
+            "<div class=\"mdview-split\">\
+             <div class=\"mdview-rendered\">{}</div>\
+             <div class=\"mdview-source\">{}</div>\
+             </div>",
+            content, source
+        );
+    }
+
+    let mut chrome = if controls.theme_toggle { THEME_TOGGLE.to_string() } else { String::new() };
+    chrome += &toc::sidebar(&toc::for_document(markdown, settings), settings.toc);
+    if settings.remote == RemoteContent::Ask {
+        chrome += resources::load_remote_bar(&html_body);
+    }
+    let rendering = Rendering {
+        // Trusted documents may run scripts of their own, which a patch would not
+        layout: if settings.trust { String::new() } else { format!("{:?}\n{:?}\n{}", settings, title, chrome) },
+        content,
+        files,
+    };
+    (markdown::wrap_viewer_html(&(chrome + &rendering.content), title, settings), rendering)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let path = Path::new("docs/guide.md");
+        let md = "# Guide\n\n![shot](img/shot.png) ![web](https://example.com/a.png)\n";
+        let (html, rendering) = build(md, &Settings::default(), path, Some("Guide"), Controls::default());
+        assert_eq!(rendering.files, vec![path.to_path_buf(), resources::document_folder(path).join("img/shot.png")]);
+        assert!(html.contains("<title>Guide</title>") && html.contains(&rendering.content));
+        assert!(html.contains("mdview-remote-bar") && !rendering.content.contains("mdview-remote-bar"));
+        assert!(!html.contains(THEME_TOGGLE));
+
+        let controls = Controls { theme_toggle: true, source: Some("<pre># Guide</pre>") };
+        let (html, split) = build(md, &Settings::default(), path, Some("Guide"), controls);
+        assert!(html.contains(THEME_TOGGLE) && split.content.starts_with("<div class=\"mdview-split\">"));
+        assert_ne!(split.layout, rendering.layout);
+
+        let trusted = Settings { trust: true, ..Settings::default() };
+        assert_eq!(build(md, &trusted, path, None, Controls::default()).1.layout, "");
+    }
+
+    #[test]
+    fn test_patch() {
+        let rendering = |layout: &str, content: &str| Rendering {
+            layout: layout.to_string(),
+            content: content.to_string(),
+            files: Vec::new(),
+        };
+        let old = rendering("dark", "<p>one</p>");
+        assert_eq!(
+            rendering("dark", "<p>two</p>").patch(&old),
+            Some(HostMessage::Patch { html: "<p>two</p>".to_string() })
+        );
+        assert_eq!(rendering("light", "<p>two</p>").patch(&old), None);
+        assert_eq!(rendering("dark", "<p>one</p>").patch(&old), None);
+        assert_eq!(rendering("", "<p>two</p>").patch(&rendering("", "<p>one</p>")), None);
+    }
+}
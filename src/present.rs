@@ -0,0 +1,290 @@
+//! Slide presentation mode: splits a document into slides and shows one per screen
+
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag};
+use std::ops::Range;
+
+use crate::markdown::{Extensions, Numbering};
+use crate::settings::Settings;
+use crate::terminal::{self, ansi, TerminalCaps, TerminalOptions};
+
+/// Where the document is split into slides
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitMode {
+    /// Split at horizontal rules (`---`)
+    #[default]
+    Rule,
+    /// Split before every H1 heading
+    H1,
+    /// Split before every H1 and H2 heading
+    H2,
+}
+
+impl SplitMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "rule" | "hr" | "---" => Some(Self::Rule),
+            "h1" => Some(Self::H1),
+            "h2" => Some(Self::H2),
+            _ => None,
+        }
+    }
+}
+
+/// Split markdown into slide sources. Speaker notes (HTML comments) are removed.
+pub fn split_slides(markdown: &str, mode: SplitMode, extensions: &Extensions) -> Vec<String> {
+    let options = extensions.options();
+
+    // Ranges removed from the output, and the ones that also start a new slide
+    let mut breaks: Vec<Range<usize>> = Vec::new();
+    let mut notes: Vec<Range<usize>> = Vec::new();
+    let mut depth = 0usize;
+
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        match event {
+            Event::Start(tag) => {
+                if depth == 0 {
+                    match tag {
+                        Tag::Heading { level, .. } if splits_at_heading(mode, level) => {
+                            breaks.push(range.start..range.start);
+                        }
+                        Tag::HtmlBlock if is_comment(&markdown[range.clone()]) => {
+                            notes.push(range.clone());
+                        }
+                        _ => {}
+                    }
+                }
+                depth += 1;
+            }
+            Event::End(_) => depth = depth.saturating_sub(1),
+            Event::Rule if depth == 0 && mode == SplitMode::Rule => breaks.push(range),
+            Event::InlineHtml(html) if is_comment(&html) => notes.push(range),
+            _ => {}
+        }
+    }
+
+    let mut slides = Vec::new();
+    let mut start = 0;
+    for cut in breaks.iter().cloned().chain(std::iter::once(markdown.len()..markdown.len())) {
+        let slide = strip_ranges(markdown, start..cut.start, &notes);
+        if !slide.trim().is_empty() {
+            slides.push(slide);
+        }
+        start = cut.end;
+    }
+    slides
+}
+
+fn splits_at_heading(mode: SplitMode, level: HeadingLevel) -> bool {
+    match mode {
+        SplitMode::Rule => false,
+        SplitMode::H1 => level == HeadingLevel::H1,
+        SplitMode::H2 => level <= HeadingLevel::H2,
+    }
+}
+
+fn is_comment(html: &str) -> bool {
+    html.trim_start().starts_with("<!--")
+}
+
+/// Copy `span` of `source`, leaving out any of the `removed` ranges inside it
+fn strip_ranges(source: &str, span: Range<usize>, removed: &[Range<usize>]) -> String {
+    let mut out = String::new();
+    let mut pos = span.start;
+    for range in removed {
+        if range.end <= pos || range.start >= span.end {
+            continue;
+        }
+        out.push_str(&source[pos..range.start.max(pos)]);
+        pos = range.end.min(span.end);
+    }
+    out.push_str(&source[pos..span.end]);
+    out
+}
+
+/// A key press, already translated from the console input record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Backspace,
+    Escape,
+    Char(char),
+}
+
+/// Slide deck with the current position and any slide number being typed
+pub struct Presentation {
+    slides: Vec<String>,
+    current: usize,
+    jump: String,
+}
+
+impl Presentation {
+    pub fn new(slides: Vec<String>) -> Self {
+        Self {
+            slides,
+            current: 0,
+            jump: String::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slides.is_empty()
+    }
+
+    /// Apply a key press. Returns false when the presentation should end.
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        let last = self.slides.len().saturating_sub(1);
+        match key {
+            Key::Char(c) if c.is_ascii_digit() => {
+                if self.jump.len() < 6 {
+                    self.jump.push(c);
+                }
+                return true;
+            }
+            Key::Enter if !self.jump.is_empty() => {
+                if let Ok(n) = self.jump.parse::<usize>() {
+                    self.current = n.clamp(1, last + 1) - 1;
+                }
+            }
+            Key::Backspace if !self.jump.is_empty() => {
+                self.jump.pop();
+                return true;
+            }
+            Key::Escape if !self.jump.is_empty() => {}
+            Key::Right | Key::Down | Key::PageDown | Key::Enter | Key::Char(' ' | 'n' | 'l' | 'j') => {
+                self.current = (self.current + 1).min(last);
+            }
+            Key::Left | Key::Up | Key::PageUp | Key::Backspace | Key::Char('p' | 'h' | 'k') => {
+                self.current = self.current.saturating_sub(1);
+            }
+            Key::Home | Key::Char('g') => self.current = 0,
+            Key::End | Key::Char('G') => self.current = last,
+            Key::Escape | Key::Char('q' | 'Q' | '\u{3}') => return false,
+            Key::Char(_) => {}
+        }
+        self.jump.clear();
+        true
+    }
+
+    /// Render the current slide as a full screen frame of `width` x `height` cells
+    pub fn render(&self, caps: &TerminalCaps, settings: &Settings, width: usize, height: usize) -> String {
+        let height = height.max(3);
+        // Headings are numbered through the whole deck
+        let mut numbering = Numbering::default();
+        if settings.number_headings {
+            numbering = Numbering::for_document(&self.slides.join("\n\n"), settings);
+            for slide in &self.slides[..self.current] {
+                numbering.skip(slide, settings);
+            }
+        }
+        // A table of contents of one slide would list only its own headings
+        let options = TerminalOptions {
+            settings: Settings { toc: false, ..settings.clone() },
+            width: width.saturating_sub(2),
+            numbering,
+            ..TerminalOptions::default()
+        };
+        let body = self
+            .slides
+            .get(self.current)
+            .map(|s| terminal::render_to_terminal_with(s, caps, &options))
+            .unwrap_or_default();
+        let mut lines: Vec<&str> = body.trim_matches('\n').lines().collect();
+
+        // Keep the last row free for the slide counter
+        let available = height - 1;
+        let clipped = lines.len() > available;
+        lines.truncate(available);
+        let top = (available - lines.len()) / 2;
+
+        let mut out = String::from("\x1b[2J\x1b[H");
+        out.push_str(&"\r\n".repeat(top));
+        for line in &lines {
+            out.push_str("  ");
+            out.push_str(line);
+            out.push_str("\r\n");
+        }
+
+        let status = if !self.jump.is_empty() {
+            format!("Go to slide: {}_ ", self.jump)
+        } else if clipped {
+            format!("(clipped) {} / {} ", self.current + 1, self.slides.len())
+        } else {
+            format!("{} / {} ", self.current + 1, self.slides.len())
+        };
+        let col = width.saturating_sub(status.chars().count()) + 1;
+        out.push_str(&format!("\x1b[{};{}H", height, col));
+        if caps.basic_ansi {
+            out.push_str(ansi::DIM);
+        }
+        out.push_str(&status);
+        if caps.basic_ansi {
+            out.push_str(ansi::RESET);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_at_rules_skips_notes() {
+        let md = "# One\n\nFirst\n\n<!-- speaker note -->\n\n---\n\n# Two\n\n- a\n\n  ---\n\n- b\n";
+        let slides = split_slides(md, SplitMode::Rule, &Extensions::default());
+        assert_eq!(slides.len(), 2);
+        assert!(slides[0].contains("First"));
+        assert!(!slides[0].contains("speaker note"));
+        assert!(slides[1].contains("- b"));
+    }
+
+    #[test]
+    fn test_split_at_headings() {
+        let md = "# Title\n\nIntro\n\n## Part A\n\nText\n\n### Detail\n\n## Part B\n";
+        assert_eq!(split_slides(md, SplitMode::H1, &Extensions::default()).len(), 1);
+        let slides = split_slides(md, SplitMode::H2, &Extensions::default());
+        assert_eq!(slides.len(), 3);
+        assert!(slides[1].contains("### Detail"));
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut deck = Presentation::new(vec!["a".into(), "b".into(), "c".into()]);
+        assert!(deck.handle_key(Key::Char(' ')));
+        assert_eq!(deck.current, 1);
+        deck.handle_key(Key::Char('G'));
+        assert_eq!(deck.current, 2);
+        deck.handle_key(Key::Right);
+        assert_eq!(deck.current, 2);
+        deck.handle_key(Key::Char('g'));
+        assert_eq!(deck.current, 0);
+        deck.handle_key(Key::Char('2'));
+        deck.handle_key(Key::Enter);
+        assert_eq!(deck.current, 1);
+        assert!(!deck.handle_key(Key::Char('q')));
+    }
+
+    #[test]
+    fn test_render() {
+        let deck = Presentation::new(vec!["# One\n\n## Part\n".into(), "# Two\n".into()]);
+        let settings = Settings { toc: true, ..Settings::default() };
+        let frame = deck.render(&TerminalCaps::basic(), &settings, 40, 10);
+        assert!(frame.contains("One") && frame.contains("Part") && frame.ends_with("1 / 2 "));
+        assert!(!frame.contains("Contents"));
+
+        // Numbers continue from the slides before
+        let mut deck = Presentation::new(vec!["# One\n\n## A\n".into(), "# Two\n\n## B\n".into()]);
+        deck.handle_key(Key::Right);
+        let settings = Settings { number_headings: true, ..Settings::default() };
+        let frame = deck.render(&TerminalCaps::basic(), &settings, 40, 10);
+        assert!(frame.contains("2 Two") && frame.contains("2.1 B"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Messages between viewer pages and their host. Each is a JSON object with the
+//! protocol version `v` and a `type`; the page script sends and receives the
+//! same shapes.
+
+use crate::json::Json;
+use crate::settings::Theme;
+
+/// Version of the message format, sent as `v` in every message
+pub const VERSION: u32 = 1;
+
+/// Messages the page posts to the host
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageMessage {
+    /// A plain click on a link to another document or page
+    FollowLink { url: String },
+    /// A Ctrl+click on a link
+    OpenLink { url: String },
+    /// Escape was pressed
+    Close,
+    /// The theme toggle switched to `theme`
+    #[allow(dead_code)]
+    Theme { theme: Theme },
+    /// The reader asked for blocked remote content
+    LoadRemote,
+    /// The page has loaded, images included
+    Ready,
+    /// The page was scrolled to `y`, in CSS pixels
+    Scroll { y: f64 },
+    /// A `#section` link moved within the page
+    Anchor,
+    /// Alt+Left, Backspace or the back mouse button
+    Back,
+    /// Alt+Right or the forward mouse button
+    Forward,
+}
+
+impl PageMessage {
+    /// Decode a message from `WebMessageAsJson`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let json = Json::parse(text)?;
+        let version = json.get("v").and_then(Json::as_f64);
+        if version != Some(VERSION as f64) {
+            return Err(format!("Unsupported message version: {:?}", version));
+        }
+        let kind = json.get("type").and_then(Json::as_str).ok_or("Message without a type")?;
+        let field = |name: &str| {
+            json.get(name)
+                .and_then(Json::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| format!("{} message without {}", kind, name))
+        };
+        match kind {
+            "followLink" => Ok(Self::FollowLink { url: field("url")? }),
+            "openLink" => Ok(Self::OpenLink { url: field("url")? }),
+            "close" => Ok(Self::Close),
+            "theme" => {
+                let theme = field("theme")?;
+                Theme::parse(&theme).map(|theme| Self::Theme { theme }).ok_or_else(|| format!("Unknown theme: {}", theme))
+            }
+            "loadRemote" => Ok(Self::LoadRemote),
+            "ready" => Ok(Self::Ready),
+            "scroll" => match json.get("y").and_then(Json::as_f64) {
+                Some(y) if y.is_finite() => Ok(Self::Scroll { y: y.max(0.0) }),
+                _ => Err("scroll message without y".to_string()),
+            },
+            "anchor" => Ok(Self::Anchor),
+            "back" => Ok(Self::Back),
+            "forward" => Ok(Self::Forward),
+            _ => Err(format!("Unknown message type: {}", kind)),
+        }
+    }
+}
+
+/// Messages the host posts to the page
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostMessage {
+    /// Highlight all matches of `text` and move to the next or previous one,
+    /// or to the first one `from_start`. `text` is a regular expression with
+    /// `regex`.
+    Find { text: String, match_case: bool, whole_words: bool, regex: bool, backwards: bool, from_start: bool },
+    /// Open the find bar
+    ShowFind,
+    /// Copy the selection to the clipboard
+    Copy,
+    SelectAll,
+    /// Scroll to `y`, in CSS pixels
+    ScrollTo { y: f64 },
+    /// Scroll to the element with the id `id`, or say that there is none
+    ShowSection { id: String },
+    /// Replace the document with `html`, the new body of the page, keeping
+    /// the parts that did not change and the scroll position
+    Patch { html: String },
+}
+
+impl HostMessage {
+    /// The message for `PostWebMessageAsJson`
+    pub fn to_json(&self) -> String {
+        let kind = match self {
+            Self::Find { .. } => "find",
+            Self::ShowFind => "showFind",
+            Self::Copy => "copy",
+            Self::SelectAll => "selectAll",
+            Self::ScrollTo { .. } => "scrollTo",
+            Self::ShowSection { .. } => "showSection",
+            Self::Patch { .. } => "patch",
+        };
+        let mut members = vec![
+            ("v".to_string(), Json::Number(VERSION as f64)),
+            ("type".to_string(), Json::String(kind.to_string())),
+        ];
+        if let Self::Find { text, match_case, whole_words, regex, backwards, from_start } = self {
+            members.push(("text".to_string(), Json::String(text.clone())));
+            members.push(("matchCase".to_string(), Json::Bool(*match_case)));
+            members.push(("wholeWords".to_string(), Json::Bool(*whole_words)));
+            members.push(("regex".to_string(), Json::Bool(*regex)));
+            members.push(("backwards".to_string(), Json::Bool(*backwards)));
+            members.push(("fromStart".to_string(), Json::Bool(*from_start)));
+        }
+        if let Self::ScrollTo { y } = self {
+            members.push(("y".to_string(), Json::Number(*y)));
+        }
+        if let Self::ShowSection { id } = self {
+            members.push(("id".to_string(), Json::String(id.clone())));
+        }
+        if let Self::Patch { html } = self {
+            members.push(("html".to_string(), Json::String(html.clone())));
+        }
+        Json::Object(members).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_messages() {
+        assert_eq!(
+            PageMessage::parse(r#"{"url":"a \"quoted\" \u0041.md#x","type":"followLink","v":1}"#),
+            Ok(PageMessage::FollowLink { url: "a \"quoted\" A.md#x".to_string() })
+        );
+        assert_eq!(
+            PageMessage::parse(r#"{"v":1,"type":"openLink","url":"C:\\docs\\close"}"#),
+            Ok(PageMessage::OpenLink { url: "C:\\docs\\close".to_string() })
+        );
+        assert_eq!(PageMessage::parse(r#"{"v":1,"type":"theme","theme":"dark"}"#), Ok(PageMessage::Theme { theme: Theme::Dark }));
+        assert_eq!(PageMessage::parse(r#"{"v":1,"type":"close"}"#), Ok(PageMessage::Close));
+        assert_eq!(PageMessage::parse(r#"{"v":1,"type":"scroll","y":1.25e3}"#), Ok(PageMessage::Scroll { y: 1250.0 }));
+        assert_eq!(PageMessage::parse(r#"{"v":1,"type":"back"}"#), Ok(PageMessage::Back));
+
+        // Wrong version, unknown or incomplete messages, and text that only mentions a type
+        for text in [
+            r#"{"type":"close"}"#,
+            r#"{"v":2,"type":"close"}"#,
+            r#"{"v":1,"type":"reboot"}"#,
+            r#"{"v":1,"type":"followLink"}"#,
+            r#"{"v":1,"type":"theme","theme":"pink"}"#,
+            r#"{"v":1,"type":"scroll","y":"100"}"#,
+            r#""close""#,
+            r#"{"v":1,"type":"followLink","url":"x" "close"}"#,
+        ] {
+            assert!(PageMessage::parse(text).is_err(), "{} should be rejected", text);
+        }
+    }
+
+    #[test]
+    fn test_host_messages() {
+        let find = HostMessage::Find {
+            text: "\"); alert(1); (\"\\\u{2028}".to_string(),
+            match_case: true,
+            whole_words: false,
+            regex: false,
+            backwards: true,
+            from_start: false,
+        };
+        let json = Json::parse(&find.to_json()).unwrap();
+        assert_eq!(json.get("type").and_then(Json::as_str), Some("find"));
+        assert_eq!(json.get("text").and_then(Json::as_str), Some("\"); alert(1); (\"\\\u{2028}"));
+        assert_eq!(json.get("matchCase").and_then(Json::as_bool), Some(true));
+        assert_eq!(json.get("backwards").and_then(Json::as_bool), Some(true));
+        assert_eq!(HostMessage::ShowFind.to_json(), r#"{"v":1,"type":"showFind"}"#);
+        assert_eq!(HostMessage::SelectAll.to_json(), r#"{"v":1,"type":"selectAll"}"#);
+        assert_eq!(HostMessage::ScrollTo { y: 640.5 }.to_json(), r#"{"v":1,"type":"scrollTo","y":640.5}"#);
+        assert_eq!(
+            HostMessage::ShowSection { id: "proxy-configuration".to_string() }.to_json(),
+            r#"{"v":1,"type":"showSection","id":"proxy-configuration"}"#
+        );
+        assert_eq!(
+            HostMessage::Patch { html: "<p>a</p>\n<script>".to_string() }.to_json(),
+            r#"{"v":1,"type":"patch","html":"\u003cp>a\u003c/p>\n\u003cscript>"}"#
+        );
+    }
+}
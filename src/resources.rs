@@ -0,0 +1,355 @@
+//! Files a document refers to. Local ones, like `![diagram](img/arch.png)`,
+//! are rewritten to something the page can reach, since pages are loaded
+//! without a base URL. Remote ones are blocked unless the settings allow them.
+
+use crate::markdown::escape_html;
+use crate::sanitize;
+use std::path::{Component, Path, PathBuf};
+
+/// Host name the viewers map to the folder of the document
+pub const DOCUMENT_HOST: &str = "document.mdview.example";
+
+/// How references to local files are written into a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalFiles {
+    /// `https://document.mdview.example/...`, for a WebView that maps the
+    /// host to the document's folder. Files outside the folder are embedded,
+    /// as long as they fit in `EMBED_LIMIT`.
+    VirtualHost,
+    /// Absolute `file:///` URLs, for exported pages
+    #[allow(dead_code)]
+    FileUrl,
+    /// `data:` URIs, for pages that must work on their own
+    #[allow(dead_code)]
+    Embed,
+}
+
+/// Bytes of files outside the document's folder a `VirtualHost` page embeds.
+/// WebView2 shows pages of up to 2 MB from a string, and base64 adds a third.
+const EMBED_LIMIT: u64 = 1024 * 1024;
+
+/// Bar above a document with blocked content, for the viewer script
+const LOAD_REMOTE_BAR: &str = "<div class=\"mdview-remote-bar\">Remote content is blocked.\
+<button class=\"mdview-load-remote\">Load remote content</button></div>\n";
+
+/// Replace elements that would load something from the network, like remote
+/// images, tracking pixels and badges, by a placeholder showing the URL.
+pub fn block_remote_content(html: &str) -> String {
+    sanitize::replace_start_tags(html, |element, attributes| {
+        let url = attributes
+            .iter()
+            .filter(|(name, _)| loads_url(element, name))
+            .flat_map(|(name, value)| urls(name, value))
+            .find(|url| is_remote(url))?;
+        let kind = match element {
+            "img" | "image" | "input" | "picture" => "image",
+            "video" | "audio" => element,
+            "iframe" | "frame" => "frame",
+            _ => "content",
+        };
+        Some(placeholder(&format!("Remote {} not loaded", kind), &format!("Remote {}", kind), attributes, url))
+    })
+}
+
+/// What is shown in place of an element that is not loaded: its alt text, or
+/// `label` without one, and the URL
+fn placeholder(title: &str, label: &str, attributes: &[(String, String)], url: &str) -> String {
+    let alt = attributes.iter().find(|(name, _)| name == "alt").map_or("", |(_, alt)| alt.trim());
+    format!(
+        "<span class=\"mdview-remote\" title=\"{}\">{} <span class=\"mdview-remote-url\">{}</span></span>",
+        title,
+        escape_html(if alt.is_empty() { label } else { alt }),
+        escape_html(url)
+    )
+}
+
+/// The bar offering to load blocked content, if `html` has any
+pub fn load_remote_bar(html: &str) -> &'static str {
+    if html.contains("<span class=\"mdview-remote\"") { LOAD_REMOTE_BAR } else { "" }
+}
+
+/// Attributes that make the browser fetch a URL
+fn loads_url(element: &str, attribute: &str) -> bool {
+    matches!(
+        (element, attribute),
+        (_, "src" | "srcset" | "poster" | "background" | "lowsrc")
+            | ("link", "href")
+            | ("object", "data")
+            | ("image" | "use" | "feimage", "href" | "xlink:href")
+    )
+}
+
+/// The URLs in an attribute value. `srcset` lists several, each with a size.
+fn urls<'a>(attribute: &str, value: &'a str) -> Vec<&'a str> {
+    if attribute == "srcset" {
+        value.split(',').filter_map(|candidate| candidate.split_whitespace().next()).collect()
+    } else {
+        vec![value.trim()]
+    }
+}
+
+/// Whether loading `url` goes to the network. Paths on other machines, like
+/// `\\server\share\a.png`, do too.
+fn is_remote(url: &str) -> bool {
+    let url = url.trim().to_ascii_lowercase();
+    if url.starts_with(['/', '\\']) && url[1..].starts_with(['/', '\\']) {
+        return true;
+    }
+    match url.find([':', '/', '\\', '?', '#']) {
+        // A drive letter is a local path, not a scheme
+        Some(i) if url[i..].starts_with(':') && i > 1 => match &url[..i] {
+            "data" | "blob" | "about" | "javascript" | "mailto" => false,
+            "file" => url[i..].starts_with("://") && !url[i..].starts_with(":///"),
+            _ => true,
+        },
+        _ => false,
+    }
+}
+
+/// The absolute folder of a document, which its references are relative to.
+/// The current directory for documents without a folder, like stdin.
+pub fn document_folder(path: &Path) -> PathBuf {
+    let folder = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    std::path::absolute(folder).unwrap_or_else(|_| folder.to_path_buf())
+}
+
+/// Rewrite image sources and stylesheet links in `html` that point to local
+/// files, resolving relative ones against `base_dir`. References that cannot
+/// be resolved are left alone.
+pub fn link_local_files(html: &str, base_dir: &Path, mode: LocalFiles) -> String {
+    let html = match mode {
+        LocalFiles::VirtualHost => leave_out_large_files(html, base_dir),
+        _ => html.to_string(),
+    };
+    sanitize::rewrite_attributes(&html, |element, name, value| {
+        let relative = local_reference(element, name, value)?;
+        let path = base_dir.join(&relative);
+        match mode {
+            LocalFiles::VirtualHost if is_inside(&relative) => {
+                Some(format!("https://{}/{}", DOCUMENT_HOST, value.trim().trim_start_matches("./")))
+            }
+            LocalFiles::FileUrl => Some(file_url(&path)),
+            _ => data_uri(&path),
+        }
+    })
+}
+
+/// The relative or absolute path of the local image or stylesheet an
+/// attribute refers to
+fn local_reference(element: &str, attribute: &str, value: &str) -> Option<PathBuf> {
+    match (element, attribute) {
+        ("img", "src") => local_path(value),
+        ("link", "href") => local_path(value).filter(|p| media_type(p) == Some("text/css")),
+        _ => None,
+    }
+}
+
+/// Whether a relative path stays in the folder it is relative to
+fn is_inside(relative: &Path) -> bool {
+    relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Replace the images outside `base_dir` that no longer fit in `EMBED_LIMIT`
+/// by a placeholder, and leave out such stylesheets
+fn leave_out_large_files(html: &str, base_dir: &Path) -> String {
+    let mut embedded = 0;
+    sanitize::replace_start_tags(html, |element, attributes| {
+        let (relative, url) = attributes
+            .iter()
+            .find_map(|(name, value)| Some((local_reference(element, name, value)?, value)))?;
+        if is_inside(&relative) {
+            return None;
+        }
+        let size = std::fs::metadata(base_dir.join(&relative)).map_or(0, |m| m.len());
+        if embedded + size <= EMBED_LIMIT {
+            embedded += size;
+            return None;
+        }
+        Some(match element {
+            "img" => placeholder("Image too large to show", "Image", attributes, url),
+            _ => String::new(),
+        })
+    })
+}
+
+/// The local files `html` shows: images and stylesheets
+pub fn local_files(html: &str, base_dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    sanitize::rewrite_attributes(html, |element, name, value| {
+        let path = match (element, name) {
+            ("img", "src") | ("link", "href") => local_path(value),
+            _ => None,
+        };
+        if let Some(path) = path.map(|p| base_dir.join(p))
+            && !files.contains(&path)
+        {
+            files.push(path);
+        }
+        None
+    });
+    files
+}
+
+/// The path of a relative URL or a local file name. `None` for URLs with a
+/// scheme, fragments, root-relative URLs and network paths.
+pub fn local_path(url: &str) -> Option<PathBuf> {
+    let url = url.trim();
+    if url.is_empty() || url.starts_with(['#', '/', '\\']) {
+        return None;
+    }
+    // A drive letter is a local path, not a scheme
+    if let Some(i) = url.find([':', '/', '\\', '?', '#'])
+        && url[i..].starts_with(':')
+        && i != 1
+    {
+        return None;
+    }
+    let path = &url[..url.find(['?', '#']).unwrap_or(url.len())];
+    Some(PathBuf::from(percent_decode(path)))
+}
+
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = text.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// `file:///C:/docs/my%20notes/a.png` for an absolute path
+fn file_url(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut url = String::from("file://");
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for c in path.chars() {
+        match c {
+            ' ' | '"' | '#' | '%' | '?' | '<' | '>' => url.push_str(&format!("%{:02X}", c as u32)),
+            c => url.push(c),
+        }
+    }
+    url
+}
+
+/// The file as a `data:` URI, if it can be read and its type is known
+fn data_uri(path: &Path) -> Option<String> {
+    let media_type = media_type(path)?;
+    let data = std::fs::read(path).ok()?;
+    Some(format!("data:{};base64,{}", media_type, base64(&data)))
+}
+
+fn media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "css" => "text/css",
+        _ => return None,
+    })
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((bytes[0] as u32) << 16) | ((bytes[1] as u32) << 8) | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_paths() {
+        assert_eq!(local_path("img/arch.png"), Some(PathBuf::from("img/arch.png")));
+        assert_eq!(local_path("./my%20diagram.svg?v=2#top"), Some(PathBuf::from("./my diagram.svg")));
+        assert_eq!(local_path("C:\\img\\a.png"), Some(PathBuf::from("C:\\img\\a.png")));
+        assert_eq!(local_path("https://example.com/a.png"), None);
+        assert_eq!(local_path("data:image/png;base64,AAAA"), None);
+        assert_eq!(local_path("//example.com/a.png"), None);
+        assert_eq!(local_path("#section"), None);
+        assert_eq!(local_path("\\\\server\\share\\a.png"), None);
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+    }
+
+    #[test]
+    fn test_block_remote_content() {
+        let html = "<p><a href=\"https://ci.example\"><img src=\"https://ci.example/badge.svg\" alt=\"build\" /></a> \
+                    <img src=\"img/a.png\"> <img src=\"\\\\server\\share\\b.png\"></p>";
+        assert_eq!(
+            block_remote_content(html),
+            "<p><a href=\"https://ci.example\"><span class=\"mdview-remote\" title=\"Remote image not loaded\">build \
+             <span class=\"mdview-remote-url\">https://ci.example/badge.svg</span></span></a> <img src=\"img/a.png\"> \
+             <span class=\"mdview-remote\" title=\"Remote image not loaded\">Remote image \
+             <span class=\"mdview-remote-url\">\\\\server\\share\\b.png</span></span></p>"
+        );
+        assert!(!load_remote_bar(&block_remote_content(html)).is_empty());
+        assert!(is_remote("//cdn.example/x.png") && is_remote("HTTP://x") && is_remote("file://server/x.png"));
+        assert!(!is_remote("C:\\img\\a.png") && !is_remote("data:image/png;base64,AA") && !is_remote("file:///C:/a.png"));
+        let media = "<video poster=\"cover.png\" src=\"https://x.example/v.mp4\"></video><img srcset=\"a.png 1x, https://x.example/b.png 2x\">";
+        assert_eq!(block_remote_content(media).matches("mdview-remote-url").count(), 2);
+    }
+
+    #[test]
+    fn test_link_local_files() {
+        let dir = std::env::temp_dir().join(format!("mdview-resources-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("img")).unwrap();
+        std::fs::write(dir.join("img").join("a.svg"), "<svg/>").unwrap();
+        let html = "<p><img src=\"img/a.svg\" alt=\"a\"> <img src=\"https://example.com/b.png\"></p>";
+
+        let hosted = link_local_files(html, &dir, LocalFiles::VirtualHost);
+        assert!(hosted.contains("<img src=\"https://document.mdview.example/img/a.svg\" alt=\"a\">"));
+        assert!(hosted.contains("<img src=\"https://example.com/b.png\">"));
+
+        let embedded = link_local_files(html, &dir, LocalFiles::Embed);
+        assert!(embedded.contains("<img src=\"data:image/svg+xml;base64,PHN2Zy8+\" alt=\"a\">"));
+        let outside = link_local_files("<img src=\"../img/a.svg\">", &dir.join("img"), LocalFiles::VirtualHost);
+        assert_eq!(outside, "<img src=\"data:image/svg+xml;base64,PHN2Zy8+\">");
+        std::fs::write(dir.join("large.png"), vec![0; EMBED_LIMIT as usize + 1]).unwrap();
+        let large = "<img src=\"../large.png\" alt=\"chart\"><img src=\"../img/a.svg\">";
+        assert_eq!(
+            link_local_files(large, &dir.join("img"), LocalFiles::VirtualHost),
+            "<span class=\"mdview-remote\" title=\"Image too large to show\">chart \
+             <span class=\"mdview-remote-url\">../large.png</span></span><img src=\"data:image/svg+xml;base64,PHN2Zy8+\">"
+        );
+
+        assert_eq!(local_files(html, &dir), vec![dir.join("img/a.svg")]);
+
+        let linked = link_local_files(html, &dir, LocalFiles::FileUrl);
+        assert!(linked.contains("src=\"file:///"));
+        assert!(linked.contains("/img/a.svg\""));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,402 @@
+//! Allowlist HTML sanitizer for the raw HTML in documents. Keeps the tags and
+//! attributes GitHub allows in markdown, so raw HTML in a downloaded file
+//! cannot run script in the viewer or pass for its controls.
+
+/// Elements that are kept. Other tags are dropped and their content kept.
+const ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "bdo", "blockquote", "br", "caption", "cite", "code", "dd", "del", "details", "dfn", "div",
+    "dl", "dt", "em", "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "input", "ins",
+    "kbd", "li", "mark", "nav", "ol", "p", "pre", "q", "rp", "rt", "ruby", "s", "samp", "small", "span", "strike",
+    "strong", "sub", "summary", "sup", "table", "tbody", "td", "tfoot", "th", "thead", "time", "tr", "tt", "ul",
+    "var", "wbr",
+];
+
+/// Elements that are dropped together with their content
+const DROP_CONTENT: &[&str] = &[
+    "iframe", "math", "noembed", "noframes", "noscript", "object", "plaintext", "script", "select", "style", "svg",
+    "template", "textarea", "title", "xmp",
+];
+
+/// Attributes allowed on every kept element
+const ATTRIBUTES: &[&str] = &[
+    "abbr", "align", "alt", "aria-describedby", "aria-hidden", "aria-label", "aria-labelledby", "axis", "border",
+    "cellpadding", "cellspacing", "clear", "colspan", "color", "compact", "coords", "datetime", "dir",
+    "headers", "height", "hreflang", "hspace", "id", "itemprop", "lang", "name", "nowrap", "open", "rel", "rev",
+    "role", "rows", "rowspan", "rules", "scope", "shape", "size", "span", "start", "summary", "tabindex", "target",
+    "title", "type", "valign", "value", "vspace", "width",
+];
+
+/// Prefix of `id` and `name` attributes, so a document cannot take the ids of
+/// the page around it
+pub const ID_PREFIX: &str = "user-content-";
+
+/// URL schemes allowed in links. Images and quotes only get http(s).
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+const SOURCE_SCHEMES: &[&str] = &["http", "https"];
+
+/// Remove everything from `html` that is not on the allowlist: scripts, event
+/// handlers, `javascript:` URLs, frames, forms, styles and classes. Ids and
+/// names get `ID_PREFIX`.
+pub fn sanitize_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(lt) = rest.find('<') {
+        out.push_str(&rest[..lt]);
+        rest = &rest[lt..];
+
+        // Comments, doctypes and processing instructions are dropped
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |i| &comment[i + 3..]);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |i| &rest[i + 1..]);
+            continue;
+        }
+
+        let Some(mut tag) = parse_tag(rest) else {
+            out.push_str("&lt;");
+            rest = &rest[1..];
+            continue;
+        };
+        rest = &rest[tag.len..];
+
+        if !tag.closing && DROP_CONTENT.contains(&tag.name.as_str()) {
+            rest = skip_past_end_tag(rest, &tag.name);
+        } else if ELEMENTS.contains(&tag.name.as_str()) {
+            let element = &tag.name;
+            tag.attributes
+                .retain(|(name, value)| attribute_allowed(element, name, value.as_deref().unwrap_or_default()));
+            for (name, value) in tag.attributes.iter_mut() {
+                if let (true, Some(value)) = (name == "id" || name == "name", value.as_mut()) {
+                    value.insert_str(0, ID_PREFIX);
+                }
+            }
+            write_tag(&mut out, &tag);
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Change attribute values in `html` and leave everything else as it is.
+/// `rewrite` gets the element, attribute and decoded value, and returns a new value.
+pub fn rewrite_attributes(html: &str, mut rewrite: impl FnMut(&str, &str, &str) -> Option<String>) -> String {
+    replace_tags(html, |tag| {
+        let mut tag = tag.clone();
+        let mut changed = false;
+        for (name, value) in tag.attributes.iter_mut() {
+            if let Some(new) = value.as_deref().and_then(|v| rewrite(&tag.name, name, &decode_entities(v))) {
+                *value = Some(new.replace('&', "&amp;"));
+                changed = true;
+            }
+        }
+        changed.then(|| {
+            let mut out = String::new();
+            write_tag(&mut out, &tag);
+            out
+        })
+    })
+}
+
+/// Replace start tags in `html` by other markup. `replace` gets the element and
+/// its decoded attributes, and returns the markup to put in place of the tag.
+pub fn replace_start_tags(html: &str, mut replace: impl FnMut(&str, &[(String, String)]) -> Option<String>) -> String {
+    replace_tags(html, |tag| {
+        if tag.closing {
+            return None;
+        }
+        let attributes: Vec<(String, String)> = tag
+            .attributes
+            .iter()
+            .map(|(name, value)| (name.clone(), value.as_deref().map(decode_entities).unwrap_or_default()))
+            .collect();
+        replace(&tag.name, &attributes)
+    })
+}
+
+/// Copy `html`, putting the markup `replace` returns in place of a tag.
+/// Comments and the content of scripts and styles are copied unchanged.
+fn replace_tags(html: &str, mut replace: impl FnMut(&HtmlTag) -> Option<String>) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(lt) = rest.find('<') {
+        out.push_str(&rest[..lt]);
+        rest = &rest[lt..];
+
+        let copied = if rest.starts_with("<!--") {
+            rest.find("-->").map_or(rest.len(), |i| i + 3)
+        } else {
+            match parse_tag(rest) {
+                None => 1,
+                Some(tag) => {
+                    if let Some(markup) = replace(&tag) {
+                        out.push_str(&markup);
+                        rest = &rest[tag.len..];
+                        continue;
+                    }
+                    match tag.name.as_str() {
+                        "script" | "style" if !tag.closing => {
+                            rest.len() - skip_past_end_tag(&rest[tag.len..], &tag.name).len()
+                        }
+                        _ => tag.len,
+                    }
+                }
+            }
+        };
+        out.push_str(&rest[..copied]);
+        rest = &rest[copied..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[derive(Clone)]
+struct HtmlTag {
+    name: String,
+    closing: bool,
+    self_closing: bool,
+    attributes: Vec<(String, Option<String>)>,
+    /// Bytes of source up to and including `>`
+    len: usize,
+}
+
+/// A start or end tag at the start of `html`. `None` if it is not a tag, or it
+/// is never closed.
+fn parse_tag(html: &str) -> Option<HtmlTag> {
+    let bytes = html.as_bytes();
+    let closing = html.starts_with("</");
+    let mut i = if closing { 2 } else { 1 };
+    if !bytes.get(i)?.is_ascii_alphabetic() {
+        return None;
+    }
+
+    let is_name_end = |b: u8| b.is_ascii_whitespace() || b == b'/' || b == b'>';
+    let start = i;
+    while !is_name_end(*bytes.get(i)?) {
+        i += 1;
+    }
+    let mut tag = HtmlTag {
+        name: html[start..i].to_ascii_lowercase(),
+        closing,
+        self_closing: false,
+        attributes: Vec::new(),
+        len: 0,
+    };
+
+    loop {
+        while bytes.get(i)?.is_ascii_whitespace() {
+            i += 1;
+        }
+        match bytes.get(i)? {
+            b'>' => break,
+            b'/' => {
+                tag.self_closing = bytes.get(i + 1) == Some(&b'>');
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let start = i;
+        i += 1;
+        while !is_name_end(*bytes.get(i)?) && bytes[i] != b'=' {
+            i += 1;
+        }
+        let name = html[start..i].to_ascii_lowercase();
+        while bytes.get(i)?.is_ascii_whitespace() {
+            i += 1;
+        }
+        if bytes[i] != b'=' {
+            tag.attributes.push((name, None));
+            continue;
+        }
+        i += 1;
+        while bytes.get(i)?.is_ascii_whitespace() {
+            i += 1;
+        }
+        let value = match bytes.get(i)? {
+            quote @ (b'"' | b'\'') => {
+                let end = i + 1 + html[i + 1..].find(*quote as char)?;
+                let value = &html[i + 1..end];
+                i = end + 1;
+                value
+            }
+            _ => {
+                let start = i;
+                while !bytes.get(i)?.is_ascii_whitespace() && bytes[i] != b'>' {
+                    i += 1;
+                }
+                &html[start..i]
+            }
+        };
+        tag.attributes.push((name, Some(value.to_string())));
+    }
+    tag.len = i + 1;
+    Some(tag)
+}
+
+/// The rest of `html` after `</name>`, or nothing if the element is never closed
+fn skip_past_end_tag<'a>(html: &'a str, name: &str) -> &'a str {
+    let lower = html.to_ascii_lowercase();
+    let end_tag = format!("</{}", name);
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(&end_tag).map(|i| i + from) {
+        let after = i + end_tag.len();
+        match lower.as_bytes().get(after) {
+            Some(b) if b.is_ascii_whitespace() || *b == b'/' || *b == b'>' => {
+                return html[after..].find('>').map_or("", |j| &html[after + j + 1..]);
+            }
+            _ => from = after,
+        }
+    }
+    ""
+}
+
+fn write_tag(out: &mut String, tag: &HtmlTag) {
+    if tag.closing {
+        out.push_str(&format!("</{}>", tag.name));
+        return;
+    }
+    out.push('<');
+    out.push_str(&tag.name);
+    for (name, value) in &tag.attributes {
+        match value {
+            Some(value) => out.push_str(&format!(" {}=\"{}\"", name, value.replace('"', "&quot;"))),
+            None => out.push_str(&format!(" {}", name)),
+        }
+    }
+    out.push_str(if tag.self_closing { " />" } else { ">" });
+}
+
+/// Whether `name="value"` may stay on `element`
+pub fn attribute_allowed(element: &str, name: &str, value: &str) -> bool {
+    match (element, name) {
+        ("a", "href") => safe_url(value, LINK_SCHEMES),
+        ("img", "src" | "longdesc") | ("blockquote" | "del" | "ins" | "q", "cite") => safe_url(value, SOURCE_SCHEMES),
+        ("div", "itemscope" | "itemtype") => true,
+        // Task list checkboxes
+        ("input", "type") => value.eq_ignore_ascii_case("checkbox"),
+        ("input", "checked" | "disabled") => true,
+        ("input", _) => false,
+        // Column alignment in tables
+        ("th" | "td", "style") => {
+            let style = decode_entities(value).to_ascii_lowercase().replace(' ', "");
+            matches!(style.trim_end_matches(';'), "text-align:left" | "text-align:center" | "text-align:right")
+        }
+        _ => ATTRIBUTES.contains(&name),
+    }
+}
+
+/// Relative URLs, and absolute ones with one of `schemes`. Browsers ignore
+/// whitespace, control characters and entities in `java&#09;script:`.
+fn safe_url(value: &str, schemes: &[&str]) -> bool {
+    let url: String = decode_entities(value)
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    match url.find([':', '/', '?', '#']) {
+        // A drive letter is a local path, not a scheme
+        Some(i) if url[i..].starts_with(':') => i == 1 || schemes.contains(&&url[..i]),
+        _ => true,
+    }
+}
+
+/// Numeric character references and the named ones that matter in URLs
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp + 1..];
+
+        if let Some(number) = rest.strip_prefix('#') {
+            let (digits, radix) = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => (hex, 16),
+                None => (number, 10),
+            };
+            let len = digits.find(|c: char| !c.is_digit(radix)).unwrap_or(digits.len());
+            if len > 0 {
+                let c = u32::from_str_radix(&digits[..len], radix).ok().and_then(char::from_u32);
+                out.push(c.unwrap_or('\u{fffd}'));
+                let after = &digits[len..];
+                rest = after.strip_prefix(';').unwrap_or(after);
+                continue;
+            }
+        }
+        let named = [("amp;", '&'), ("colon;", ':'), ("tab;", '\t'), ("newline;", '\n'), ("quot;", '"'), ("lt;", '<'), ("gt;", '>')];
+        match named.iter().find(|(name, _)| rest.get(..name.len()).is_some_and(|n| n.eq_ignore_ascii_case(name))) {
+            Some((name, c)) => {
+                out.push(*c);
+                rest = &rest[name.len()..];
+            }
+            None => out.push('&'),
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_removes_scripts() {
+        assert_eq!(sanitize_html("<p>a<script>alert(1)</script>b</p>"), "<p>ab</p>");
+        assert_eq!(sanitize_html("<img src=x onerror=\"alert(1)\">"), "<img src=\"x\">");
+        assert_eq!(sanitize_html("<iframe src=\"https://example.com\"></iframe>ok"), "ok");
+        assert_eq!(sanitize_html("<SCRIPT>x</script >y<!-- c -->"), "y");
+        assert_eq!(sanitize_html("<svg onload=alert(1)><script>x</script></svg>z"), "z");
+        assert_eq!(sanitize_html("<center>text</center>"), "text");
+        assert_eq!(sanitize_html("1 < 2 <3 <"), "1 &lt; 2 &lt;3 &lt;");
+        assert_eq!(sanitize_html("<a href=\"#\" title='say \"hi\"'>x</a>"), "<a href=\"#\" title=\"say &quot;hi&quot;\">x</a>");
+    }
+
+    #[test]
+    fn test_urls() {
+        assert_eq!(sanitize_html("<a href=\"javascript:alert(1)\">x</a>"), "<a>x</a>");
+        assert_eq!(sanitize_html("<a href=\" JaVa&#x09;script&colon;alert(1)\">x</a>"), "<a>x</a>");
+        assert_eq!(sanitize_html("<a href=\"java\nscript:x\">x</a>"), "<a>x</a>");
+        assert_eq!(sanitize_html("<img src=\"data:text/html,x\">"), "<img>");
+        assert_eq!(sanitize_html("<a href=\"mailto:a@b.c\">m</a>"), "<a href=\"mailto:a@b.c\">m</a>");
+        assert_eq!(sanitize_html("<a href=\"docs/a:b.md#x\">d</a>"), "<a href=\"docs/a:b.md#x\">d</a>");
+        assert_eq!(sanitize_html("<img src=\"C:\\img\\a.png\" />"), "<img src=\"C:\\img\\a.png\" />");
+    }
+
+    #[test]
+    fn test_keeps_formatting() {
+        let html = "<h1><a href=\"#x\" aria-hidden=\"true\">#</a>H</h1>\n\
+                    <table><thead><tr><th style=\"text-align: center\">a</th></tr></thead></table>\n\
+                    <ul>\n<li><input disabled=\"\" type=\"checkbox\" checked=\"\" />\ndone</li>\n</ul>\n\
+                    <details open><summary>Metadata</summary></details>\n";
+        assert_eq!(sanitize_html(html), html);
+        assert_eq!(sanitize_html("<td style=\"color: red\">x</td>"), "<td>x</td>");
+        assert_eq!(sanitize_html("<input type=\"text\" value=\"x\">"), "<input>");
+    }
+
+    #[test]
+    fn test_cannot_pass_for_the_viewer() {
+        assert_eq!(sanitize_html("<a class=\"mdview-load-remote\" href=\"#\">x</a>"), "<a href=\"#\">x</a>");
+        assert_eq!(sanitize_html("<div class=\"mdview-notice\">x</div>"), "<div>x</div>");
+        assert_eq!(
+            sanitize_html("<h2 id=\"intro\">I</h2><a name=\"top\"></a>"),
+            "<h2 id=\"user-content-intro\">I</h2><a name=\"user-content-top\"></a>"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_attributes() {
+        let html = "<p>1 < 2</p><img alt=x src='a.png?a=1&amp;b=2'><!-- <img src=\"b.png\"> --><script>'<img src=c.png>'</script><img src=d.png>";
+        let rewritten = rewrite_attributes(html, |element, name, value| {
+            (element == "img" && name == "src").then(|| format!("/{}", value))
+        });
+        assert_eq!(
+            rewritten,
+            "<p>1 < 2</p><img alt=\"x\" src=\"/a.png?a=1&amp;b=2\"><!-- <img src=\"b.png\"> --><script>'<img src=c.png>'</script><img src=\"/d.png\">"
+        );
+    }
+}
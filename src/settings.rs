@@ -0,0 +1,231 @@
+//! Rendering preferences, layered: mdview.ini, then the document's own
+//! `mdview:` front matter, then the command line
+
+use crate::config::parse_bool;
+use crate::frontmatter::{self, FrontMatter, Value};
+use crate::markdown::Extensions;
+use crate::style::Style;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// Follow the system (or the host application)
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "auto" | "system" => Some(Self::Auto),
+            "light" => Some(Self::Light),
+            "dark" => Some(Self::Dark),
+            _ => None,
+        }
+    }
+
+    /// `data-theme` of a page; pages without one follow the system color scheme
+    pub fn attribute(self) -> Option<&'static str> {
+        match self {
+            Self::Auto => None,
+            Self::Light => Some("light"),
+            Self::Dark => Some("dark"),
+        }
+    }
+}
+
+/// What pages do with images and other content from the network
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RemoteContent {
+    Allow,
+    /// Show a placeholder with the URL instead
+    Block,
+    /// Block, and offer to load it in the viewer. Exported pages keep it.
+    #[default]
+    Ask,
+}
+
+impl RemoteContent {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "allow" => Some(Self::Allow),
+            "block" => Some(Self::Block),
+            "ask" => Some(Self::Ask),
+            _ => None,
+        }
+    }
+}
+
+/// Settings for rendering one document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Settings {
+    pub extensions: Extensions,
+    pub theme: Theme,
+    /// Built-in stylesheet of HTML pages
+    pub style: Style,
+    /// User stylesheet added after the built-in one
+    pub css: Option<PathBuf>,
+    /// Prefix headings with section numbers (1, 1.1, 1.2, ...)
+    pub number_headings: bool,
+    /// Text width in columns. Unset: the console width, or the default page width in HTML.
+    pub width: Option<usize>,
+    /// Put a table of contents at the top (a placeholder in the document always gets one)
+    pub toc: bool,
+    /// Deepest heading level in the table of contents
+    pub toc_depth: Option<usize>,
+    /// Keep raw HTML as written, scripts included. Only the command line can set this.
+    pub trust: bool,
+    /// Loading of remote images and media. Documents cannot change this.
+    pub remote: RemoteContent,
+}
+
+/// One setting changed by a layer
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Extension(String, bool),
+    Theme(Theme),
+    Style(Style),
+    /// Only from the command line and mdview.ini, documents don't pick files to read
+    Css(PathBuf),
+    NumberHeadings(bool),
+    Width(usize),
+    Toc(bool),
+    TocDepth(usize),
+    /// Only from the command line (`--trust`)
+    #[allow(dead_code)]
+    Trust(bool),
+    /// Only from the command line and mdview.ini, documents must not load what they like
+    Remote(RemoteContent),
+}
+
+impl Change {
+    /// A `key = value` setting as written in mdview.ini or front matter. `Trust`,
+    /// `Css` and `Remote` are left out on purpose: a document must not switch off
+    /// its own sanitizing, read other files or load remote content.
+    pub fn parse(key: &str, value: &str) -> Option<Self> {
+        match key.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "theme" => Theme::parse(value).map(Change::Theme),
+            "style" => Style::parse(value).map(Change::Style),
+            "number_headings" => parse_bool(value.trim()).map(Change::NumberHeadings),
+            "width" => value.trim().parse().ok().filter(|w| *w >= 20).map(Change::Width),
+            "toc" => parse_bool(value.trim()).map(Change::Toc),
+            "toc_depth" => value.trim().parse().ok().filter(|d| (1..=6).contains(d)).map(Change::TocDepth),
+            _ => None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn apply(&mut self, changes: &[Change]) {
+        for change in changes {
+            match change {
+                Change::Extension(name, enabled) => {
+                    self.extensions.set(name, *enabled);
+                }
+                Change::Theme(theme) => self.theme = *theme,
+                Change::Style(style) => self.style = *style,
+                Change::Css(path) => self.css = Some(path.clone()),
+                Change::NumberHeadings(enabled) => self.number_headings = *enabled,
+                Change::Width(width) => self.width = Some(*width),
+                Change::Toc(enabled) => self.toc = *enabled,
+                Change::TocDepth(depth) => self.toc_depth = Some(*depth),
+                Change::Trust(trust) => self.trust = *trust,
+                Change::Remote(remote) => self.remote = *remote,
+            }
+        }
+    }
+}
+
+/// Defaults below the document and command line options above it
+#[derive(Debug, Clone, Default)]
+pub struct RenderSettings {
+    pub defaults: Settings,
+    pub overrides: Vec<Change>,
+}
+
+impl RenderSettings {
+    /// The settings for a document, including its own front matter settings
+    pub fn resolve(&self, markdown: &str) -> Settings {
+        let mut settings = self.defaults.clone();
+        if let Some(front_matter) = frontmatter::split(markdown).0 {
+            settings.apply(&document_changes(&front_matter));
+        }
+        settings.apply(&self.overrides);
+        settings
+    }
+}
+
+/// Changes requested under the `mdview:` key of the front matter. Unknown keys
+/// and values are ignored.
+pub fn document_changes(front_matter: &FrontMatter) -> Vec<Change> {
+    let Some(Value::Table(fields)) = front_matter.get("mdview") else {
+        return Vec::new();
+    };
+
+    let mut changes = Vec::new();
+    for (key, value) in fields {
+        if key != "extensions" {
+            changes.extend(Change::parse(key, &value.display()));
+            continue;
+        }
+        // A list of extensions to enable, or a table of name: true/false
+        match value {
+            Value::List(names) => {
+                changes.extend(names.iter().filter_map(Value::as_str).map(|n| Change::Extension(n.to_string(), true)));
+            }
+            Value::Table(switches) => {
+                for (name, enabled) in switches {
+                    if let Some(enabled) = parse_bool(&enabled.display()) {
+                        changes.push(Change::Extension(name.clone(), enabled));
+                    }
+                }
+            }
+            Value::String(names) => {
+                changes.extend(names.split(',').map(|n| Change::Extension(n.trim().to_string(), true)));
+            }
+            _ => {}
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_changes() {
+        let md = "---\ntitle: x\nmdview:\n  toc: true\n  theme: dark\n  number_headings: true\n  width: 100\n  extensions: [math, wikilinks]\n  unknown: 1\n---\n";
+        let front_matter = frontmatter::split(md).0.unwrap();
+        assert_eq!(
+            document_changes(&front_matter),
+            vec![
+                Change::Toc(true),
+                Change::Theme(Theme::Dark),
+                Change::NumberHeadings(true),
+                Change::Width(100),
+                Change::Extension("math".to_string(), true),
+                Change::Extension("wikilinks".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_command_line_wins_over_document() {
+        let render = RenderSettings {
+            defaults: Settings { theme: Theme::Light, width: Some(60), ..Settings::default() },
+            overrides: vec![Change::Width(120), Change::Extension("math".to_string(), false)],
+        };
+        let md = "+++\n[mdview]\ntheme = \"dark\"\nwidth = 80\nextensions = { math = true, smart = true }\n+++\nText";
+        let settings = render.resolve(md);
+        assert_eq!(settings.theme, Theme::Dark);
+        assert_eq!(settings.width, Some(120));
+        assert!(!settings.extensions.math);
+        assert!(settings.extensions.smart_punctuation);
+
+        // Without front matter the defaults apply
+        assert_eq!(render.resolve("Text").theme, Theme::Light);
+    }
+}
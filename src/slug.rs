@@ -0,0 +1,71 @@
+//! Heading anchors compatible with GitHub's slugs
+
+use std::collections::HashMap;
+
+/// GitHub's slug for heading text: lowercase, punctuation and symbols removed,
+/// spaces turned into hyphens. Letters and digits of any script are kept.
+pub fn slugify(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Hands out unique slugs for the headings of one document. Repeats get
+/// `-1`, `-2`, ... appended, like on GitHub.
+#[derive(Debug, Default)]
+pub struct Slugger {
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    pub fn slug(&mut self, text: &str) -> String {
+        self.unique(slugify(text))
+    }
+
+    /// Reserve an id that was chosen by the author (`{#id}`), so generated ones avoid it
+    pub fn reserve(&mut self, id: &str) {
+        self.seen.entry(id.to_string()).or_insert(0);
+    }
+
+    fn unique(&mut self, base: String) -> String {
+        let mut slug = base.clone();
+        while self.seen.contains_key(&slug) {
+            let count = self.seen.entry(base.clone()).or_insert(0);
+            *count += 1;
+            slug = format!("{}-{}", base, count);
+        }
+        self.seen.insert(slug.clone(), 0);
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello World!"), "hello-world");
+        assert_eq!(slugify("C++ & Rust"), "c--rust");
+        assert_eq!(slugify("Use `--toc-depth`"), "use---toc-depth");
+        assert_eq!(slugify("Über Größe 2.0"), "über-größe-20");
+        assert_eq!(slugify("snake_case 🚀"), "snake_case-");
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut slugger = Slugger::default();
+        assert_eq!(slugger.slug("Intro"), "intro");
+        assert_eq!(slugger.slug("Intro"), "intro-1");
+        assert_eq!(slugger.slug("Intro 1"), "intro-1-1");
+        assert_eq!(slugger.slug("Intro"), "intro-2");
+        slugger.reserve("setup");
+        assert_eq!(slugger.slug("Setup"), "setup-1");
+    }
+}
@@ -0,0 +1,272 @@
+//! Markdown source view with syntax coloring driven by the parser's source offsets
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+use std::ops::Range;
+
+use crate::markdown::Extensions;
+use crate::terminal::{ansi, TerminalCaps};
+
+/// Syntax element a source byte belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    Heading,
+    Emphasis,
+    Marker,
+    Link,
+    InlineCode,
+    CodeFence,
+    CodeBlock,
+    ListMarker,
+    Quote,
+    FrontMatter,
+    Html,
+    Rule,
+}
+
+impl SpanKind {
+    fn css_class(self) -> &'static str {
+        match self {
+            SpanKind::Heading => "md-heading",
+            SpanKind::Emphasis => "md-emphasis",
+            SpanKind::Marker => "md-marker",
+            SpanKind::Link => "md-link",
+            SpanKind::InlineCode => "md-code",
+            SpanKind::CodeFence => "md-fence",
+            SpanKind::CodeBlock => "md-code-block",
+            SpanKind::ListMarker => "md-list",
+            SpanKind::Quote => "md-quote",
+            SpanKind::FrontMatter => "md-front-matter",
+            SpanKind::Html => "md-html",
+            SpanKind::Rule => "md-rule",
+        }
+    }
+
+    fn ansi_style(self, caps: &TerminalCaps) -> String {
+        match self {
+            SpanKind::Heading => format!("{}{}", ansi::BOLD, ansi::FG_MAGENTA),
+            SpanKind::Emphasis => ansi::ITALIC.to_string(),
+            SpanKind::Marker | SpanKind::ListMarker => ansi::FG_YELLOW.to_string(),
+            SpanKind::Link => ansi::FG_BLUE.to_string(),
+            SpanKind::InlineCode | SpanKind::CodeBlock => {
+                if caps.true_color {
+                    ansi::fg_rgb(230, 192, 123)
+                } else {
+                    ansi::FG_CYAN.to_string()
+                }
+            }
+            SpanKind::CodeFence => format!("{}{}", ansi::DIM, ansi::FG_GREEN),
+            SpanKind::Quote | SpanKind::FrontMatter | SpanKind::Rule => ansi::FG_GRAY.to_string(),
+            SpanKind::Html => ansi::FG_RED.to_string(),
+        }
+    }
+}
+
+/// Classify every byte of the source, parsed with the document's `extensions`.
+/// Inner elements override the ones around them.
+pub fn highlight(markdown: &str, extensions: &Extensions) -> Vec<Option<SpanKind>> {
+    let mut options = extensions.options();
+    options.insert(Options::ENABLE_YAML_STYLE_METADATA_BLOCKS);
+    options.insert(Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS);
+
+    let mut styles = vec![None; markdown.len()];
+    let mut paint = |range: Range<usize>, kind: SpanKind| {
+        let end = range.end.min(styles.len());
+        for style in &mut styles[range.start.min(end)..end] {
+            *style = Some(kind);
+        }
+    };
+
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        let text = &markdown[range.clone()];
+        match event {
+            Event::Start(Tag::Heading { .. }) => paint(range, SpanKind::Heading),
+            Event::Start(Tag::Emphasis | Tag::Strong | Tag::Strikethrough) => {
+                let marker = text.as_bytes()[0];
+                let open = text.bytes().take_while(|&b| b == marker).count();
+                let close = text.bytes().rev().take_while(|&b| b == marker).count();
+                paint(range.clone(), SpanKind::Emphasis);
+                paint(range.start..range.start + open, SpanKind::Marker);
+                paint(range.end - close..range.end, SpanKind::Marker);
+            }
+            Event::Start(Tag::Link { .. } | Tag::Image { .. }) => paint(range, SpanKind::Link),
+            Event::Start(Tag::CodeBlock(kind)) => {
+                paint(range.clone(), SpanKind::CodeBlock);
+                if let CodeBlockKind::Fenced(_) = kind {
+                    let first_line_end = text.find('\n').map_or(range.end, |i| range.start + i);
+                    paint(range.start..first_line_end, SpanKind::CodeFence);
+                    let trimmed = text.trim_end_matches(['\n', '\r']);
+                    if let Some(last_line) = trimmed.rfind('\n') {
+                        let line = trimmed[last_line + 1..].trim_start();
+                        if line.starts_with("```") || line.starts_with("~~~") {
+                            paint(range.start + last_line + 1..range.start + trimmed.len(), SpanKind::CodeFence);
+                        }
+                    }
+                }
+            }
+            Event::Start(Tag::Item) => {
+                let indent = text.len() - text.trim_start().len();
+                let marker_len = list_marker_len(&text[indent..]);
+                paint(range.start + indent..range.start + indent + marker_len, SpanKind::ListMarker);
+            }
+            Event::TaskListMarker(_) => paint(range, SpanKind::ListMarker),
+            Event::Start(Tag::BlockQuote(_)) => {
+                let mut offset = range.start;
+                for line in text.split_inclusive('\n') {
+                    let indent = line.len() - line.trim_start_matches([' ', '>']).len();
+                    if line.trim_start().starts_with('>') {
+                        paint(offset..offset + indent, SpanKind::Quote);
+                    }
+                    offset += line.len();
+                }
+            }
+            Event::Start(Tag::MetadataBlock(_)) => paint(range, SpanKind::FrontMatter),
+            Event::Start(Tag::HtmlBlock) | Event::InlineHtml(_) => paint(range, SpanKind::Html),
+            Event::Code(_) | Event::InlineMath(_) | Event::DisplayMath(_) => paint(range, SpanKind::InlineCode),
+            Event::Rule => paint(range, SpanKind::Rule),
+            _ => {}
+        }
+    }
+    styles
+}
+
+/// Length of a bullet (`-`, `*`, `+`) or ordered (`1.`, `1)`) list marker
+fn list_marker_len(item: &str) -> usize {
+    let digits = item.bytes().take_while(u8::is_ascii_digit).count();
+    match item.as_bytes().get(digits) {
+        Some(b'.' | b')') if digits > 0 => digits + 1,
+        Some(b'-' | b'*' | b'+') if digits == 0 => 1,
+        _ => 0,
+    }
+}
+
+/// Render the source with ANSI colors, optionally with a line number gutter
+pub fn render_source_to_terminal(
+    markdown: &str,
+    extensions: &Extensions,
+    caps: &TerminalCaps,
+    line_numbers: bool,
+) -> String {
+    let styles = highlight(markdown, extensions);
+    let line_count = markdown.lines().count().max(1);
+    let gutter_width = line_count.to_string().len();
+
+    let mut output = String::new();
+    let mut offset = 0;
+    for (index, line) in markdown.split_inclusive('\n').enumerate() {
+        if line_numbers {
+            if caps.basic_ansi {
+                output.push_str(ansi::FG_GRAY);
+            }
+            let bar = if caps.unicode { '│' } else { '|' };
+            output.push_str(&format!("{:>width$} {} ", index + 1, bar, width = gutter_width));
+            if caps.basic_ansi {
+                output.push_str(ansi::RESET);
+            }
+        }
+
+        let content = line.trim_end_matches(['\n', '\r']);
+        let mut current: Option<SpanKind> = None;
+        for (i, ch) in content.char_indices() {
+            let style = styles[offset + i];
+            if caps.basic_ansi && style != current {
+                if current.is_some() {
+                    output.push_str(ansi::RESET);
+                }
+                if let Some(kind) = style {
+                    output.push_str(&kind.ansi_style(caps));
+                }
+                current = style;
+            }
+            output.push(ch);
+        }
+        if current.is_some() {
+            output.push_str(ansi::RESET);
+        }
+        output.push('\n');
+        offset += line.len();
+    }
+    output
+}
+
+/// Render the source as highlighted HTML (`<pre class="md-source">`), one element per line
+pub fn render_source_to_html(markdown: &str, extensions: &Extensions, line_numbers: bool) -> String {
+    let styles = highlight(markdown, extensions);
+    let class = if line_numbers { "md-source md-line-numbers" } else { "md-source" };
+
+    let mut output = format!("<pre class=\"{}\"><code>", class);
+    let mut offset = 0;
+    for line in markdown.split_inclusive('\n') {
+        output.push_str("<span class=\"md-line\">");
+        let content = line.trim_end_matches(['\n', '\r']);
+        let mut current: Option<SpanKind> = None;
+        for (i, ch) in content.char_indices() {
+            let style = styles[offset + i];
+            if style != current {
+                if current.is_some() {
+                    output.push_str("</span>");
+                }
+                if let Some(kind) = style {
+                    output.push_str(&format!("<span class=\"{}\">", kind.css_class()));
+                }
+                current = style;
+            }
+            match ch {
+                '<' => output.push_str("&lt;"),
+                '>' => output.push_str("&gt;"),
+                '&' => output.push_str("&amp;"),
+                _ => output.push(ch),
+            }
+        }
+        if current.is_some() {
+            output.push_str("</span>");
+        }
+        output.push_str("</span>\n");
+        offset += line.len();
+    }
+    output.push_str("</code></pre>");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_spans() {
+        let md = "# Title\n\n- item with *emphasis*\n\n```rust\nlet x = 1;\n```\n";
+        let styles = highlight(md, &Extensions::default());
+        assert_eq!(styles[0], Some(SpanKind::Heading));
+        let item = md.find('-').unwrap();
+        assert_eq!(styles[item], Some(SpanKind::ListMarker));
+        let star = md.find('*').unwrap();
+        assert_eq!(styles[star], Some(SpanKind::Marker));
+        assert_eq!(styles[star + 1], Some(SpanKind::Emphasis));
+        let fence = md.find("```rust").unwrap();
+        assert_eq!(styles[fence], Some(SpanKind::CodeFence));
+        assert_eq!(styles[md.find("let").unwrap()], Some(SpanKind::CodeBlock));
+
+        // Colored the way the document is parsed
+        let md = "Area $a*b*c$ of [[Page]]\n";
+        let plain = highlight(md, &Extensions::default());
+        assert_eq!(plain[md.find('b').unwrap()], Some(SpanKind::Emphasis));
+        assert_eq!(plain[md.find("[[").unwrap()], None);
+        let extensions = Extensions { math: true, wikilinks: true, ..Extensions::default() };
+        let styles = highlight(md, &extensions);
+        assert_eq!(styles[md.find('b').unwrap()], Some(SpanKind::InlineCode));
+        assert_eq!(styles[md.find("[[").unwrap()], Some(SpanKind::Link));
+    }
+
+    #[test]
+    fn test_source_output_keeps_text() {
+        let md = "---\ntitle: Doc\n---\n\nSee [link](a.md) <b>x</b>\n";
+        let caps = TerminalCaps::basic();
+        let extensions = Extensions::default();
+        assert_eq!(render_source_to_terminal(md, &extensions, &caps, false), md);
+        let numbered = render_source_to_terminal(md, &extensions, &caps, true);
+        assert!(numbered.starts_with("1 | ---\n"));
+
+        let html = render_source_to_html(md, &extensions, true);
+        assert!(html.contains("<span class=\"md-front-matter\">title: Doc</span>"));
+        assert!(html.contains("&lt;b&gt;"));
+    }
+}
@@ -0,0 +1,322 @@
+//! Splits a markdown stream into blocks that can be rendered as soon as they are complete
+
+/// Default cap on text held back while waiting for a block to close
+pub const DEFAULT_LIMIT: usize = 256 * 1024;
+
+/// An open fenced code block
+struct Fence {
+    marker: char,
+    len: usize,
+    opener: String,
+}
+
+impl Fence {
+    /// Recognize an opening fence: up to three spaces, then three or more backticks or tildes
+    fn open(line: &str) -> Option<Self> {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if indent > 3 {
+            return None;
+        }
+        let rest = &line[indent..];
+        let marker = rest.chars().next().filter(|&c| c == '`' || c == '~')?;
+        let len = rest.chars().take_while(|&c| c == marker).count();
+        if len < 3 || (marker == '`' && rest[len..].contains('`')) {
+            return None;
+        }
+        Some(Self {
+            marker,
+            len,
+            opener: line.trim_end_matches(['\r', '\n']).to_string() + "\n",
+        })
+    }
+
+    fn closes(&self, line: &str) -> bool {
+        let trimmed = line.trim();
+        let len = trimmed.chars().take_while(|&c| c == self.marker).count();
+        len >= self.len && len == trimmed.chars().count()
+    }
+
+    fn closer(&self) -> String {
+        self.marker.to_string().repeat(self.len) + "\n"
+    }
+}
+
+/// Collects lines until a block is closed. Memory is bounded by `limit`: a block
+/// that grows beyond it is released early (a code block is closed and reopened).
+pub struct BlockSplitter {
+    pending: String,
+    fence: Option<Fence>,
+    /// Closing delimiter of front matter still being read
+    front_matter: Option<&'static str>,
+    first_line: bool,
+    /// The pending block ended with a blank line, and the next line may continue it
+    after_blank: bool,
+    /// Reference definitions (`[x]: url`) of the blocks so far
+    definitions: String,
+    limit: usize,
+}
+
+impl BlockSplitter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            pending: String::new(),
+            fence: None,
+            front_matter: None,
+            first_line: true,
+            after_blank: false,
+            definitions: String::new(),
+            limit,
+        }
+    }
+
+    /// Add one line (with or without its newline). Returns the blocks it completed.
+    pub fn push_line(&mut self, line: &str) -> Vec<String> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let mut blocks = Vec::new();
+
+        // Front matter is one block, so it can be recognized and hidden
+        if std::mem::take(&mut self.first_line) {
+            self.front_matter = match line.trim_start_matches('\u{feff}').trim_end() {
+                "---" => Some("---"),
+                "+++" => Some("+++"),
+                _ => None,
+            };
+            if self.front_matter.is_some() {
+                self.pending.push_str(line);
+                self.pending.push('\n');
+                return blocks;
+            }
+        }
+        if let Some(closer) = self.front_matter {
+            let trimmed = line.trim_end();
+            let closes = trimmed == closer || (closer == "---" && trimmed == "...");
+            // Only key: value lines are held back; anything else means the
+            // opening line was a rule, which is shown right away
+            let started = self.pending.lines().skip(1).any(|l| !l.trim().is_empty());
+            if !closes && !is_front_matter_line(line, closer, started) {
+                self.front_matter = None;
+                let held = std::mem::take(&mut self.pending);
+                for held_line in held.lines().chain([line]) {
+                    blocks.extend(self.push_line(held_line));
+                }
+                return blocks;
+            }
+            self.pending.push_str(line);
+            self.pending.push('\n');
+            if closes || self.pending.len() > self.limit {
+                self.front_matter = None;
+                blocks.extend(self.take());
+            }
+            return blocks;
+        }
+
+        if let Some(fence) = &self.fence {
+            self.pending.push_str(line);
+            self.pending.push('\n');
+            if fence.closes(line) {
+                self.fence = None;
+                blocks.extend(self.take());
+            } else if self.pending.len() > self.limit {
+                let mut block = std::mem::replace(&mut self.pending, fence.opener.clone());
+                block.push_str(&fence.closer());
+                blocks.push(block);
+            }
+            return blocks;
+        }
+
+        // A list item or indented code goes on after a blank line when the next line is part of it
+        if self.after_blank && !line.trim().is_empty() {
+            self.after_blank = false;
+            if !line.starts_with([' ', '\t']) && !is_list_item(line) {
+                self.pending.truncate(self.pending.trim_end_matches('\n').len() + 1);
+                blocks.extend(self.take());
+            }
+        }
+
+        if line.trim().is_empty() {
+            let continues = self.pending.starts_with("    ")
+                || self.pending.starts_with('\t')
+                || self.pending.lines().any(is_list_item);
+            if continues && !self.pending.is_empty() {
+                self.pending.push('\n');
+                self.after_blank = true;
+            } else {
+                blocks.extend(self.take());
+            }
+        } else if let Some(fence) = Fence::open(line) {
+            // A fence interrupts whatever came before it
+            blocks.extend(self.take());
+            self.pending.push_str(&fence.opener);
+            self.fence = Some(fence);
+        } else if is_atx_heading(line) {
+            blocks.extend(self.take());
+            blocks.push(self.with_definitions(format!("{}\n", line)));
+        } else if is_underline(line) {
+            // A rule on its own, or the underline of a setext heading
+            self.pending.push_str(line);
+            self.pending.push('\n');
+            blocks.extend(self.take());
+        } else {
+            self.pending.push_str(line);
+            self.pending.push('\n');
+            if self.pending.len() > self.limit {
+                blocks.extend(self.take());
+            }
+        }
+        blocks
+    }
+
+    /// End of input: whatever is still held back
+    pub fn finish(mut self) -> Option<String> {
+        self.take()
+    }
+
+    fn take(&mut self) -> Option<String> {
+        self.after_blank = false;
+        let block = std::mem::take(&mut self.pending);
+        if block.trim().is_empty() { None } else { Some(self.with_definitions(block)) }
+    }
+
+    /// `block` followed by the reference definitions of the blocks before it, so
+    /// that its `[text][x]` links find an `[x]: url` given earlier
+    fn with_definitions(&mut self, block: String) -> String {
+        let earlier = self.definitions.clone();
+        for line in block.lines().filter(|line| is_definition(line)) {
+            self.definitions.push_str(line.trim());
+            self.definitions.push('\n');
+        }
+        if earlier.is_empty() { block } else { format!("{}\n{}", block, earlier) }
+    }
+}
+
+/// `- item`, `* item`, `+ item`, `1. item` or `1) item`, at any indent
+fn is_list_item(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    let marker_len = match trimmed[digits..].chars().next() {
+        Some('-' | '*' | '+') if digits == 0 => 1,
+        Some('.' | ')') if (1..=9).contains(&digits) => digits + 1,
+        _ => return false,
+    };
+    trimmed[marker_len..].chars().next().is_none_or(|c| c == ' ' || c == '\t')
+}
+
+/// `[label]: destination`, a link reference definition (footnotes excluded)
+fn is_definition(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    line.len() - trimmed.len() <= 3
+        && trimmed.starts_with('[')
+        && !trimmed.starts_with("[^")
+        && trimmed.find("]:").is_some_and(|i| i > 1 && !trimmed[i + 2..].trim().is_empty())
+}
+
+/// Whether a line can be part of front matter closed by `closer`: a `key: value`
+/// (YAML) or `key = value` (TOML) line, or once one was seen, what continues it
+fn is_front_matter_line(line: &str, closer: &str, started: bool) -> bool {
+    let line = line.trim_end();
+    if line.is_empty() {
+        return true;
+    }
+    // Before the first key a `#` line is a heading, not a comment
+    if started && (line.starts_with([' ', '\t', '#']) || (closer == "---" && line.starts_with('-'))) {
+        return true;
+    }
+    if closer == "+++" {
+        return line.starts_with('[') || line.split_once('=').is_some_and(|(key, _)| !key.trim().is_empty());
+    }
+    line.split_once(':').is_some_and(|(key, value)| {
+        !key.trim().is_empty() && !key.starts_with([' ', '-']) && (value.is_empty() || value.starts_with(' '))
+    })
+}
+
+fn is_atx_heading(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    line.len() - trimmed.len() <= 3
+        && (1..=6).contains(&hashes)
+        && trimmed[hashes..].chars().next().is_none_or(|c| c == ' ' || c == '\t')
+}
+
+/// `---`, `***`, `___` (thematic breaks) and `===` (setext H1 underline)
+fn is_underline(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    match compact.chars().next() {
+        Some('=') => compact.chars().all(|c| c == '='),
+        Some(marker @ ('-' | '*' | '_')) => compact.len() >= 3 && compact.chars().all(|c| c == marker),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(text: &str, limit: usize) -> Vec<String> {
+        let mut splitter = BlockSplitter::new(limit);
+        let mut blocks: Vec<String> = text.lines().flat_map(|l| splitter.push_line(l)).collect();
+        blocks.extend(splitter.finish());
+        blocks
+    }
+
+    #[test]
+    fn test_blocks_close_at_blank_lines_and_fences() {
+        let blocks = split(
+            "# Title\nSome text\nmore\n\n```\ncode\n\nstill code\n```\n| a | b |\n|---|---|\n| 1 | 2 |\n",
+            DEFAULT_LIMIT,
+        );
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0], "# Title\n");
+        assert_eq!(blocks[1], "Some text\nmore\n");
+        assert_eq!(blocks[2], "```\ncode\n\nstill code\n```\n");
+        assert!(blocks[3].starts_with("| a | b |"));
+    }
+
+    #[test]
+    fn test_setext_heading_and_rule() {
+        let blocks = split("Title\n---\n\n***\nafter\n", DEFAULT_LIMIT);
+        assert_eq!(blocks, vec!["Title\n---\n", "***\n", "after\n"]);
+    }
+
+    #[test]
+    fn test_front_matter_is_one_block() {
+        let blocks = split("---\ntitle: x\n\ntags: [a]\n---\nText\n", DEFAULT_LIMIT);
+        assert_eq!(blocks, vec!["---\ntitle: x\n\ntags: [a]\n---\n", "Text\n"]);
+
+        // A rule at the start is released as soon as the text after it is no front matter
+        let mut splitter = BlockSplitter::new(DEFAULT_LIMIT);
+        assert!(splitter.push_line("---").is_empty());
+        assert_eq!(splitter.push_line("Some text"), vec!["---\n"]);
+        assert_eq!(split("---\n\n# Intro\n", DEFAULT_LIMIT), vec!["---\n", "# Intro\n"]);
+    }
+
+    #[test]
+    fn test_lists_and_references_across_blocks() {
+        let blocks = split("- a\n\n    more\n- b\n\nAfter [docs][d]\n", DEFAULT_LIMIT);
+        assert_eq!(blocks, vec!["- a\n\n    more\n- b\n", "After [docs][d]\n"]);
+
+        let blocks = split("[d]: https://example.com\n\n# See [docs][d]\n\nText [docs][d]\n", DEFAULT_LIMIT);
+        assert_eq!(
+            blocks,
+            vec![
+                "[d]: https://example.com\n",
+                "# See [docs][d]\n\n[d]: https://example.com\n",
+                "Text [docs][d]\n\n[d]: https://example.com\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_code_block_is_released_in_parts() {
+        let mut splitter = BlockSplitter::new(32);
+        assert!(splitter.push_line("~~~rust").is_empty());
+        let mut released = Vec::new();
+        for i in 0..20 {
+            released.extend(splitter.push_line(&format!("let x{} = {};", i, i)));
+        }
+        assert!(!released.is_empty());
+        for part in &released {
+            assert!(part.starts_with("~~~rust\n") && part.ends_with("~~~\n"));
+        }
+        assert!(splitter.pending.len() <= 64);
+    }
+}
@@ -0,0 +1,468 @@
+//! Page stylesheets: built-in styles with light and dark palettes, and the
+//! user's own stylesheet on top
+
+use crate::settings::Settings;
+
+/// Built-in look of HTML pages. Colors come from the style's light or dark palette.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Style {
+    #[default]
+    Github,
+    HighContrast,
+    Sepia,
+    Solarized,
+    /// Black on white with serif text, for paper
+    Print,
+    /// Only the user stylesheet (plus what the viewer needs for its sidebar and panes)
+    None,
+}
+
+struct Palette {
+    dark: bool,
+    background: &'static str,
+    text: &'static str,
+    code_background: &'static str,
+    link: &'static str,
+    border: &'static str,
+}
+
+impl Style {
+    #[allow(dead_code)]
+    pub const NAMES: &[&str] = &["github", "high-contrast", "sepia", "solarized", "print", "none"];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "github" | "default" => Some(Self::Github),
+            "high-contrast" => Some(Self::HighContrast),
+            "sepia" => Some(Self::Sepia),
+            "solarized" => Some(Self::Solarized),
+            "print" => Some(Self::Print),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    fn palette(self, dark: bool) -> Palette {
+        let colors = match (self, dark) {
+            (Self::Github | Self::None, false) => ["#ffffff", "#24292e", "#f6f8fa", "#0366d6", "#e1e4e8"],
+            (Self::Github | Self::None, true) => ["#0d1117", "#c9d1d9", "#161b22", "#58a6ff", "#30363d"],
+            (Self::HighContrast, false) => ["#ffffff", "#000000", "#f0f0f0", "#0000ee", "#000000"],
+            (Self::HighContrast, true) => ["#000000", "#ffffff", "#1a1a1a", "#ffff00", "#ffffff"],
+            (Self::Sepia, false) => ["#f4ecd8", "#5b4636", "#eae0c8", "#8b4513", "#d8c9a8"],
+            (Self::Sepia, true) => ["#2b2418", "#e0d3b8", "#3a3122", "#d9a066", "#4a3f2c"],
+            (Self::Solarized, false) => ["#fdf6e3", "#657b83", "#eee8d5", "#268bd2", "#93a1a1"],
+            (Self::Solarized, true) => ["#002b36", "#839496", "#073642", "#268bd2", "#586e75"],
+            // Paper is white, also with a dark theme
+            (Self::Print, _) => ["#ffffff", "#000000", "#f5f5f5", "#000000", "#999999"],
+        };
+        let [background, text, code_background, link, border] = colors;
+        Palette { dark: dark && self != Self::Print, background, text, code_background, link, border }
+    }
+
+    /// Rules on top of the document stylesheet
+    fn extra_css(self) -> &'static str {
+        match self {
+            Self::HighContrast => HIGH_CONTRAST_CSS,
+            Self::Sepia => SEPIA_CSS,
+            Self::Print => PRINT_CSS,
+            Self::Github | Self::Solarized | Self::None => "",
+        }
+    }
+}
+
+/// The complete stylesheet of a page: both palettes of the style, the style
+/// itself and the user stylesheet. The system color scheme picks the palette
+/// unless `data-theme` on the root element forces one. A user stylesheet that
+/// cannot be read is left out.
+pub fn stylesheet(settings: &Settings) -> String {
+    let style = settings.style;
+    let width = settings.width.map_or("900px".to_string(), |w| format!("{}ch", w));
+
+    let mut css = format!(":root {{\n    --mdview-width: {};\n}}\n", width);
+    css.push_str(&palette_rule(":root", style.palette(false)));
+    // Paper stays white
+    if style != Style::Print {
+        css.push_str("@media (prefers-color-scheme: dark) {\n");
+        css.push_str(&palette_rule(":root", style.palette(true)));
+        css.push_str("}\n");
+        css.push_str(&palette_rule(":root[data-theme=\"light\"]", style.palette(false)));
+        css.push_str(&palette_rule(":root[data-theme=\"dark\"]", style.palette(true)));
+    }
+    if style != Style::None {
+        css.push_str(DOCUMENT_CSS);
+        css.push_str(style.extra_css());
+    }
+    css.push_str(VIEWER_CSS);
+    if let Some(user) = settings.css.as_ref().and_then(|path| std::fs::read_to_string(path).ok()) {
+        // Must not end the <style> element it is embedded in
+        css.push_str(&user.trim_start_matches('\u{feff}').replace("</", "<\\/"));
+    }
+    css
+}
+
+fn palette_rule(selector: &str, palette: Palette) -> String {
+    format!(
+        "{} {{\n    color-scheme: {};\n    --mdview-bg: {};\n    --mdview-text: {};\n    --mdview-code-bg: {};\n    \
+         --mdview-link: {};\n    --mdview-border: {};\n}}\n",
+        selector,
+        if palette.dark { "dark" } else { "light" },
+        palette.background,
+        palette.text,
+        palette.code_background,
+        palette.link,
+        palette.border
+    )
+}
+
+/// Typography and colors of the document itself
+const DOCUMENT_CSS: &str = r#"body {
+    font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif;
+    font-size: 14px;
+    line-height: 1.6;
+    padding: 20px;
+    max-width: var(--mdview-width);
+    margin: 0 auto;
+    background-color: var(--mdview-bg);
+    color: var(--mdview-text);
+}
+a { color: var(--mdview-link); text-decoration: none; }
+a:hover { text-decoration: underline; }
+code {
+    background-color: var(--mdview-code-bg);
+    padding: 0.2em 0.4em;
+    border-radius: 3px;
+    font-family: "Cascadia Code", "Fira Code", Consolas, monospace;
+    font-size: 85%;
+}
+pre {
+    background-color: var(--mdview-code-bg);
+    padding: 16px;
+    overflow: auto;
+    border-radius: 6px;
+}
+pre code {
+    background-color: transparent;
+    padding: 0;
+}
+blockquote {
+    border-left: 4px solid var(--mdview-border);
+    margin: 0;
+    padding-left: 16px;
+    color: var(--mdview-text);
+    opacity: 0.8;
+}
+table {
+    border-collapse: collapse;
+    width: 100%;
+}
+th, td {
+    border: 1px solid var(--mdview-border);
+    padding: 8px 12px;
+    text-align: left;
+}
+th {
+    background-color: var(--mdview-code-bg);
+}
+img {
+    max-width: 100%;
+}
+h1, h2 {
+    border-bottom: 1px solid var(--mdview-border);
+    padding-bottom: 0.3em;
+}
+hr {
+    border: none;
+    border-top: 1px solid var(--mdview-border);
+}
+input[type="checkbox"] {
+    margin-right: 0.5em;
+}
+"#;
+
+const HIGH_CONTRAST_CSS: &str = r#"body {
+    font-size: 16px;
+}
+a {
+    text-decoration: underline;
+}
+blockquote {
+    opacity: 1;
+}
+"#;
+
+const SEPIA_CSS: &str = r#"body {
+    font-family: Georgia, "Palatino Linotype", serif;
+    font-size: 16px;
+}
+"#;
+
+const PRINT_CSS: &str = r#"body {
+    font-family: Georgia, "Times New Roman", serif;
+    font-size: 11pt;
+    max-width: none;
+}
+a {
+    text-decoration: underline;
+}
+pre {
+    border: 1px solid var(--mdview-border);
+    white-space: pre-wrap;
+}
+h1, h2, h3 {
+    break-after: avoid;
+}
+.mdview-sidebar, .mdview-sidebar-toggle, .mdview-theme-toggle, a.anchor {
+    display: none;
+}
+body.mdview-sidebar-open {
+    margin-left: auto;
+}
+"#;
+
+/// Headings anchors, front matter, contents, sidebar and source pane. Kept with
+/// every style.
+const VIEWER_CSS: &str = r#"h1, h2, h3, h4, h5, h6 {
+    position: relative;
+}
+a.anchor {
+    position: absolute;
+    margin-left: -1em;
+    padding-right: 0.25em;
+    opacity: 0;
+    text-decoration: none;
+}
+h1:hover a.anchor, h2:hover a.anchor, h3:hover a.anchor,
+h4:hover a.anchor, h5:hover a.anchor, h6:hover a.anchor,
+a.anchor:focus {
+    opacity: 0.6;
+}
+details.front-matter {
+    margin-bottom: 16px;
+    font-size: 90%;
+}
+details.front-matter summary {
+    cursor: pointer;
+    opacity: 0.7;
+}
+details.front-matter table {
+    width: auto;
+    margin-top: 8px;
+}
+nav.toc {
+    margin-bottom: 16px;
+}
+nav.toc ul {
+    list-style: none;
+    padding-left: 1.2em;
+    margin: 0;
+}
+nav.toc > ul {
+    padding-left: 0;
+}
+nav.toc li {
+    margin: 0.2em 0;
+}
+.mdview-sidebar {
+    position: fixed;
+    top: 0;
+    left: 0;
+    bottom: 0;
+    width: 260px;
+    overflow: auto;
+    padding: 48px 16px 16px;
+    box-sizing: border-box;
+    background-color: var(--mdview-bg);
+    border-right: 1px solid var(--mdview-border);
+    font-size: 13px;
+    transform: translateX(-100%);
+    transition: transform 0.15s;
+}
+body.mdview-sidebar-open .mdview-sidebar {
+    transform: none;
+}
+body.mdview-sidebar-open {
+    margin-left: 280px;
+}
+.mdview-sidebar a {
+    color: var(--mdview-text);
+    opacity: 0.8;
+}
+.mdview-sidebar a.active {
+    color: var(--mdview-link);
+    opacity: 1;
+    font-weight: 600;
+}
+.mdview-sidebar-toggle {
+    position: fixed;
+    top: 8px;
+    left: 8px;
+    z-index: 1;
+    border: 1px solid var(--mdview-border);
+    border-radius: 4px;
+    background-color: var(--mdview-code-bg);
+    color: var(--mdview-text);
+    cursor: pointer;
+}
+a { cursor: pointer; }
+body:has(.mdview-split) {
+    max-width: none;
+}
+.mdview-split {
+    display: grid;
+    grid-template-columns: 1fr 1fr;
+    gap: 24px;
+    align-items: start;
+}
+.mdview-source {
+    position: sticky;
+    top: 0;
+    max-height: calc(100vh - 40px);
+    overflow: auto;
+}
+pre.md-source {
+    margin: 0;
+    white-space: pre-wrap;
+    font-family: "Cascadia Code", "Fira Code", Consolas, monospace;
+    font-size: 13px;
+}
+pre.md-source .md-line {
+    display: block;
+}
+pre.md-line-numbers {
+    counter-reset: line;
+}
+pre.md-line-numbers .md-line::before {
+    counter-increment: line;
+    content: counter(line);
+    display: inline-block;
+    width: 3em;
+    margin-right: 1em;
+    text-align: right;
+    opacity: 0.5;
+}
+.md-heading { color: var(--mdview-link); font-weight: bold; }
+.md-emphasis { font-style: italic; }
+.md-marker, .md-list { color: #d19a66; }
+.md-link { color: var(--mdview-link); }
+.md-code, .md-code-block { color: #98c379; }
+.md-fence, .md-quote, .md-front-matter, .md-rule { opacity: 0.6; }
+.md-html { color: #e06c75; }
+.mdview-theme-toggle {
+    position: fixed;
+    top: 8px;
+    right: 8px;
+    z-index: 1;
+    border: 1px solid var(--mdview-border);
+    border-radius: 4px;
+    background-color: var(--mdview-code-bg);
+    color: var(--mdview-text);
+    cursor: pointer;
+}
+span.mdview-remote {
+    display: inline-block;
+    max-width: 100%;
+    padding: 0 4px;
+    border: 1px dashed var(--mdview-border);
+    border-radius: 3px;
+    font-size: 85%;
+    overflow: hidden;
+    text-overflow: ellipsis;
+    white-space: nowrap;
+    vertical-align: middle;
+}
+.mdview-remote-url {
+    opacity: 0.6;
+}
+.mdview-remote-bar {
+    margin-bottom: 16px;
+    padding: 6px 12px;
+    border: 1px solid var(--mdview-border);
+    border-radius: 4px;
+    background-color: var(--mdview-code-bg);
+    font-size: 13px;
+}
+.mdview-remote-bar button {
+    margin-left: 8px;
+    cursor: pointer;
+}
+mark.mdview-match {
+    background-color: #fff3a3;
+    color: #1f2328;
+}
+mark.mdview-match-current {
+    background-color: #ff9632;
+}
+.mdview-findbar {
+    position: fixed;
+    top: 8px;
+    right: 48px;
+    z-index: 2;
+    display: flex;
+    align-items: center;
+    gap: 6px;
+    padding: 4px 8px;
+    border: 1px solid var(--mdview-border);
+    border-radius: 4px;
+    background-color: var(--mdview-code-bg);
+    color: var(--mdview-text);
+    font-size: 13px;
+}
+.mdview-findbar[hidden] {
+    display: none;
+}
+.mdview-findbar-count {
+    min-width: 5em;
+    color: var(--mdview-text);
+    opacity: 0.7;
+}
+.mdview-notice {
+    position: fixed;
+    bottom: 16px;
+    left: 50%;
+    transform: translateX(-50%);
+    padding: 6px 12px;
+    border: 1px solid var(--mdview-border);
+    border-radius: 4px;
+    background-color: var(--mdview-code-bg);
+    font-size: 13px;
+}
+@media print {
+    .mdview-sidebar, .mdview-sidebar-toggle, .mdview-theme-toggle, .mdview-remote-bar, .mdview-notice, .mdview-findbar, a.anchor { display: none; }
+    body.mdview-sidebar-open { margin-left: auto; }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_styles() {
+        let solarized = Settings { style: Style::Solarized, ..Settings::default() };
+        let css = stylesheet(&solarized);
+        assert!(css.contains("@media (prefers-color-scheme: dark) {\n:root {\n    color-scheme: dark;\n    --mdview-bg: #002b36;"));
+        assert!(css.contains(":root[data-theme=\"light\"] {\n    color-scheme: light;\n    --mdview-bg: #fdf6e3;"));
+        assert!(css.contains(DOCUMENT_CSS) && css.contains(VIEWER_CSS));
+
+        // Print stays light
+        let print = Settings { style: Style::Print, ..solarized };
+        let css = stylesheet(&print);
+        assert!(css.contains("--mdview-bg: #ffffff;") && !css.contains("color-scheme: dark"));
+        assert_eq!(Style::parse("High_Contrast"), Some(Style::HighContrast));
+        assert_eq!(Style::parse("fancy"), None);
+    }
+
+    #[test]
+    fn test_user_stylesheet() {
+        let path = std::env::temp_dir().join("mdview-test-user.css");
+        std::fs::write(&path, "body { color: red; }\n/* </style><script> */\n").unwrap();
+        let settings = Settings { style: Style::None, css: Some(path.clone()), ..Settings::default() };
+        let css = stylesheet(&settings);
+        std::fs::remove_file(&path).unwrap();
+        assert!(!css.contains(DOCUMENT_CSS));
+        assert!(css.ends_with("body { color: red; }\n/* <\\/style><script> */\n"));
+
+        let missing = Settings { css: Some("does-not-exist.css".into()), ..Settings::default() };
+        assert!(stylesheet(&missing).ends_with(VIEWER_CSS));
+    }
+}
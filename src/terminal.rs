@@ -1,6 +1,13 @@
 //! Terminal markdown rendering with ANSI formatting and modern terminal features
 
-use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd, HeadingLevel, CodeBlockKind};
+use pulldown_cmark::{Event, Tag, TagEnd, HeadingLevel, CodeBlockKind};
+
+use crate::frontmatter::{self, FrontMatter};
+use crate::markdown;
+use crate::settings::Settings;
+use crate::toc;
+use crate::{bigtext, mermaid};
+use std::collections::VecDeque;
 use std::env;
 
 /// Terminal capabilities detected at runtime
@@ -10,6 +17,8 @@ pub struct TerminalCaps {
     pub hyperlinks: bool,
     pub unicode: bool,
     pub basic_ansi: bool,
+    /// Kitty text sizing protocol (OSC 66)
+    pub text_sizing: bool,
 }
 
 impl TerminalCaps {
@@ -29,6 +38,7 @@ impl TerminalCaps {
                 hyperlinks: true,
                 unicode: true,
                 basic_ansi: true,
+                text_sizing: false,
             };
         }
 
@@ -39,6 +49,7 @@ impl TerminalCaps {
                 hyperlinks: true,
                 unicode: true,
                 basic_ansi: true,
+                text_sizing: false,
             };
         }
 
@@ -49,6 +60,7 @@ impl TerminalCaps {
                 hyperlinks: true,
                 unicode: true,
                 basic_ansi: true,
+                text_sizing: false,
             };
         }
 
@@ -64,6 +76,9 @@ impl TerminalCaps {
             || term.contains("kitty")
             || term.contains("iterm");
 
+        // Text sizing (OSC 66) - kitty only so far
+        let text_sizing = env::var("KITTY_WINDOW_ID").is_ok() || term == "xterm-kitty";
+
         // Unicode support - assume yes for most modern terminals
         let unicode = !term.is_empty() || cfg!(windows);
 
@@ -76,6 +91,7 @@ impl TerminalCaps {
             hyperlinks,
             unicode,
             basic_ansi,
+            text_sizing,
         }
     }
 
@@ -87,13 +103,14 @@ impl TerminalCaps {
             hyperlinks: false,
             unicode: false,
             basic_ansi: false,
+            text_sizing: false,
         }
     }
 }
 
 /// ANSI escape codes
 #[allow(dead_code)]
-mod ansi {
+pub(crate) mod ansi {
     pub const RESET: &str = "\x1b[0m";
     pub const BOLD: &str = "\x1b[1m";
     pub const DIM: &str = "\x1b[2m";
@@ -154,37 +171,219 @@ mod unicode {
     pub const TABLE_T_LEFT: char = '┤';
 }
 
+/// Which heading levels are drawn large
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BigHeadings {
+    #[default]
+    Off,
+    H1,
+    H2,
+}
+
+impl BigHeadings {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "off" | "none" => Some(Self::Off),
+            "h1" => Some(Self::H1),
+            "h2" => Some(Self::H2),
+            _ => None,
+        }
+    }
+
+    fn includes(self, level: HeadingLevel) -> bool {
+        match self {
+            BigHeadings::Off => false,
+            BigHeadings::H1 => level == HeadingLevel::H1,
+            BigHeadings::H2 => level <= HeadingLevel::H2,
+        }
+    }
+}
+
+/// Rendering options that don't depend on the terminal
+#[derive(Debug, Clone)]
+pub struct TerminalOptions {
+    pub big_headings: BigHeadings,
+    /// Announce structure in words for screen readers instead of drawing it
+    pub accessible: bool,
+    pub settings: Settings,
+    /// Console width in columns
+    pub width: usize,
+    /// Section numbers of the parts shown before, for slides and stream blocks
+    pub numbering: markdown::Numbering,
+}
+
+impl Default for TerminalOptions {
+    fn default() -> Self {
+        Self {
+            big_headings: BigHeadings::Off,
+            accessible: false,
+            settings: Settings::default(),
+            width: 80,
+            numbering: markdown::Numbering::default(),
+        }
+    }
+}
+
 /// Render markdown to terminal with ANSI formatting
 pub fn render_to_terminal(markdown: &str, caps: &TerminalCaps) -> String {
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_TABLES);
-    options.insert(Options::ENABLE_FOOTNOTES);
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_TASKLISTS);
+    render_to_terminal_with(markdown, caps, &TerminalOptions::default())
+}
 
-    let parser = Parser::new_ext(markdown, options);
-    let mut renderer = TerminalRenderer::new(caps.clone());
+/// Render markdown to terminal with explicit rendering options
+pub fn render_to_terminal_with(markdown: &str, caps: &TerminalCaps, options: &TerminalOptions) -> String {
+    let (front_matter, body) = frontmatter::split(markdown);
+    let events = markdown::parse_part(body, &options.settings, &mut options.numbering.clone());
+    let mut caps = caps.clone();
+    if options.accessible {
+        // Screen readers read escape sequences out; the structure is in words
+        caps = TerminalCaps {
+            true_color: false,
+            hyperlinks: false,
+            unicode: caps.unicode,
+            basic_ansi: false,
+            text_sizing: false,
+        };
+    }
+    let mut renderer = TerminalRenderer::new(caps, options.clone());
+    if options.accessible {
+        renderer.list_sizes = list_sizes(&events);
+    }
+    if let Some(front_matter) = front_matter {
+        renderer.front_matter(&front_matter);
+    }
+    let toc_range = toc::position(&events, options.settings.toc);
+    let entries = toc::entries(&events, options.settings.toc_depth.unwrap_or(toc::DEFAULT_DEPTH));
 
-    for event in parser {
+    for (i, event) in events.into_iter().enumerate() {
+        if let Some(range) = &toc_range {
+            if i == range.start {
+                renderer.table_of_contents(&entries);
+            }
+            if range.contains(&i) {
+                continue;
+            }
+        }
         renderer.process_event(event);
     }
 
     renderer.finish()
 }
 
+/// Number of items in each list, in the order the lists start
+fn list_sizes(events: &[Event]) -> VecDeque<usize> {
+    let mut sizes = VecDeque::new();
+    let mut open = Vec::new();
+    for event in events {
+        match event {
+            Event::Start(Tag::List(_)) => {
+                open.push(sizes.len());
+                sizes.push_back(0);
+            }
+            Event::End(TagEnd::List(_)) => {
+                open.pop();
+            }
+            Event::Start(Tag::Item) => {
+                if let Some(&list) = open.last() {
+                    sizes[list] += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    sizes
+}
+
+/// Wrap text that contains escape sequences at `width` visible columns.
+/// Continuation lines start with `prefix`.
+fn wrap_styled(text: &str, width: usize, prefix: &str) -> String {
+    let prefix_width = visible_width(prefix);
+    let mut out = String::new();
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let mut column = 0;
+        for (j, word) in line.split(' ').enumerate() {
+            let word_width = visible_width(word);
+            if j > 0 {
+                if column + 1 + word_width > width && column > prefix_width {
+                    out.push('\n');
+                    out.push_str(prefix);
+                    column = prefix_width;
+                } else {
+                    out.push(' ');
+                    column += 1;
+                }
+            }
+            out.push_str(word);
+            column += word_width;
+        }
+    }
+    out
+}
+
+/// Width in columns, not counting CSI and OSC escape sequences
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            width += 1;
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                // Ends with BEL or ESC \
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    width
+}
+
+fn heading_color(level: HeadingLevel) -> &'static str {
+    match level {
+        HeadingLevel::H1 => ansi::FG_MAGENTA,
+        HeadingLevel::H2 => ansi::FG_BLUE,
+        HeadingLevel::H3 => ansi::FG_CYAN,
+        _ => ansi::FG_GREEN,
+    }
+}
+
 struct TerminalRenderer {
     caps: TerminalCaps,
+    options: TerminalOptions,
     output: String,
 
     // State tracking
     in_heading: Option<HeadingLevel>,
+    big_heading: Option<String>,
     in_emphasis: bool,
     in_strong: bool,
     in_strikethrough: bool,
     in_code_block: bool,
+    mermaid_source: Option<String>,
     in_block_quote: u32,
     in_list: bool,
     list_index: Option<u64>,
+    // Accessible mode: item counts of upcoming lists, next item number of open ones
+    list_sizes: VecDeque<usize>,
+    open_lists: Vec<u64>,
+    image_url: Option<String>,
+    /// Output offset where the current paragraph starts, for wrapping
+    paragraph_start: Option<usize>,
     pending_link: Option<(String, String)>, // (url, title)
     link_text: String,
 
@@ -197,18 +396,31 @@ struct TerminalRenderer {
 }
 
 impl TerminalRenderer {
-    fn new(caps: TerminalCaps) -> Self {
+    fn new(mut caps: TerminalCaps, options: TerminalOptions) -> Self {
+        if options.accessible {
+            // Plain characters only, and link targets as text
+            caps.unicode = false;
+            caps.hyperlinks = false;
+            caps.text_sizing = false;
+        }
         Self {
             caps,
+            options,
             output: String::new(),
             in_heading: None,
+            big_heading: None,
             in_emphasis: false,
             in_strong: false,
             in_strikethrough: false,
             in_code_block: false,
+            mermaid_source: None,
             in_block_quote: 0,
             in_list: false,
             list_index: None,
+            list_sizes: VecDeque::new(),
+            open_lists: Vec::new(),
+            image_url: None,
+            paragraph_start: None,
             pending_link: None,
             link_text: String::new(),
             in_table: false,
@@ -220,11 +432,23 @@ impl TerminalRenderer {
     }
 
     fn process_event(&mut self, event: Event) {
+        // Big headings are collected as plain text and drawn at the end
+        if let Some(heading) = self.big_heading.as_mut() {
+            match event {
+                Event::Text(text) | Event::Code(text) => heading.push_str(&text),
+                Event::SoftBreak | Event::HardBreak => heading.push(' '),
+                Event::End(TagEnd::Heading(level)) => self.big_heading_end(level),
+                _ => {}
+            }
+            return;
+        }
+
         match event {
             Event::Start(tag) => self.start_tag(tag),
             Event::End(tag) => self.end_tag(tag),
             Event::Text(text) => self.text(&text),
-            Event::Code(code) => self.inline_code(&code),
+            Event::Code(code) | Event::InlineMath(code) => self.inline_code(&code),
+            Event::DisplayMath(math) => self.display_math(&math),
             Event::SoftBreak => self.soft_break(),
             Event::HardBreak => self.hard_break(),
             Event::Rule => self.horizontal_rule(),
@@ -236,17 +460,18 @@ impl TerminalRenderer {
     fn start_tag(&mut self, tag: Tag) {
         match tag {
             Tag::Heading { level, .. } => {
-                self.in_heading = Some(level);
                 self.output.push('\n');
+                if self.options.big_headings.includes(level) && !self.options.accessible {
+                    self.big_heading = Some(String::new());
+                    return;
+                }
+                self.in_heading = Some(level);
                 if self.caps.basic_ansi {
                     self.output.push_str(ansi::BOLD);
-                    let color = match level {
-                        HeadingLevel::H1 => ansi::FG_MAGENTA,
-                        HeadingLevel::H2 => ansi::FG_BLUE,
-                        HeadingLevel::H3 => ansi::FG_CYAN,
-                        _ => ansi::FG_GREEN,
-                    };
-                    self.output.push_str(color);
+                    self.output.push_str(heading_color(level));
+                }
+                if self.options.accessible {
+                    self.output.push_str(&format!("Heading level {}: ", level as usize));
                 }
                 // No prefix - just colored/bold text
             }
@@ -254,6 +479,7 @@ impl TerminalRenderer {
                 if !self.output.is_empty() && !self.output.ends_with('\n') {
                     self.output.push('\n');
                 }
+                self.paragraph_start = Some(self.output.len());
                 self.write_blockquote_prefix();
             }
             Tag::Emphasis => {
@@ -273,10 +499,25 @@ impl TerminalRenderer {
                 if self.caps.basic_ansi {
                     self.output.push_str(ansi::STRIKETHROUGH);
                 }
+                if self.options.accessible {
+                    self.output.push_str("deleted: ");
+                }
+            }
+            Tag::CodeBlock(CodeBlockKind::Fenced(lang)) if lang.as_ref() == "mermaid" && !self.options.accessible => {
+                // Collected and drawn as a diagram when the block ends
+                self.mermaid_source = Some(String::new());
             }
             Tag::CodeBlock(kind) => {
                 self.in_code_block = true;
                 self.output.push('\n');
+                if self.options.accessible {
+                    match &kind {
+                        CodeBlockKind::Fenced(lang) if !lang.is_empty() => {
+                            self.output.push_str(&format!("Code block, {}:\n", lang));
+                        }
+                        _ => self.output.push_str("Code block:\n"),
+                    }
+                }
                 if self.caps.basic_ansi {
                     if self.caps.true_color {
                         self.output.push_str(&ansi::bg_rgb(40, 44, 52));
@@ -286,29 +527,34 @@ impl TerminalRenderer {
                     }
                 }
                 // Show language if specified
-                if let CodeBlockKind::Fenced(lang) = kind {
-                    if !lang.is_empty() {
-                        if self.caps.basic_ansi {
-                            self.output.push_str(ansi::DIM);
-                        }
-                        self.output.push_str(&format!("  {}\n", lang));
-                        if self.caps.basic_ansi {
-                            self.output.push_str(ansi::RESET);
-                            if self.caps.true_color {
-                                self.output.push_str(&ansi::bg_rgb(40, 44, 52));
-                                self.output.push_str(&ansi::fg_rgb(171, 178, 191));
-                            } else {
-                                self.output.push_str(ansi::BG_GRAY);
-                            }
+                if let CodeBlockKind::Fenced(lang) = kind
+                    && !lang.is_empty()
+                    && !self.options.accessible
+                {
+                    if self.caps.basic_ansi {
+                        self.output.push_str(ansi::DIM);
+                    }
+                    self.output.push_str(&format!("  {}\n", lang));
+                    if self.caps.basic_ansi {
+                        self.output.push_str(ansi::RESET);
+                        if self.caps.true_color {
+                            self.output.push_str(&ansi::bg_rgb(40, 44, 52));
+                            self.output.push_str(&ansi::fg_rgb(171, 178, 191));
+                        } else {
+                            self.output.push_str(ansi::BG_GRAY);
                         }
                     }
                 }
             }
             Tag::BlockQuote(_) => {
-                self.in_block_quote += 1;
                 if !self.output.ends_with('\n') {
                     self.output.push('\n');
                 }
+                if self.options.accessible {
+                    self.write_blockquote_prefix();
+                    self.output.push_str("Quote:\n");
+                }
+                self.in_block_quote += 1;
             }
             Tag::List(start) => {
                 self.in_list = true;
@@ -316,6 +562,25 @@ impl TerminalRenderer {
                 if !self.output.ends_with('\n') {
                     self.output.push('\n');
                 }
+                if self.options.accessible {
+                    let count = self.list_sizes.pop_front().unwrap_or(0);
+                    let kind = if start.is_some() { "Numbered list" } else { "List" };
+                    let items = if count == 1 { "item" } else { "items" };
+                    self.write_blockquote_prefix();
+                    self.output.push_str(&"  ".repeat(self.open_lists.len()));
+                    self.output.push_str(&format!("{}, {} {}:\n", kind, count, items));
+                    self.open_lists.push(start.unwrap_or(1));
+                }
+            }
+            Tag::Item if self.options.accessible => {
+                self.write_blockquote_prefix();
+                let depth = self.open_lists.len().saturating_sub(1);
+                let number = self.open_lists.last_mut().map_or(1, |n| {
+                    *n += 1;
+                    *n - 1
+                });
+                self.output.push_str(&"  ".repeat(depth));
+                self.output.push_str(&format!("Item {}: ", number));
             }
             Tag::Item => {
                 self.write_blockquote_prefix();
@@ -335,6 +600,10 @@ impl TerminalRenderer {
                 self.pending_link = Some((dest_url.to_string(), title.to_string()));
                 self.link_text.clear();
             }
+            Tag::Image { dest_url, .. } if self.options.accessible => {
+                self.output.push_str("Image: ");
+                self.image_url = Some(dest_url.to_string());
+            }
             Tag::Image { dest_url, title, .. } => {
                 if self.caps.basic_ansi {
                     self.output.push_str(ansi::DIM);
@@ -348,6 +617,20 @@ impl TerminalRenderer {
                     self.output.push_str(ansi::RESET);
                 }
             }
+            Tag::Superscript | Tag::Subscript => {
+                self.output.push(if tag == Tag::Superscript { '^' } else { '_' });
+            }
+            Tag::DefinitionList if !self.output.ends_with('\n') => self.output.push('\n'),
+            Tag::DefinitionListTitle => {
+                self.write_blockquote_prefix();
+                if self.caps.basic_ansi {
+                    self.output.push_str(ansi::BOLD);
+                }
+            }
+            Tag::DefinitionListDefinition => {
+                self.write_blockquote_prefix();
+                self.output.push_str("    ");
+            }
             Tag::Table(_) => {
                 self.in_table = true;
                 self.table_rows.clear();
@@ -379,6 +662,11 @@ impl TerminalRenderer {
                 self.in_heading = None;
             }
             TagEnd::Paragraph => {
+                if let (Some(start), Some(width)) = (self.paragraph_start.take(), self.wrap_width()) {
+                    let text = self.output.split_off(start);
+                    let prefix = self.blockquote_prefix();
+                    self.output.push_str(&wrap_styled(&text, width, &prefix));
+                }
                 self.output.push_str("\n\n");
             }
             TagEnd::Emphasis => {
@@ -402,20 +690,54 @@ impl TerminalRenderer {
                     self.output.push_str(ansi::RESET);
                     self.restore_styles();
                 }
+                if self.options.accessible {
+                    self.output.push_str(", end deleted");
+                }
+            }
+            TagEnd::CodeBlock if self.mermaid_source.is_some() => {
+                let source = self.mermaid_source.take().unwrap_or_default();
+                self.mermaid_diagram(&source);
             }
             TagEnd::CodeBlock => {
                 self.in_code_block = false;
                 if self.caps.basic_ansi {
                     self.output.push_str(ansi::RESET);
                 }
+                if self.options.accessible {
+                    self.output.push_str("End of code block.\n");
+                }
                 self.output.push('\n');
             }
             TagEnd::BlockQuote(_) => {
                 self.in_block_quote = self.in_block_quote.saturating_sub(1);
+                if self.options.accessible {
+                    if !self.output.ends_with('\n') {
+                        self.output.push('\n');
+                    }
+                    self.write_blockquote_prefix();
+                    self.output.push_str("End of quote.\n\n");
+                }
             }
             TagEnd::List(_) => {
                 self.in_list = false;
                 self.list_index = None;
+                if self.options.accessible {
+                    self.open_lists.pop();
+                    if !self.output.ends_with('\n') {
+                        self.output.push('\n');
+                    }
+                    self.write_blockquote_prefix();
+                    self.output.push_str(&"  ".repeat(self.open_lists.len()));
+                    self.output.push_str("End of list.\n");
+                    if self.open_lists.is_empty() {
+                        self.output.push('\n');
+                    }
+                }
+            }
+            TagEnd::Image => {
+                if let Some(url) = self.image_url.take() {
+                    self.output.push_str(&format!(" (image file: {})", url));
+                }
             }
             TagEnd::Item => {
                 if !self.output.ends_with('\n') {
@@ -449,7 +771,11 @@ impl TerminalRenderer {
                         if self.caps.basic_ansi {
                             self.output.push_str(ansi::DIM);
                         }
-                        self.output.push_str(&format!(" ({})", url));
+                        if self.options.accessible {
+                            self.output.push_str(&format!(" (link: {})", url));
+                        } else {
+                            self.output.push_str(&format!(" ({})", url));
+                        }
                         if self.caps.basic_ansi {
                             self.output.push_str(ansi::RESET);
                         }
@@ -457,8 +783,20 @@ impl TerminalRenderer {
                 }
                 self.link_text.clear();
             }
+            TagEnd::DefinitionListTitle => {
+                if self.caps.basic_ansi {
+                    self.output.push_str(ansi::RESET);
+                }
+                self.output.push('\n');
+            }
+            TagEnd::DefinitionListDefinition if !self.output.ends_with('\n') => self.output.push('\n'),
+            TagEnd::DefinitionList => self.output.push('\n'),
             TagEnd::Table => {
-                self.render_table();
+                if self.options.accessible {
+                    self.describe_table();
+                } else {
+                    self.render_table();
+                }
                 self.in_table = false;
             }
             TagEnd::TableHead => {
@@ -478,6 +816,11 @@ impl TerminalRenderer {
     }
 
     fn text(&mut self, text: &str) {
+        if let Some(source) = self.mermaid_source.as_mut() {
+            source.push_str(text);
+            return;
+        }
+
         if self.pending_link.is_some() {
             self.link_text.push_str(text);
             return;
@@ -500,6 +843,65 @@ impl TerminalRenderer {
         }
     }
 
+    /// Draw a collected big heading: scaled text where the terminal supports it,
+    /// else the block font, else normal styling when it doesn't fit
+    fn big_heading_end(&mut self, level: HeadingLevel) {
+        let text = self.big_heading.take().unwrap_or_default();
+        let text = text.trim();
+        let text_width = text.chars().count();
+        let style = if self.caps.basic_ansi {
+            format!("{}{}", ansi::BOLD, heading_color(level))
+        } else {
+            String::new()
+        };
+        let reset = if self.caps.basic_ansi { ansi::RESET } else { "" };
+
+        if self.caps.text_sizing && text_width * 2 <= self.options.width {
+            // Double size text takes two rows; the cursor stays on the first
+            self.output.push_str(&format!("{}\x1b]66;s=2;{}\x07{}\n\n\n", style, text, reset));
+            return;
+        }
+
+        let fits = bigtext::width(text).is_some_and(|w| w <= self.options.width);
+        if let Some(rows) = bigtext::render(text, self.caps.unicode).filter(|_| fits) {
+            for row in rows {
+                self.output.push_str(&format!("{}{}{}\n", style, row, reset));
+            }
+            self.output.push('\n');
+            return;
+        }
+
+        self.output.push_str(&format!("{}{}{}\n\n", style, text, reset));
+    }
+
+    /// Draw a mermaid block, or show it as code with a note when it can't be drawn
+    fn mermaid_diagram(&mut self, source: &str) {
+        self.output.push('\n');
+        match mermaid::render(source, self.caps.unicode) {
+            Ok(diagram) => {
+                for line in diagram.lines() {
+                    self.output.push_str("  ");
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                }
+            }
+            Err(reason) => {
+                if self.caps.basic_ansi {
+                    self.output.push_str(ansi::DIM);
+                }
+                self.output.push_str(&format!("  mermaid: {} (showing source)", reason));
+                if self.caps.basic_ansi {
+                    self.output.push_str(ansi::RESET);
+                }
+                self.start_tag(Tag::CodeBlock(CodeBlockKind::Indented));
+                self.text(source);
+                self.end_tag(TagEnd::CodeBlock);
+                return;
+            }
+        }
+        self.output.push('\n');
+    }
+
     fn inline_code(&mut self, code: &str) {
         if self.in_table {
             self.current_cell.push('`');
@@ -525,6 +927,17 @@ impl TerminalRenderer {
         }
     }
 
+    fn display_math(&mut self, math: &str) {
+        if !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+        for line in math.trim().lines() {
+            self.output.push_str("    ");
+            self.inline_code(line.trim());
+            self.output.push('\n');
+        }
+    }
+
     fn soft_break(&mut self) {
         if self.pending_link.is_some() {
             self.link_text.push(' ');
@@ -542,6 +955,10 @@ impl TerminalRenderer {
 
     fn horizontal_rule(&mut self) {
         self.output.push('\n');
+        if self.options.accessible {
+            self.output.push_str("Separator.\n\n");
+            return;
+        }
         if self.caps.basic_ansi {
             self.output.push_str(ansi::DIM);
         }
@@ -558,6 +975,10 @@ impl TerminalRenderer {
     }
 
     fn task_list_marker(&mut self, checked: bool) {
+        if self.options.accessible {
+            self.output.push_str(if checked { "checked: " } else { "unchecked: " });
+            return;
+        }
         let marker = if self.caps.unicode {
             if checked { unicode::CHECKBOX_CHECKED } else { unicode::CHECKBOX_UNCHECKED }
         } else {
@@ -578,19 +999,38 @@ impl TerminalRenderer {
     }
 
     fn write_blockquote_prefix(&mut self) {
+        let prefix = self.blockquote_prefix();
+        self.output.push_str(&prefix);
+    }
+
+    fn blockquote_prefix(&self) -> String {
+        let mut prefix = String::new();
+        if self.options.accessible {
+            // Quotes are announced in words, nesting only shows as indentation
+            prefix.push_str(&"  ".repeat(self.in_block_quote as usize));
+            return prefix;
+        }
         if self.in_block_quote > 0 {
             if self.caps.basic_ansi {
-                self.output.push_str(ansi::FG_GRAY);
+                prefix.push_str(ansi::FG_GRAY);
             }
             for _ in 0..self.in_block_quote {
                 let bar = if self.caps.unicode { unicode::QUOTE_BAR } else { "|" };
-                self.output.push_str(bar);
-                self.output.push(' ');
+                prefix.push_str(bar);
+                prefix.push(' ');
             }
             if self.caps.basic_ansi {
-                self.output.push_str(ansi::RESET);
+                prefix.push_str(ansi::RESET);
             }
         }
+        prefix
+    }
+
+    /// Paragraphs are wrapped when a text width is set, never wider than the console.
+    /// Screen readers get unbroken lines.
+    fn wrap_width(&self) -> Option<usize> {
+        let width = self.options.settings.width?;
+        (!self.options.accessible).then(|| width.min(self.options.width))
     }
 
     fn restore_styles(&mut self) {
@@ -696,6 +1136,93 @@ impl TerminalRenderer {
         self.output.push_str("\n\n");
     }
 
+    /// Describe a table row by row for screen readers, naming each cell by its header
+    fn describe_table(&mut self) {
+        let Some((header, body)) = self.table_rows.split_first() else {
+            return;
+        };
+        let columns = self.table_rows.iter().map(|r| r.len()).max().unwrap_or(0);
+        let mut text = format!("Table, {} columns, {} rows.\n", columns, self.table_rows.len());
+        text.push_str(&format!("Row 1, headers: {}.\n", header.join(", ")));
+        for (index, row) in body.iter().enumerate() {
+            let cells: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| match header.get(i).filter(|h| !h.trim().is_empty()) {
+                    Some(name) => format!("{} = {}", name.trim(), cell.trim()),
+                    None => format!("column {} = {}", i + 1, cell.trim()),
+                })
+                .collect();
+            text.push_str(&format!("Row {}: {}.\n", index + 2, cells.join(", ")));
+        }
+        text.push_str("End of table.\n\n");
+        self.output.push_str(&text);
+    }
+
+    /// `code`, if the terminal understands ANSI codes
+    fn style(&self, code: &'static str) -> &'static str {
+        if self.caps.basic_ansi { code } else { "" }
+    }
+
+    /// Metadata block above the document: aligned key/value lines in gray
+    fn front_matter(&mut self, front_matter: &FrontMatter) {
+        let fields = front_matter.flatten();
+        if fields.is_empty() {
+            return;
+        }
+        if self.options.accessible {
+            self.output.push_str("Metadata:\n");
+            for (key, value) in &fields {
+                self.output.push_str(&format!("{}: {}\n", key, value));
+            }
+            self.output.push_str("End of metadata.\n\n");
+            return;
+        }
+
+        let key_width = fields.iter().map(|(k, _)| k.chars().count()).max().unwrap_or(0);
+        let (dim, reset) = (self.style(ansi::DIM), self.style(ansi::RESET));
+        self.output.push_str(&format!("{}{}Metadata{}\n", dim, self.style(ansi::BOLD), reset));
+        for (key, value) in &fields {
+            // Multi-line values are shown on one line
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            self.output.push_str(&format!(
+                "  {}{:width$}{}  {}{}{}\n",
+                self.style(ansi::FG_GRAY),
+                key,
+                reset,
+                dim,
+                value,
+                reset,
+                width = key_width
+            ));
+        }
+        self.output.push('\n');
+    }
+
+    fn table_of_contents(&mut self, entries: &[toc::Entry]) {
+        if entries.is_empty() {
+            return;
+        }
+        if self.options.accessible {
+            let count = if entries.len() == 1 { "1 entry".to_string() } else { format!("{} entries", entries.len()) };
+            self.output.push_str(&format!("Table of contents, {}:\n", count));
+            for (depth, text) in toc::outline(entries) {
+                self.output.push_str(&format!("{}{}\n", "  ".repeat(depth), text));
+            }
+            self.output.push_str("End of table of contents.\n\n");
+            return;
+        }
+
+        let bullet = if self.caps.unicode { unicode::BULLET } else { '*' };
+        let reset = self.style(ansi::RESET);
+        self.output.push_str(&format!("{}{}Contents{}\n", self.style(ansi::DIM), self.style(ansi::BOLD), reset));
+        for (depth, text) in toc::outline(entries) {
+            let gray = self.style(ansi::FG_GRAY);
+            self.output.push_str(&format!("{}{}{}{} {}\n", "  ".repeat(depth + 1), gray, bullet, reset, text));
+        }
+        self.output.push('\n');
+    }
+
     fn finish(mut self) -> String {
         // Trim trailing whitespace but keep one newline
         while self.output.ends_with("\n\n") {
@@ -719,6 +1246,82 @@ mod tests {
         // Headings render without # prefix, just bold text
         assert!(output.contains("Hello"));
         assert!(output.contains("World"));
+
+        // A text width wraps paragraphs, continuing quotes on every line
+        let options = TerminalOptions {
+            settings: Settings { width: Some(20), ..Settings::default() },
+            ..TerminalOptions::default()
+        };
+        let output = render_to_terminal_with("> one two **three** four five six seven", &caps, &options);
+        let lines: Vec<&str> = output.lines().filter(|l| !l.is_empty()).collect();
+        assert_eq!(lines, vec!["| one two three four", "| five six seven"]);
+    }
+
+    #[test]
+    fn test_mermaid_blocks() {
+        let caps = TerminalCaps::basic();
+        let output = render_to_terminal("```mermaid\ngraph LR\n  A --> B\n```", &caps);
+        assert!(output.contains("| A +-->| B |"));
+        assert!(!output.contains("graph LR"));
+
+        let output = render_to_terminal("```mermaid\npie\n  \"a\" : 1\n```", &caps);
+        assert!(output.contains("mermaid: 'pie' diagrams are not supported"));
+        assert!(output.contains("  pie"));
+    }
+
+    #[test]
+    fn test_big_headings() {
+        let mut caps = TerminalCaps::basic();
+        let options = TerminalOptions { big_headings: BigHeadings::H1, width: 40, ..TerminalOptions::default() };
+        let output = render_to_terminal_with("# Hi\n\n## Sub", &caps, &options);
+        assert!(output.contains("#  # ###"));
+        assert!(output.contains("Sub"));
+
+        // Too wide for the terminal: plain heading text
+        let output = render_to_terminal_with("# A rather long title here", &caps, &options);
+        assert!(output.contains("A rather long title here"));
+
+        caps.text_sizing = true;
+        let output = render_to_terminal_with("# Hi", &caps, &options);
+        assert!(output.contains("\x1b]66;s=2;Hi\x07"));
+    }
+
+    #[test]
+    fn test_accessible_output() {
+        let caps = TerminalCaps::basic();
+        let settings = Settings { toc: true, ..Settings::default() };
+        let options = TerminalOptions { accessible: true, settings, ..TerminalOptions::default() };
+        let md = "---\ntitle: Guide\n---\n## Setup\n\n- [x] one\n- [ ] two\n- see [docs](https://example.com)\n\n\
+                  | Name | Size |\n|---|---|\n| foo | 3 |\n| bar | 4 |\n";
+        let output = render_to_terminal_with(md, &caps, &options);
+        assert!(output.starts_with("Metadata:\ntitle: Guide\nEnd of metadata.\n\nTable of contents, 1 entry:\nSetup\nEnd of table of contents."));
+        assert!(output.contains("Heading level 2: Setup"));
+        assert!(output.contains("List, 3 items:"));
+        assert!(output.contains("Item 1: checked: one"));
+        assert!(output.contains("Item 2: unchecked: two"));
+        assert!(output.contains("docs (link: https://example.com)"));
+        assert!(output.contains("Table, 2 columns, 3 rows."));
+        assert!(output.contains("Row 2: Name = foo, Size = 3."));
+        assert!(output.contains("End of list."));
+        assert!(output.is_ascii());
+
+        // No colors, whatever the terminal can do
+        let mut caps = TerminalCaps::basic();
+        caps.basic_ansi = true;
+        caps.true_color = true;
+        let output = render_to_terminal_with("# Setup\n\n```rust\nlet x = 1;\n```\n", &caps, &options);
+        assert!(output.contains("Heading level 1: Setup") && !output.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_plain_output() {
+        let caps = TerminalCaps::basic();
+        let settings = Settings { toc: true, ..Settings::default() };
+        let options = TerminalOptions { settings, ..TerminalOptions::default() };
+        let output = render_to_terminal_with("---\ntitle: Guide\ntags: [a, b]\n---\n# Setup\n", &caps, &options);
+        assert!(output.starts_with("Metadata\n  title  Guide\n"));
+        assert!(output.contains("Contents\n  * Setup\n"));
+        assert!(!output.contains('\x1b'));
     }
 
     #[test]
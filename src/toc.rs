@@ -0,0 +1,271 @@
+//! Table of contents from the heading structure
+
+use pulldown_cmark::{Event, LinkType, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+use crate::frontmatter;
+use crate::markdown::{self, escape_html};
+use crate::settings::Settings;
+
+/// Deepest heading level listed when no depth is configured
+pub const DEFAULT_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub level: usize,
+    pub id: String,
+    pub text: String,
+}
+
+/// Headings of parsed events, down to level `depth`
+pub fn entries(events: &[Event], depth: usize) -> Vec<Entry> {
+    events
+        .iter()
+        .enumerate()
+        .filter_map(|(i, event)| match event {
+            Event::Start(Tag::Heading { level, id, .. }) if *level as usize <= depth => Some(Entry {
+                level: *level as usize,
+                id: id.as_deref().unwrap_or_default().to_string(),
+                text: markdown::heading_text(&events[i + 1..]).trim().to_string(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Entries for a whole document, with its settings
+pub fn for_document(markdown: &str, settings: &Settings) -> Vec<Entry> {
+    let events = markdown::parse(frontmatter::split(markdown).1, settings);
+    entries(&events, settings.toc_depth.unwrap_or(DEFAULT_DEPTH))
+}
+
+/// Where the table goes: a `[[_TOC_]]` paragraph or `<!-- toc -->` comment is
+/// replaced by it. Otherwise it goes at the top if `enabled`.
+pub fn position(events: &[Event], enabled: bool) -> Option<Range<usize>> {
+    // `<!-- toc -->` ... `<!-- tocstop -->` is a list maintained in the file (--update-toc)
+    let maintained = events.iter().any(|e| matches!(e, Event::Html(html) if is_marker(html, "tocstop")));
+
+    for (start, event) in events.iter().enumerate() {
+        let end_tag = match event {
+            Event::Start(Tag::Paragraph) => TagEnd::Paragraph,
+            Event::Start(Tag::HtmlBlock) if !maintained => TagEnd::HtmlBlock,
+            _ => continue,
+        };
+        let Some(len) = events[start..].iter().position(|e| *e == Event::End(end_tag)) else {
+            continue;
+        };
+        let inner = &events[start + 1..start + len];
+        let is_placeholder = match end_tag {
+            TagEnd::HtmlBlock => {
+                let html: String = inner.iter().filter_map(|e| if let Event::Html(h) = e { Some(h.as_ref()) } else { None }).collect();
+                is_marker(&html, "toc")
+            }
+            _ => is_toc_paragraph(inner),
+        };
+        if is_placeholder {
+            return Some(start..start + len + 1);
+        }
+    }
+    enabled.then_some(0..0)
+}
+
+/// `<!-- name -->`, ignoring case and spacing
+pub fn is_marker(html: &str, name: &str) -> bool {
+    html.trim()
+        .strip_prefix("<!--")
+        .and_then(|h| h.strip_suffix("-->"))
+        .is_some_and(|inner| inner.trim().eq_ignore_ascii_case(name))
+}
+
+/// `[[_TOC_]]` as a paragraph of its own. Depending on the extensions it is parsed
+/// as text, text with emphasis, or a wikilink.
+fn is_toc_paragraph(inner: &[Event]) -> bool {
+    if let [Event::Start(Tag::Link { link_type: LinkType::WikiLink { .. }, dest_url, .. }), .., Event::End(TagEnd::Link)] = inner {
+        return dest_url.as_ref() == "_TOC_";
+    }
+    let text: String = inner
+        .iter()
+        .map(|e| match e {
+            Event::Text(text) => text.as_ref(),
+            Event::Start(Tag::Emphasis) | Event::End(TagEnd::Emphasis) => "_",
+            _ => "\u{0}",
+        })
+        .collect();
+    text.trim() == "[[_TOC_]]"
+}
+
+/// Nested `<ul>` lists of links in a `<nav>`
+pub fn to_html(entries: &[Entry]) -> String {
+    let mut out = String::from("<nav class=\"toc\">\n");
+    let base = entries.iter().map(|e| e.level).min().unwrap_or(1);
+    let mut open = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        let target = entry.level + 1 - base;
+        if i > 0 && target <= open {
+            out.push_str("</li>\n");
+        }
+        while open > target {
+            out.push_str("</ul>\n</li>\n");
+            open -= 1;
+        }
+        // Skipped levels get an empty item to nest in
+        let mut nested = false;
+        while open < target {
+            if nested {
+                out.push_str("<li>\n");
+            }
+            out.push_str("<ul>\n");
+            open += 1;
+            nested = true;
+        }
+        out.push_str(&format!("<li><a href=\"#{}\">{}</a>", escape_html(&entry.id), escape_html(&entry.text)));
+    }
+    if !entries.is_empty() {
+        out.push_str("</li>\n");
+    }
+    while open > 0 {
+        out.push_str("</ul>\n");
+        open -= 1;
+        if open > 0 {
+            out.push_str("</li>\n");
+        }
+    }
+    out.push_str("</nav>\n");
+    out
+}
+
+/// Markdown link list, as kept between `<!-- toc -->` and `<!-- tocstop -->`
+#[allow(dead_code)]
+pub fn to_markdown(entries: &[Entry]) -> String {
+    outline(entries)
+        .into_iter()
+        .zip(entries)
+        .map(|((depth, text), entry)| {
+            format!("{}- [{}](#{})\n", "  ".repeat(depth), escape_link_text(text), escape_destination(&entry.id))
+        })
+        .collect()
+}
+
+fn escape_link_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn escape_destination(id: &str) -> String {
+    id.replace('\\', "\\\\").replace('(', "\\(").replace(')', "\\)")
+}
+
+/// Regenerate the link lists between `<!-- toc -->` and `<!-- tocstop -->`
+/// markers, leaving everything else as it is. `None` if there are no markers.
+#[allow(dead_code)]
+pub fn update_markers(markdown: &str, settings: &Settings) -> Option<String> {
+    let body = frontmatter::split(markdown).1;
+    let body = body.strip_prefix('\u{feff}').unwrap_or(body);
+    let offset = markdown.len() - body.len();
+    let entries = entries(&markdown::parse(body, settings), settings.toc_depth.unwrap_or(DEFAULT_DEPTH));
+    let newline = if markdown.contains("\r\n") { "\r\n" } else { "\n" };
+    let list = to_markdown(&entries).replace('\n', newline);
+
+    // Markers are HTML blocks of their own; ones in code blocks don't count
+    let mut blocks = Parser::new_ext(body, settings.extensions.options())
+        .into_offset_iter()
+        .filter_map(|(event, range)| matches!(event, Event::Start(Tag::HtmlBlock)).then_some(range));
+    let mut out = String::new();
+    let mut copied = 0;
+    while let Some(start) = blocks.find(|r| is_marker(&body[r.clone()], "toc")) {
+        let Some(stop) = blocks.find(|r| is_marker(&body[r.clone()], "tocstop")) else {
+            break;
+        };
+        out.push_str(&markdown[copied..offset + start.end]);
+        if !list.is_empty() {
+            out.push_str(newline);
+            out.push_str(&list);
+        }
+        out.push_str(newline);
+        copied = offset + stop.start;
+    }
+    if copied == 0 {
+        return None;
+    }
+    out.push_str(&markdown[copied..]);
+    Some(out)
+}
+
+/// Entries with their nesting depth, starting at 0 for the highest level present
+pub fn outline(entries: &[Entry]) -> Vec<(usize, &str)> {
+    let base = entries.iter().map(|e| e.level).min().unwrap_or(1);
+    entries.iter().map(|e| (e.level - base, e.text.as_str())).collect()
+}
+
+/// Collapsible sidebar for the viewer. The viewer script opens and closes it
+/// and highlights the section being read.
+pub fn sidebar(entries: &[Entry], open: bool) -> String {
+    if entries.len() < 2 {
+        return String::new();
+    }
+    format!(
+        "<button class=\"mdview-sidebar-toggle\" title=\"Contents\" aria-label=\"Contents\">&#9776;</button>\n\
+         <aside class=\"mdview-sidebar\" data-open=\"{}\">\n{}</aside>\n",
+        open,
+        to_html(entries)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::Extensions;
+
+    fn entry(level: usize, text: &str) -> Entry {
+        Entry { level, id: text.to_lowercase(), text: text.to_string() }
+    }
+
+    #[test]
+    fn test_nested_html() {
+        let html = to_html(&[entry(2, "A"), entry(4, "B"), entry(2, "C")]);
+        assert_eq!(
+            html,
+            "<nav class=\"toc\">\n<ul>\n<li><a href=\"#a\">A</a><ul>\n<li>\n<ul>\n<li><a href=\"#b\">B</a></li>\n</ul>\n</li>\n</ul>\n</li>\n<li><a href=\"#c\">C</a></li>\n</ul>\n</nav>\n"
+        );
+        assert_eq!(outline(&[entry(2, "A"), entry(3, "B")]), vec![(0, "A"), (1, "B")]);
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let settings = Settings::default();
+        let doc = "# T\n\nIntro\n\n[[_TOC_]]\n\n## A\n";
+        let events = markdown::parse(doc, &settings);
+        let range = position(&events, false).unwrap();
+        assert_eq!(events[range.start], Event::Start(Tag::Paragraph));
+        assert!(range.start > 3);
+
+        let wiki = Settings { extensions: Extensions { wikilinks: true, ..Extensions::default() }, ..Settings::default() };
+        assert!(position(&markdown::parse(doc, &wiki), false).is_some());
+        assert!(position(&markdown::parse("<!-- TOC -->\n\n# A\n", &settings), false).is_some());
+
+        // A maintained list between markers is left alone
+        let maintained = "<!-- toc -->\n- [A](#a)\n<!-- tocstop -->\n\n# A\n";
+        assert_eq!(position(&markdown::parse(maintained, &settings), true), Some(0..0));
+        assert_eq!(position(&markdown::parse("# A\n", &settings), false), None);
+    }
+
+    #[test]
+    fn test_update_markers() {
+        let settings = Settings::default();
+        let doc = "\u{feff}# Intro\r\n\r\n<!-- toc -->\r\n- [Old](#old)\r\n<!-- tocstop -->\r\n\r\n## A [b] *c*\r\n\r\n```\r\n<!-- toc -->\r\n```\r\n\r\n## Intro\r\n";
+        let updated = update_markers(doc, &settings).unwrap();
+        assert_eq!(
+            updated,
+            "\u{feff}# Intro\r\n\r\n<!-- toc -->\r\n\r\n- [Intro](#intro)\r\n  - [A \\[b\\] c](#a-b-c)\r\n  - [Intro](#intro-1)\r\n\r\n<!-- tocstop -->\r\n\r\n## A [b] *c*\r\n\r\n```\r\n<!-- toc -->\r\n```\r\n\r\n## Intro\r\n"
+        );
+        // Up to date: nothing changes
+        assert_eq!(update_markers(&updated, &settings).as_deref(), Some(updated.as_str()));
+        assert_eq!(update_markers("# A\n\n<!-- toc -->\n", &settings), None);
+    }
+}
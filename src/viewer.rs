@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use std::env;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
@@ -22,6 +23,12 @@ fn log_debug(msg: &str) {
         }
     }
 }
+use crate::host::{self, WM_FILE_CHANGED};
+use crate::link_policy::LinkPolicy;
+use crate::navigation::{self, History, Location, Navigation, Position};
+use crate::page::Rendering;
+use crate::protocol::{HostMessage, PageMessage};
+use crate::watch::Watcher;
 use webview2_com::Microsoft::Web::WebView2::Win32::*;
 use webview2_com::{
     pwstr_from_str, CreateCoreWebView2ControllerCompletedHandler,
@@ -33,7 +40,6 @@ use windows::Win32::Foundation::*;
 use windows::Win32::Graphics::Gdi::*;
 use windows::Win32::System::Com::{CoInitializeEx, COINIT_APARTMENTTHREADED};
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
-use windows::Win32::UI::Shell::ShellExecuteW;
 use windows::Win32::UI::WindowsAndMessaging::*;
 
 const WINDOW_CLASS: &str = "MDViewWebView2Host";
@@ -41,9 +47,39 @@ const WINDOW_CLASS: &str = "MDViewWebView2Host";
 // Thread-local storage for WebView2 controllers (COM objects are single-threaded)
 thread_local! {
     static CONTROLLERS: RefCell<HashMap<isize, Rc<ICoreWebView2Controller>>> = RefCell::new(HashMap::new());
+    /// What each window shows, for reloads
+    static SESSIONS: RefCell<HashMap<isize, Rc<RefCell<Session>>>> = RefCell::new(HashMap::new());
 }
 
-pub fn create_viewer(parent: HWND, html: &str) -> windows::core::Result<HWND> {
+/// Timer that retries a reload the window was too busy for
+const RELOAD_TIMER: usize = 1;
+
+/// A rendered document
+pub struct Page {
+    /// The markdown file
+    pub path: PathBuf,
+    pub html: String,
+    /// Folder the page loads local images from, as `resources::DOCUMENT_HOST`
+    pub folder: PathBuf,
+    /// What `html` shows, to reload it when it changes on disk
+    pub rendering: Rendering,
+}
+
+/// Renders the markdown file a link leads to, with its remote content when
+/// the second argument is true
+pub type Render = Box<dyn Fn(&Path, bool) -> Option<Page>>;
+
+/// A child window of `parent` showing `page`. Links to other markdown files
+/// show them in the same window through `render`; `links` decides what a
+/// click on a link may open. With `live_reload` the page is rendered again
+/// when its files change.
+pub fn create_viewer(
+    parent: HWND,
+    page: Page,
+    links: LinkPolicy,
+    render: Render,
+    live_reload: bool,
+) -> windows::core::Result<HWND> {
     unsafe {
         // Initialize COM if not already initialized (safe to call multiple times)
         let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
@@ -73,8 +109,20 @@ pub fn create_viewer(parent: HWND, html: &str) -> windows::core::Result<HWND> {
             None,
         )?;
 
+        let session = Session {
+            window: hwnd,
+            page,
+            links,
+            render,
+            history: History::default(),
+            scroll: 0.0,
+            pending: None,
+            remote: false,
+            live_reload,
+            watcher: None,
+        };
         // Initialize WebView2 synchronously
-        if let Err(e) = init_webview2_sync(hwnd, html) {
+        if let Err(e) = init_webview2_sync(hwnd, session) {
             let _ = DestroyWindow(hwnd);
             return Err(e);
         }
@@ -83,6 +131,122 @@ pub fn create_viewer(parent: HWND, html: &str) -> windows::core::Result<HWND> {
     }
 }
 
+/// Navigate to `page`, serving its folder
+fn show_page(webview: &ICoreWebView2, page: &Page) {
+    host::map_folder(webview, &page.folder);
+    let html_wide = pwstr_from_str(&page.html);
+    let _ = unsafe { webview.NavigateToString(PCWSTR(html_wide.as_ptr())) };
+}
+
+/// What a viewer window shows, and where the reader has been
+struct Session {
+    window: HWND,
+    page: Page,
+    links: LinkPolicy,
+    render: Render,
+    history: History,
+    /// Scroll position the page last reported
+    scroll: f64,
+    /// Where to put the page once it is ready
+    pending: Option<Position>,
+    /// The reader loaded the remote content of the page
+    remote: bool,
+    live_reload: bool,
+    watcher: Option<Watcher>,
+}
+
+impl Session {
+    fn location(&self) -> Location {
+        Location { path: self.page.path.clone(), scroll: self.scroll }
+    }
+
+    /// Show `page` at `position`
+    fn show(&mut self, webview: &ICoreWebView2, page: Page, position: Option<Position>) {
+        show_page(webview, &page);
+        self.page = page;
+        self.scroll = match position {
+            Some(Position::Scroll(y)) => y,
+            _ => 0.0,
+        };
+        self.pending = position;
+        self.watch();
+    }
+
+    /// Render and show another file at `position`. Returns whether it could be read.
+    fn show_file(&mut self, webview: &ICoreWebView2, path: &Path, position: Option<Position>) -> bool {
+        let Some(page) = (self.render)(path, false) else {
+            log_debug(&format!("Cannot show {}", path.display()));
+            return false;
+        };
+        self.remote = false;
+        self.show(webview, page, position);
+        true
+    }
+
+    /// Show the remote content the reader asked for, where they are
+    fn load_remote(&mut self, webview: &ICoreWebView2) {
+        if let Some(page) = (self.render)(&self.page.path, true) {
+            self.remote = true;
+            self.show(webview, page, Some(Position::Scroll(self.scroll)));
+        }
+    }
+
+    /// Show the document again after it changed on disk. When the page around
+    /// it stays the same, only the changed blocks are replaced.
+    fn reload(&mut self, webview: &ICoreWebView2) {
+        // Still being written: the next change reloads
+        let Some(page) = (self.render)(&self.page.path, self.remote) else {
+            return;
+        };
+        match page.rendering.patch(&self.page.rendering) {
+            Some(patch) => {
+                host::post_to(webview, &patch);
+                self.page = page;
+                self.watch();
+            }
+            None => self.show(webview, page, Some(Position::Scroll(self.scroll))),
+        }
+    }
+
+    /// Watch the files of the page, when live reload is on
+    fn watch(&mut self) {
+        self.watcher = self
+            .live_reload
+            .then(|| host::watch_files(self.window, self.page.rendering.files.clone()));
+    }
+
+    /// Follow a link clicked in the page, showing markdown files in this window
+    fn follow_link(&mut self, webview: &ICoreWebView2, url: &str, external: bool) {
+        let folder = self.page.folder.clone();
+        let hwnd = self.window;
+        match navigation::follow(url, &folder, &self.links, external, |link| host::confirm_link(Some(hwnd), link)) {
+            Navigation::Document { path, fragment } => {
+                let current = self.location();
+                if self.show_file(webview, &path, fragment.map(Position::Section)) {
+                    self.history.visit(current);
+                }
+            }
+            Navigation::Open(target) => host::open_url_in_browser(&target),
+            Navigation::Stay => {}
+        }
+    }
+
+    /// Go one place back or forward in the history
+    fn go_through_history(&mut self, webview: &ICoreWebView2, forward: bool) {
+        let current = self.location();
+        let target = if forward { self.history.forward(current) } else { self.history.back(current) };
+        let Some(target) = target else {
+            return;
+        };
+        if target.path == self.page.path {
+            self.scroll = target.scroll;
+            host::post_to(webview, &HostMessage::ScrollTo { y: target.scroll });
+        } else {
+            self.show_file(webview, &target.path, Some(Position::Scroll(target.scroll)));
+        }
+    }
+}
+
 fn register_window_class() -> windows::core::Result<()> {
     unsafe {
         let class_name = U16CString::from_str(WINDOW_CLASS).unwrap();
@@ -105,10 +269,9 @@ fn register_window_class() -> windows::core::Result<()> {
     }
 }
 
-fn init_webview2_sync(hwnd: HWND, html: &str) -> windows::core::Result<()> {
+fn init_webview2_sync(hwnd: HWND, mut session: Session) -> windows::core::Result<()> {
     log_debug(&format!("init_webview2_sync started, hwnd={:?}", hwnd.0));
 
-    let html_owned = html.to_string();
     let controller_result: Rc<RefCell<Option<ICoreWebView2Controller>>> = Rc::new(RefCell::new(None));
     let error_result: Rc<RefCell<Option<windows::core::Error>>> = Rc::new(RefCell::new(None));
     let completed = Rc::new(RefCell::new(false));
@@ -136,7 +299,6 @@ fn init_webview2_sync(hwnd: HWND, html: &str) -> windows::core::Result<()> {
             let controller_inner = controller_clone.clone();
             let error_inner = error_clone.clone();
             let completed_inner = completed_clone.clone();
-            let html_for_nav = html_owned.clone();
 
             // Create the controller
             log_debug("Creating controller handler");
@@ -176,36 +338,68 @@ fn init_webview2_sync(hwnd: HWND, html: &str) -> windows::core::Result<()> {
                                 // Zoom control left enabled for accessibility (Ctrl+scroll)
                             }
 
-                            // Navigate to HTML directly
-                            let html_wide = pwstr_from_str(&html_for_nav);
-                            let _ = webview.NavigateToString(PCWSTR(html_wide.as_ptr()));
+                            show_page(&webview, &session.page);
+                            session.watch();
 
-                            // Add message handler for Ctrl+click links and ESC to close
+                            // Add message handler for links, history, ESC to close and remote content
                             let parent_hwnd = hwnd;
+                            let session = Rc::new(RefCell::new(session));
+                            SESSIONS.with(|s| s.borrow_mut().insert(hwnd.0 as isize, session.clone()));
                             let handler = webview2_com::WebMessageReceivedEventHandler::create(
-                                Box::new(move |_webview, args| {
+                                Box::new(move |webview, args| {
+                                    // Busy with an earlier message, like asking about a link
+                                    let Ok(mut session) = session.try_borrow_mut() else {
+                                        return Ok(());
+                                    };
                                     if let Some(args) = args {
                                         let mut message_ptr: windows::core::PWSTR = windows::core::PWSTR::null();
                                         if args.WebMessageAsJson(&mut message_ptr).is_ok() && !message_ptr.is_null() {
                                             let msg_str = message_ptr.to_string().unwrap_or_default();
                                             windows::Win32::System::Com::CoTaskMemFree(Some(message_ptr.0 as *const _));
 
-                                            if msg_str.contains("\"close\"") {
-                                                // Send WM_CLOSE to parent (TC lister window)
-                                                if let Ok(parent) = GetParent(parent_hwnd) {
-                                                    if !parent.is_invalid() {
-                                                        let _ = PostMessageW(Some(parent), WM_CLOSE, WPARAM(0), LPARAM(0));
+                                            match PageMessage::parse(&msg_str) {
+                                                Ok(PageMessage::Close) => {
+                                                    // Send WM_CLOSE to parent (TC lister window)
+                                                    if let Ok(parent) = GetParent(parent_hwnd) {
+                                                        if !parent.is_invalid() {
+                                                            let _ = PostMessageW(Some(parent), WM_CLOSE, WPARAM(0), LPARAM(0));
+                                                        }
+                                                    }
+                                                }
+                                                Ok(PageMessage::LoadRemote) => {
+                                                    if let Some(webview) = webview {
+                                                        session.load_remote(&webview);
+                                                    }
+                                                }
+                                                Ok(PageMessage::FollowLink { url }) => {
+                                                    if let Some(webview) = webview {
+                                                        session.follow_link(&webview, &url, false);
                                                     }
                                                 }
-                                            } else if msg_str.contains("openLink") {
-                                                if let Some(start) = msg_str.find("\"url\":\"") {
-                                                    let url_start = start + 7;
-                                                    if let Some(end) = msg_str[url_start..].find('"') {
-                                                        let url = &msg_str[url_start..url_start + end];
-                                                        // Open URL in default browser
-                                                        open_url_in_browser(url);
+                                                Ok(PageMessage::OpenLink { url }) => {
+                                                    // Ctrl+click opens outside Total Commander
+                                                    if let Some(webview) = webview {
+                                                        session.follow_link(&webview, &url, true);
                                                     }
                                                 }
+                                                Ok(PageMessage::Ready) => {
+                                                    if let (Some(webview), Some(position)) = (webview, session.pending.take()) {
+                                                        host::post_to(&webview, &position.message());
+                                                    }
+                                                }
+                                                Ok(PageMessage::Scroll { y }) => session.scroll = y,
+                                                // A #section link: Back returns to where the reader was
+                                                Ok(PageMessage::Anchor) => {
+                                                    let current = session.location();
+                                                    session.history.visit(current);
+                                                }
+                                                Ok(message @ (PageMessage::Back | PageMessage::Forward)) => {
+                                                    if let Some(webview) = webview {
+                                                        session.go_through_history(&webview, message == PageMessage::Forward);
+                                                    }
+                                                }
+                                                Ok(_) => {}
+                                                Err(e) => log_debug(&format!("Ignoring message from the page: {}", e)),
                                             }
                                         }
                                     }
@@ -342,6 +536,13 @@ fn init_webview2_sync(hwnd: HWND, html: &str) -> windows::core::Result<()> {
 }
 
 pub fn close_window(hwnd: HWND) {
+    // Stop watching the files
+    if let Some(session) = SESSIONS.with(|s| s.borrow_mut().remove(&(hwnd.0 as isize)))
+        && let Ok(mut session) = session.try_borrow_mut()
+    {
+        session.watcher = None;
+    }
+
     // Remove and close the controller
     CONTROLLERS.with(|c| {
         if let Some(controller) = c.borrow_mut().remove(&(hwnd.0 as isize)) {
@@ -353,17 +554,35 @@ pub fn close_window(hwnd: HWND) {
     let _ = unsafe { DestroyWindow(hwnd) };
 }
 
-pub fn execute_script(hwnd: HWND, script: &str) {
+/// Send a message to the page in the viewer window `hwnd`
+pub fn post_message(hwnd: HWND, message: &HostMessage) {
     CONTROLLERS.with(|c| {
         if let Some(controller) = c.borrow().get(&(hwnd.0 as isize)) {
             if let Ok(webview) = unsafe { controller.CoreWebView2() } {
-                let script_wide = pwstr_from_str(script);
-                let _ = unsafe { webview.ExecuteScript(PCWSTR(script_wide.as_ptr()), None) };
+                host::post_to(&webview, message);
             }
         }
     });
 }
 
+/// Reload the page of window `hwnd` after its files changed. A window busy
+/// with the reader, like asking about a link, tries again a little later.
+fn reload(hwnd: HWND) {
+    let session = SESSIONS.with(|s| s.borrow().get(&(hwnd.0 as isize)).cloned());
+    let webview = CONTROLLERS.with(|c| {
+        c.borrow().get(&(hwnd.0 as isize)).and_then(|controller| unsafe { controller.CoreWebView2() }.ok())
+    });
+    let (Some(session), Some(webview)) = (session, webview) else {
+        return;
+    };
+    match session.try_borrow_mut() {
+        Ok(mut session) => session.reload(&webview),
+        Err(_) => unsafe {
+            SetTimer(Some(hwnd), RELOAD_TIMER, 500, None);
+        },
+    }
+}
+
 fn resize_webview(hwnd: HWND) {
     CONTROLLERS.with(|c| {
         if let Some(controller) = c.borrow().get(&(hwnd.0 as isize)) {
@@ -387,24 +606,18 @@ unsafe extern "system" fn window_proc(
             resize_webview(hwnd);
             LRESULT(0)
         }
+        WM_FILE_CHANGED => {
+            reload(hwnd);
+            LRESULT(0)
+        }
+        WM_TIMER if wparam.0 == RELOAD_TIMER => {
+            let _ = unsafe { KillTimer(Some(hwnd), RELOAD_TIMER) };
+            reload(hwnd);
+            LRESULT(0)
+        }
         WM_DESTROY => {
             LRESULT(0)
         }
         _ => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
     }
 }
-
-fn open_url_in_browser(url: &str) {
-    let url_wide = U16CString::from_str(url).unwrap_or_default();
-    let open_wide = U16CString::from_str("open").unwrap_or_default();
-    unsafe {
-        ShellExecuteW(
-            None,
-            PCWSTR(open_wide.as_ptr()),
-            PCWSTR(url_wide.as_ptr()),
-            None,
-            None,
-            SW_SHOWNORMAL,
-        );
-    }
-}
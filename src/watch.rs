@@ -0,0 +1,142 @@
+//! Live reload: notices when a document, or a local file it shows, changes on
+//! disk. Files are polled, which also sees editors that save by writing a new
+//! file and renaming it over the old one.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// How often the files are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Size and modification time of each watched file, `None` while it is missing
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot(Vec<Option<(SystemTime, u64)>>);
+
+impl Snapshot {
+    fn take(paths: &[PathBuf]) -> Self {
+        Snapshot(
+            paths
+                .iter()
+                .map(|path| {
+                    let metadata = std::fs::metadata(path).ok()?;
+                    Some((metadata.modified().ok()?, metadata.len()))
+                })
+                .collect(),
+        )
+    }
+
+    /// The document, the first file, is there. It is briefly missing while an
+    /// editor replaces it.
+    fn complete(&self) -> bool {
+        self.0.first().is_some_and(Option::is_some)
+    }
+}
+
+/// Decides when a series of snapshots is a change worth a reload: the files
+/// must differ from the last reload and then stay the same for one poll, so
+/// that a save in several steps reloads once.
+#[derive(Debug)]
+struct Changes {
+    known: Snapshot,
+    pending: Option<Snapshot>,
+}
+
+impl Changes {
+    fn new(known: Snapshot) -> Self {
+        Changes { known, pending: None }
+    }
+
+    /// Take in the next snapshot. Returns whether to reload.
+    fn poll(&mut self, now: Snapshot) -> bool {
+        if now == self.known {
+            self.pending = None;
+            return false;
+        }
+        if self.pending.as_ref() == Some(&now) && now.complete() {
+            self.known = now;
+            self.pending = None;
+            return true;
+        }
+        self.pending = Some(now);
+        false
+    }
+}
+
+/// Watches files on a background thread until dropped
+pub struct Watcher {
+    stop: Arc<AtomicBool>,
+}
+
+impl Watcher {
+    /// Watch `paths`, the document first. `on_change` runs on the watcher's
+    /// thread, so it should only post a message to the window.
+    pub fn start(paths: Vec<PathBuf>, on_change: impl Fn() + Send + 'static) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        std::thread::spawn(move || {
+            let mut changes = Changes::new(Snapshot::take(&paths));
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                if stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                if changes.poll(Snapshot::take(&paths)) {
+                    on_change();
+                }
+            }
+        });
+        Watcher { stop }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(files: &[Option<u64>]) -> Snapshot {
+        Snapshot(files.iter().map(|len| len.map(|len| (SystemTime::UNIX_EPOCH, len))).collect())
+    }
+
+    #[test]
+    fn test_changes() {
+        let mut changes = Changes::new(snapshot(&[Some(10), Some(5)]));
+        assert!(!changes.poll(snapshot(&[Some(10), Some(5)])));
+
+        // Written in two steps: one reload once the size settles
+        assert!(!changes.poll(snapshot(&[Some(0), Some(5)])));
+        assert!(!changes.poll(snapshot(&[Some(12), Some(5)])));
+        assert!(changes.poll(snapshot(&[Some(12), Some(5)])));
+        assert!(!changes.poll(snapshot(&[Some(12), Some(5)])));
+
+        // Replaced by renaming: missing for a moment, then back
+        assert!(!changes.poll(snapshot(&[None, Some(5)])));
+        assert!(!changes.poll(snapshot(&[None, Some(5)])));
+        assert!(!changes.poll(snapshot(&[Some(14), Some(5)])));
+        assert!(changes.poll(snapshot(&[Some(14), Some(5)])));
+
+        // An image changed
+        assert!(!changes.poll(snapshot(&[Some(14), Some(6)])));
+        assert!(changes.poll(snapshot(&[Some(14), Some(6)])));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let path = std::env::temp_dir().join(format!("mdview-watch-{}.md", std::process::id()));
+        let paths = [path.clone(), path.with_extension("missing")];
+        std::fs::write(&path, "# One").unwrap();
+        let before = Snapshot::take(&paths);
+        assert!(before.complete() && before.0[1].is_none());
+        std::fs::write(&path, "# One and two").unwrap();
+        assert_ne!(Snapshot::take(&paths), before);
+        std::fs::remove_file(&path).unwrap();
+        assert!(!Snapshot::take(&paths).complete());
+    }
+}